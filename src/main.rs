@@ -1,14 +1,12 @@
 use std::{thread::sleep, time::Duration};
 
-use crate::system::system::System;
-
-mod system;
+use hwmonitor::system::system::System;
 
 fn main() -> Result<(), String> {
     let system = System::builder().cpu().build()?;
 
     let binding = system.cpu.as_ref().unwrap();
-    let cpu = binding.get(0).unwrap();
+    let cpu = binding.first().unwrap();
 
     for core in cpu.cores() {
         println!("Core {} temp: {:?}", core.core_id, core.temperature());
@@ -1,39 +1,152 @@
 use std::sync::Arc;
 
+use raw_cpuid::CpuId;
+
 use crate::system::{
-    cpu::{backend::CpuBackend, group_affinity::GroupAffinity},
-    kernal_driver::KernelDriver,
+    cpu::{
+        backend::{
+            zen::{self, EnergyCounter, PState},
+            CpuBackend,
+        },
+        counters::CounterSample,
+        group_affinity::GroupAffinity,
+        vendor::get_family_model,
+    },
+    kernal_driver::{KernelDriver, MsrGuard, MsrRestoreLog},
+    pci::{PciAddress, PciDevice, VENDOR_AMD},
 };
 
+/// Tctl offsets reported by AMD for early Ryzen and Threadripper parts
+const TCTL_OFFSETS: &[(&str, f32)] = &[
+    ("AMD Ryzen 5 1600X", 20.0),
+    ("AMD Ryzen 7 1700X", 20.0),
+    ("AMD Ryzen 7 1800X", 20.0),
+    ("AMD Ryzen 7 2700X", 10.0),
+    ("AMD Ryzen Threadripper 19", 27.0),
+    ("AMD Ryzen Threadripper 29", 27.0),
+];
+
 #[derive(Debug)]
 pub struct AmdBackend {
     driver: Arc<KernelDriver>,
+    family: u8,
+    model: u8,
+    cppc_supported: bool,
+    irperf_supported: bool,
+    /// Bridge that exposes the SMN index/data registers
+    root_complex: Option<PciAddress>,
+    tctl_offset: f32,
+    package_energy: EnergyCounter,
 }
 
 impl CpuBackend for AmdBackend {
-    fn read_package_temp(&self, affinity: &GroupAffinity) -> Result<f32, String> {
-        Err("Not implmented".into())
+    fn read_package_temp(&self, _affinity: &GroupAffinity) -> Result<f32, String> {
+        if !self.is_zen() {
+            return Err(format!("Unsupported AMD family {:#x}", self.family));
+        }
+
+        let root = self.root_complex.ok_or("AMD root complex not found")?;
+        zen::read_tctl(&self.driver, root, self.tctl_offset)
     }
 
-    fn read_core_temp(&self, core_id: u32) -> Option<f32> {
+    fn read_core_temp(&self, _affinity: &GroupAffinity) -> Option<f32> {
         None
     }
 
-    fn read_thread_load(&self, thread_id: u32) -> Option<f32> {
+    fn read_thread_load(&self, _thread_id: u32) -> Option<f32> {
         None
     }
 
-    fn read_power(&self, package_id: u32) -> Option<f32> {
-        None
+    fn read_power(&self, affinity: &GroupAffinity) -> Option<f32> {
+        if !self.is_zen() {
+            return None;
+        }
+
+        self.package_energy
+            .sample(&self.driver, zen::MSR_PKG_ENERGY_STATUS, affinity)
     }
 
-    fn read_voltage(&self, core_id: u32) -> Option<f32> {
+    fn read_voltage(&self, _core_id: u32) -> Option<f32> {
         None
     }
+
+    fn read_pstates(&self, affinity: &GroupAffinity) -> Result<Vec<PState>, String> {
+        if !self.is_zen() {
+            return Err(format!("Unsupported AMD family {:#x}", self.family));
+        }
+
+        zen::read_pstates(&self.driver, affinity, self.family, self.model)
+    }
+
+    fn read_cppc_highest_perf(&self, affinity: &GroupAffinity) -> Result<u8, String> {
+        if !self.cppc_supported {
+            return Err("CPPC not supported".into());
+        }
+
+        zen::read_cppc_highest_perf(&self.driver, affinity)
+    }
+
+    fn enable_counters(
+        &self,
+        affinity: &GroupAffinity,
+        log: &Arc<MsrRestoreLog>,
+    ) -> Result<Vec<MsrGuard>, String> {
+        if !self.is_zen() || !self.irperf_supported {
+            return Err("IRPERF not supported".into());
+        }
+
+        zen::enable_counters(&self.driver, affinity, log)
+    }
+
+    fn read_counters(&self, affinity: &GroupAffinity) -> Result<CounterSample, String> {
+        if !self.is_zen() || !self.irperf_supported {
+            return Err("IRPERF not supported".into());
+        }
+
+        zen::read_counters(&self.driver, affinity)
+    }
+
+    fn host_bridge(&self) -> Option<PciAddress> {
+        self.root_complex
+    }
 }
 
 impl AmdBackend {
-    pub fn new(driver: Arc<KernelDriver>) -> Self {
-        Self { driver }
+    pub fn new(
+        driver: Arc<KernelDriver>,
+        brand: &str,
+        affinity: &GroupAffinity,
+        devices: &[PciDevice],
+    ) -> Self {
+        let (family, model) = get_family_model(&CpuId::new());
+
+        let tctl_offset = TCTL_OFFSETS
+            .iter()
+            .find(|(name, _)| brand.starts_with(name))
+            .map_or(0.0, |(_, offset)| *offset);
+
+        // Each package reads Tctl through the root complex of its own node
+        let root_complex = if family >= 0x17 {
+            zen::node_id(affinity)
+                .ok()
+                .and_then(|node| zen::find_root_complex(devices, VENDOR_AMD, node))
+        } else {
+            None
+        };
+
+        Self {
+            driver,
+            family,
+            model,
+            cppc_supported: zen::has_cppc(),
+            irperf_supported: zen::has_irperf(),
+            root_complex,
+            tctl_offset,
+            package_energy: EnergyCounter::default(),
+        }
+    }
+
+    fn is_zen(&self) -> bool {
+        self.family >= 0x17
     }
 }
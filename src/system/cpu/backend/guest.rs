@@ -0,0 +1,71 @@
+//! Backend used when running as a virtual machine guest. Thermal, power and
+//! frequency MSRs are trapped or faked by the hypervisor, so every sensor built
+//! on them is reported as unsupported. Counters that are already running in
+//! the virtual PMU can still be read, but they are never enabled through MSR
+//! writes; on Linux perf_event_open is used instead.
+
+use std::sync::Arc;
+
+use crate::system::{
+    cpu::{
+        backend::CpuBackend, counters::CounterSample, group_affinity::GroupAffinity,
+        hypervisor::Hypervisor,
+    },
+    kernal_driver::{MsrGuard, MsrRestoreLog},
+    pci::PciAddress,
+};
+
+pub struct GuestBackend {
+    inner: Arc<dyn CpuBackend + Send + Sync>,
+    hypervisor: Hypervisor,
+}
+
+impl CpuBackend for GuestBackend {
+    fn read_package_temp(&self, _affinity: &GroupAffinity) -> Result<f32, String> {
+        Err(format!(
+            "Package temperature not available under {}",
+            self.hypervisor
+        ))
+    }
+
+    fn read_core_temp(&self, _affinity: &GroupAffinity) -> Option<f32> {
+        None
+    }
+
+    fn read_thread_load(&self, _thread_id: u32) -> Option<f32> {
+        None
+    }
+
+    fn read_power(&self, _affinity: &GroupAffinity) -> Option<f32> {
+        None
+    }
+
+    fn read_voltage(&self, _core_id: u32) -> Option<f32> {
+        None
+    }
+
+    fn enable_counters(
+        &self,
+        _affinity: &GroupAffinity,
+        _log: &Arc<MsrRestoreLog>,
+    ) -> Result<Vec<MsrGuard>, String> {
+        Err(format!(
+            "Enabling performance counters not supported under {}",
+            self.hypervisor
+        ))
+    }
+
+    fn read_counters(&self, affinity: &GroupAffinity) -> Result<CounterSample, String> {
+        self.inner.read_counters(affinity)
+    }
+
+    fn host_bridge(&self) -> Option<PciAddress> {
+        self.inner.host_bridge()
+    }
+}
+
+impl GuestBackend {
+    pub fn new(inner: Arc<dyn CpuBackend + Send + Sync>, hypervisor: Hypervisor) -> Self {
+        Self { inner, hypervisor }
+    }
+}
@@ -0,0 +1,87 @@
+//! Intel Hardware P-states (HWP, marketed as Speed Shift).
+//! For more information see the Intel SDM, Vol. 3B, section 14.4.
+
+pub const IA32_PM_ENABLE: u32 = 0x770;
+pub const IA32_HWP_CAPABILITIES: u32 = 0x771;
+pub const IA32_HWP_REQUEST: u32 = 0x774;
+pub const IA32_HWP_STATUS: u32 = 0x777;
+
+/// Decoded IA32_HWP_CAPABILITIES. Values are abstract performance levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HwpCapabilities {
+    pub highest: u8,
+    pub guaranteed: u8,
+    pub most_efficient: u8,
+    pub lowest: u8,
+}
+
+impl HwpCapabilities {
+    pub fn from_raw(value: u64) -> Self {
+        Self {
+            highest: value as u8,
+            guaranteed: (value >> 8) as u8,
+            most_efficient: (value >> 16) as u8,
+            lowest: (value >> 24) as u8,
+        }
+    }
+}
+
+/// Decoded IA32_HWP_REQUEST
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HwpRequest {
+    pub minimum: u8,
+    pub maximum: u8,
+    /// 0 lets the hardware choose autonomously
+    pub desired: u8,
+    /// 0 is maximum performance, 255 is maximum energy saving.
+    /// `None` when the CPU does not support EPP.
+    pub energy_performance_preference: Option<u8>,
+    /// `None` when the hardware picks the window autonomously
+    pub activity_window_us: Option<u64>,
+    /// The package-level request (IA32_HWP_REQUEST_PKG) takes precedence
+    pub package_control: bool,
+}
+
+impl HwpRequest {
+    pub fn from_raw(value: u64, epp_supported: bool) -> Self {
+        // 7-bit mantissa and 3-bit base-10 exponent, in microseconds
+        let window = (value >> 32) & 0x3FF;
+        let mantissa = window & 0x7F;
+        let exponent = (window >> 7) as u32;
+
+        Self {
+            minimum: value as u8,
+            maximum: (value >> 8) as u8,
+            desired: (value >> 16) as u8,
+            energy_performance_preference: epp_supported.then_some((value >> 24) as u8),
+            activity_window_us: (window != 0).then(|| mantissa * 10u64.pow(exponent)),
+            package_control: value & (1 << 42) != 0,
+        }
+    }
+}
+
+/// Decoded IA32_HWP_STATUS. Both bits are sticky until cleared by software.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HwpStatus {
+    pub guaranteed_performance_changed: bool,
+    pub excursion_to_minimum: bool,
+}
+
+impl HwpStatus {
+    pub fn from_raw(value: u64) -> Self {
+        Self {
+            guaranteed_performance_changed: value & (1 << 0) != 0,
+            excursion_to_minimum: value & (1 << 2) != 0,
+        }
+    }
+}
+
+/// HWP configuration of a single logical processor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hwp {
+    /// IA32_PM_ENABLE bit 0. Once set it stays set until reset.
+    pub enabled: bool,
+    pub capabilities: HwpCapabilities,
+    pub request: HwpRequest,
+    pub status: HwpStatus,
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use raw_cpuid::CpuId;
+
+use crate::system::{
+    cpu::{
+        backend::{
+            zen::{self, EnergyCounter, PState},
+            CpuBackend,
+        },
+        counters::CounterSample,
+        group_affinity::GroupAffinity,
+        vendor::get_family_model,
+    },
+    kernal_driver::{KernelDriver, MsrGuard, MsrRestoreLog},
+    pci::{PciAddress, PciDevice, VENDOR_HYGON},
+};
+
+/// Hygon Dhyana (family 18h) is derived from Zen and shares its sensors
+#[derive(Debug)]
+pub struct HygonBackend {
+    driver: Arc<KernelDriver>,
+    family: u8,
+    model: u8,
+    cppc_supported: bool,
+    irperf_supported: bool,
+    /// Bridge that exposes the SMN index/data registers
+    root_complex: Option<PciAddress>,
+    package_energy: EnergyCounter,
+}
+
+impl CpuBackend for HygonBackend {
+    fn read_package_temp(&self, _affinity: &GroupAffinity) -> Result<f32, String> {
+        let root = self.root_complex.ok_or("Hygon root complex not found")?;
+        zen::read_tctl(&self.driver, root, 0.0)
+    }
+
+    fn read_core_temp(&self, _affinity: &GroupAffinity) -> Option<f32> {
+        None
+    }
+
+    fn read_thread_load(&self, _thread_id: u32) -> Option<f32> {
+        None
+    }
+
+    fn read_power(&self, affinity: &GroupAffinity) -> Option<f32> {
+        self.package_energy
+            .sample(&self.driver, zen::MSR_PKG_ENERGY_STATUS, affinity)
+    }
+
+    fn read_voltage(&self, _core_id: u32) -> Option<f32> {
+        None
+    }
+
+    fn read_pstates(&self, affinity: &GroupAffinity) -> Result<Vec<PState>, String> {
+        zen::read_pstates(&self.driver, affinity, self.family, self.model)
+    }
+
+    fn read_cppc_highest_perf(&self, affinity: &GroupAffinity) -> Result<u8, String> {
+        if !self.cppc_supported {
+            return Err("CPPC not supported".into());
+        }
+
+        zen::read_cppc_highest_perf(&self.driver, affinity)
+    }
+
+    fn enable_counters(
+        &self,
+        affinity: &GroupAffinity,
+        log: &Arc<MsrRestoreLog>,
+    ) -> Result<Vec<MsrGuard>, String> {
+        if !self.irperf_supported {
+            return Err("IRPERF not supported".into());
+        }
+
+        zen::enable_counters(&self.driver, affinity, log)
+    }
+
+    fn read_counters(&self, affinity: &GroupAffinity) -> Result<CounterSample, String> {
+        if !self.irperf_supported {
+            return Err("IRPERF not supported".into());
+        }
+
+        zen::read_counters(&self.driver, affinity)
+    }
+
+    fn host_bridge(&self) -> Option<PciAddress> {
+        self.root_complex
+    }
+}
+
+impl HygonBackend {
+    pub fn new(driver: Arc<KernelDriver>, affinity: &GroupAffinity, devices: &[PciDevice]) -> Self {
+        let (family, model) = get_family_model(&CpuId::new());
+        let root_complex = zen::node_id(affinity)
+            .ok()
+            .and_then(|node| zen::find_root_complex(devices, VENDOR_HYGON, node));
+
+        Self {
+            driver,
+            family,
+            model,
+            cppc_supported: zen::has_cppc(),
+            irperf_supported: zen::has_irperf(),
+            root_complex,
+            package_energy: EnergyCounter::default(),
+        }
+    }
+}
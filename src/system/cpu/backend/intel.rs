@@ -1,51 +1,205 @@
 use std::sync::Arc;
 
-use x86::msr::IA32_PACKAGE_THERM_STATUS;
+use std::time::Instant;
+
+use raw_cpuid::CpuId;
+use x86::msr::{
+    IA32_FIXED_CTR0, IA32_FIXED_CTR1, IA32_FIXED_CTR2, IA32_FIXED_CTR_CTRL,
+    IA32_PACKAGE_THERM_STATUS, IA32_PERF_GLOBAL_CTRL, IA32_THERM_STATUS, MSR_TEMPERATURE_TARGET,
+};
 
 use crate::system::{
-    cpu::{backend::CpuBackend, group_affinity::GroupAffinity},
-    kernal_driver::KernelDriver,
+    cpu::{
+        backend::{
+            hwp::{
+                Hwp, HwpCapabilities, HwpRequest, HwpStatus, IA32_HWP_CAPABILITIES,
+                IA32_HWP_REQUEST, IA32_HWP_STATUS, IA32_PM_ENABLE,
+            },
+            oc_mailbox::{OcMailbox, VoltagePlane},
+            psys::{PlatformEnergyCounter, PlatformPower},
+            uncore::{self, UncoreFrequency},
+            CpuBackend,
+        },
+        counters::CounterSample,
+        group_affinity::GroupAffinity,
+        msr::{join, TemperatureTarget, ThermStatus},
+    },
+    kernal_driver::{KernelDriver, MsrGuard, MsrRestoreLog},
+    pci::{host_bridge, PciAddress, PciDevice, VENDOR_INTEL},
 };
 
 #[derive(Debug)]
 pub struct IntelBackend {
     driver: Arc<KernelDriver>,
+    hwp_supported: bool,
+    epp_supported: bool,
+    /// Bit width of the fixed-function counters, if all three are present
+    fixed_counter_width: Option<u32>,
+    platform_energy: PlatformEnergyCounter,
+    oc_mailbox: OcMailbox,
+    /// Host bridge that holds MCHBAR
+    imc: Option<PciAddress>,
 }
 
+/// Used when MSR_TEMPERATURE_TARGET cannot be read
+const DEFAULT_TJ_MAX: u8 = 100;
+
+/// Count in ring 0 and ring 3 for fixed counters 0-2
+const FIXED_CTR_CTRL_ENABLE: u64 = 0x333;
+/// Global enable bits of fixed counters 0-2
+const PERF_GLOBAL_CTRL_FIXED: u64 = 0x7 << 32;
+
 impl CpuBackend for IntelBackend {
     fn read_package_temp(&self, affinity: &GroupAffinity) -> Result<f32, String> {
-        let (eax, _) = self.driver.rdmsr_tx(IA32_PACKAGE_THERM_STATUS, affinity)?;
+        let (eax, edx) = self.driver.rdmsr_tx(IA32_PACKAGE_THERM_STATUS, affinity)?;
 
-        if (eax & 0x80000000) != 0 {
-            let delta_t = ((eax & 0x007F0000) >> 16) as f32;
-            let tj_max = 100f32;
-            let t_slope = 1f32;
+        ThermStatus::from_raw(join(eax, edx))
+            .temperature(self.tj_max(affinity))
+            .ok_or_else(|| "Unknown value".into())
+    }
 
-            return Ok(tj_max - t_slope * delta_t);
-        }
+    fn read_core_temp(&self, affinity: &GroupAffinity) -> Option<f32> {
+        let (eax, edx) = self.driver.rdmsr_tx(IA32_THERM_STATUS, affinity).ok()?;
 
-        Err("Unknown value".into())
+        ThermStatus::from_raw(join(eax, edx)).temperature(self.tj_max(affinity))
     }
 
-    fn read_core_temp(&self, core_id: u32) -> Option<f32> {
+    fn read_thread_load(&self, _thread_id: u32) -> Option<f32> {
         None
     }
 
-    fn read_thread_load(&self, thread_id: u32) -> Option<f32> {
+    fn read_power(&self, _affinity: &GroupAffinity) -> Option<f32> {
         None
     }
 
-    fn read_power(&self, package_id: u32) -> Option<f32> {
+    fn read_voltage(&self, _core_id: u32) -> Option<f32> {
         None
     }
 
-    fn read_voltage(&self, core_id: u32) -> Option<f32> {
-        None
+    fn read_hwp(&self, affinity: &GroupAffinity) -> Result<Hwp, String> {
+        if !self.hwp_supported {
+            return Err("HWP not supported".into());
+        }
+
+        let read = |index| {
+            self.driver
+                .rdmsr_tx(index, affinity)
+                .map(|(eax, edx)| join(eax, edx))
+        };
+
+        Ok(Hwp {
+            enabled: read(IA32_PM_ENABLE)? & 1 != 0,
+            capabilities: HwpCapabilities::from_raw(read(IA32_HWP_CAPABILITIES)?),
+            request: HwpRequest::from_raw(read(IA32_HWP_REQUEST)?, self.epp_supported),
+            status: HwpStatus::from_raw(read(IA32_HWP_STATUS)?),
+        })
+    }
+
+    fn enable_counters(
+        &self,
+        affinity: &GroupAffinity,
+        log: &Arc<MsrRestoreLog>,
+    ) -> Result<Vec<MsrGuard>, String> {
+        if self.fixed_counter_width.is_none() {
+            return Err("Fixed-function counters not supported".into());
+        }
+
+        let mut guards = Vec::new();
+        for (index, bits) in [
+            (IA32_FIXED_CTR_CTRL, FIXED_CTR_CTRL_ENABLE),
+            (IA32_PERF_GLOBAL_CTRL, PERF_GLOBAL_CTRL_FIXED),
+        ] {
+            let (eax, edx) = self.driver.rdmsr_tx(index, affinity)?;
+            let value = join(eax, edx);
+
+            if value & bits != bits {
+                guards.push(MsrGuard::write_unchecked(
+                    &self.driver,
+                    log,
+                    index,
+                    value | bits,
+                    affinity,
+                )?);
+            }
+        }
+
+        Ok(guards)
+    }
+
+    fn read_counters(&self, affinity: &GroupAffinity) -> Result<CounterSample, String> {
+        let width = self
+            .fixed_counter_width
+            .ok_or("Fixed-function counters not supported")?;
+
+        let values = self.driver.rdmsr_on(
+            affinity,
+            &[IA32_FIXED_CTR0, IA32_FIXED_CTR1, IA32_FIXED_CTR2],
+        )?;
+        let timestamp = Instant::now();
+
+        let mut values = values
+            .into_iter()
+            .map(|v| v.map(|(eax, edx)| join(eax, edx)));
+
+        Ok(CounterSample {
+            instructions: values.next().ok_or("Missing counter")??,
+            cycles: values.next().ok_or("Missing counter")??,
+            ref_cycles: values.next().ok_or("Missing counter")??,
+            width,
+            timestamp,
+        })
+    }
+
+    fn read_voltage_offset(
+        &self,
+        affinity: &GroupAffinity,
+        plane: VoltagePlane,
+    ) -> Result<f32, String> {
+        self.oc_mailbox
+            .read_voltage_offset(&self.driver, affinity, plane)
+    }
+
+    fn read_platform_power(&self, affinity: &GroupAffinity) -> Result<PlatformPower, String> {
+        self.platform_energy.sample(&self.driver, affinity)
+    }
+
+    fn read_uncore_frequency(&self, affinity: &GroupAffinity) -> Result<UncoreFrequency, String> {
+        uncore::read_uncore_frequency(&self.driver, affinity)
+    }
+
+    fn host_bridge(&self) -> Option<PciAddress> {
+        self.imc
     }
 }
 
 impl IntelBackend {
-    pub fn new(driver: Arc<KernelDriver>) -> Self {
-        Self { driver }
+    fn tj_max(&self, affinity: &GroupAffinity) -> u8 {
+        self.driver
+            .rdmsr_tx(MSR_TEMPERATURE_TARGET, affinity)
+            .map(|(eax, edx)| TemperatureTarget::from_raw(join(eax, edx)).tj_max)
+            .ok()
+            .filter(|&tj_max| tj_max != 0)
+            .unwrap_or(DEFAULT_TJ_MAX)
+    }
+
+    pub fn new(driver: Arc<KernelDriver>, devices: &[PciDevice]) -> Self {
+        let cpuid = CpuId::new();
+        let power = cpuid.get_thermal_power_info();
+        let fixed_counter_width = cpuid
+            .get_performance_monitoring_info()
+            .filter(|pm| pm.version_id() >= 2 && pm.fixed_function_counters() >= 3)
+            .map(|pm| pm.fixed_function_counters_bit_width() as u32);
+
+        Self {
+            driver,
+            hwp_supported: power.as_ref().is_some_and(|p| p.has_hwp()),
+            epp_supported: power
+                .as_ref()
+                .is_some_and(|p| p.has_hwp_energy_performance_preference()),
+            fixed_counter_width,
+            platform_energy: PlatformEnergyCounter::default(),
+            oc_mailbox: OcMailbox::default(),
+            imc: host_bridge(devices, VENDOR_INTEL).map(|d| d.address),
+        }
     }
 }
@@ -1,13 +1,83 @@
-use crate::system::cpu::group_affinity::GroupAffinity;
+use std::sync::Arc;
+
+use crate::system::{
+    cpu::{
+        backend::{
+            hwp::Hwp, oc_mailbox::VoltagePlane, psys::PlatformPower, uncore::UncoreFrequency,
+            zen::PState,
+        },
+        counters::CounterSample,
+        group_affinity::GroupAffinity,
+    },
+    kernal_driver::{MsrGuard, MsrRestoreLog},
+    pci::PciAddress,
+};
 
 pub mod amd;
+pub mod guest;
+pub mod hwp;
+pub mod hygon;
 pub mod intel;
+pub mod oc_mailbox;
+pub mod psys;
+pub mod uncore;
 pub mod unknown;
+pub mod zen;
+pub mod zhaoxin;
 
 pub trait CpuBackend {
     fn read_package_temp(&self, affinity: &GroupAffinity) -> Result<f32, String>;
-    fn read_core_temp(&self, core_id: u32) -> Option<f32>;
+    fn read_core_temp(&self, affinity: &GroupAffinity) -> Option<f32>;
     fn read_thread_load(&self, thread_id: u32) -> Option<f32>;
-    fn read_power(&self, package_id: u32) -> Option<f32>;
+    fn read_power(&self, affinity: &GroupAffinity) -> Option<f32>;
     fn read_voltage(&self, core_id: u32) -> Option<f32>;
+
+    fn read_hwp(&self, _affinity: &GroupAffinity) -> Result<Hwp, String> {
+        Err("HWP not supported".into())
+    }
+
+    fn read_pstates(&self, _affinity: &GroupAffinity) -> Result<Vec<PState>, String> {
+        Err("P-state table not supported".into())
+    }
+
+    fn read_cppc_highest_perf(&self, _affinity: &GroupAffinity) -> Result<u8, String> {
+        Err("CPPC not supported".into())
+    }
+
+    /// Start the fixed-function counters if firmware left them disabled.
+    /// The returned guards put the original control values back.
+    fn enable_counters(
+        &self,
+        _affinity: &GroupAffinity,
+        _log: &Arc<MsrRestoreLog>,
+    ) -> Result<Vec<MsrGuard>, String> {
+        Err("Performance counters not supported".into())
+    }
+
+    fn read_counters(&self, _affinity: &GroupAffinity) -> Result<CounterSample, String> {
+        Err("Performance counters not supported".into())
+    }
+
+    /// Voltage offset of a plane in millivolts
+    fn read_voltage_offset(
+        &self,
+        _affinity: &GroupAffinity,
+        _plane: VoltagePlane,
+    ) -> Result<f32, String> {
+        Err("Voltage offsets not supported".into())
+    }
+
+    fn read_platform_power(&self, _affinity: &GroupAffinity) -> Result<PlatformPower, String> {
+        Err("Platform power not supported".into())
+    }
+
+    fn read_uncore_frequency(&self, _affinity: &GroupAffinity) -> Result<UncoreFrequency, String> {
+        Err("Uncore frequency not supported".into())
+    }
+
+    /// Host bridge of the package: the AMD root complex of its node or the
+    /// Intel IMC
+    fn host_bridge(&self) -> Option<PciAddress> {
+        None
+    }
 }
@@ -0,0 +1,117 @@
+//! Intel overclocking mailbox (MSR 0x150), used here read-only to audit
+//! voltage offsets. For more information see the intel-undervolt project.
+
+use std::sync::Mutex;
+
+use crate::system::{
+    cpu::{group_affinity::GroupAffinity, msr::join},
+    kernal_driver::KernelDriver,
+};
+
+pub const MSR_OC_MAILBOX: u32 = 0x150;
+
+const MAILBOX_BUSY: u64 = 1 << 63;
+const CMD_READ_VOLTAGE_OFFSET: u64 = 0x10;
+/// Reads of the busy bit before a request is abandoned
+const MAILBOX_POLL_LIMIT: usize = 1000;
+
+/// Voltage planes addressed by the mailbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VoltagePlane {
+    Core,
+    Gpu,
+    Cache,
+    Uncore,
+    AnalogIo,
+}
+
+impl VoltagePlane {
+    pub const ALL: [VoltagePlane; 5] = [
+        VoltagePlane::Core,
+        VoltagePlane::Gpu,
+        VoltagePlane::Cache,
+        VoltagePlane::Uncore,
+        VoltagePlane::AnalogIo,
+    ];
+
+    fn index(self) -> u64 {
+        match self {
+            VoltagePlane::Core => 0,
+            VoltagePlane::Gpu => 1,
+            VoltagePlane::Cache => 2,
+            VoltagePlane::Uncore => 3,
+            VoltagePlane::AnalogIo => 4,
+        }
+    }
+}
+
+// Mailbox completion codes
+fn status_message(code: u8) -> String {
+    match code {
+        0x01 => "OC mailbox locked".into(),
+        0x02 => "Invalid voltage plane".into(),
+        0x05 => "Overclocking not supported".into(),
+        0x07 => "OC mailbox read failed".into(),
+        code => format!("OC mailbox error {:#x}", code),
+    }
+}
+
+/// The mailbox of one package. Requests are serialized because a command and
+/// its response share the same register.
+#[derive(Debug, Default)]
+pub struct OcMailbox {
+    lock: Mutex<()>,
+}
+
+impl OcMailbox {
+    /// Read the voltage offset of a plane in millivolts
+    pub fn read_voltage_offset(
+        &self,
+        driver: &KernelDriver,
+        affinity: &GroupAffinity,
+        plane: VoltagePlane,
+    ) -> Result<f32, String> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| "OC mailbox lock poisoned".to_string())?;
+
+        // A request left by someone else must complete first
+        wait_ready(driver, affinity)?;
+
+        let command = MAILBOX_BUSY | (plane.index() << 40) | (CMD_READ_VOLTAGE_OFFSET << 32);
+        driver.wrmsr_tx_unchecked(MSR_OC_MAILBOX, command, affinity)?;
+
+        let response = wait_ready(driver, affinity)?;
+
+        // Completion code, bits 39:32
+        let status = (response >> 32) as u8;
+        if status != 0 {
+            return Err(status_message(status));
+        }
+
+        // 11-bit two's complement in 1/1024 V, bits 31:21
+        let mut raw = ((response >> 21) & 0x7FF) as i32;
+        if raw & 0x400 != 0 {
+            raw -= 0x800;
+        }
+
+        Ok(raw as f32 * 1000.0 / 1024.0)
+    }
+}
+
+// Poll until the busy bit clears and return the register value
+fn wait_ready(driver: &KernelDriver, affinity: &GroupAffinity) -> Result<u64, String> {
+    for _ in 0..MAILBOX_POLL_LIMIT {
+        let (eax, edx) = driver.rdmsr_tx(MSR_OC_MAILBOX, affinity)?;
+        let value = join(eax, edx);
+
+        if value & MAILBOX_BUSY == 0 {
+            return Ok(value);
+        }
+
+        std::hint::spin_loop();
+    }
+
+    Err("OC mailbox busy".into())
+}
@@ -0,0 +1,84 @@
+//! Intel platform (PSys) energy counter. Covers the whole platform as seen by
+//! the power delivery, not only the package. For more information see the
+//! Linux intel_rapl driver.
+
+use std::{sync::Mutex, time::Instant};
+
+use x86::msr::MSR_RAPL_POWER_UNIT;
+
+use crate::system::{
+    cpu::{
+        group_affinity::GroupAffinity,
+        msr::{join, RaplPowerUnit},
+    },
+    kernal_driver::KernelDriver,
+};
+
+pub const MSR_PLATFORM_ENERGY_COUNTER: u32 = 0x64D;
+
+/// Platform power reading
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlatformPower {
+    /// Average power since the previous reading, `None` on the first one
+    pub watts: Option<f32>,
+    /// Energy consumed since the first reading
+    pub joules: f64,
+}
+
+#[derive(Debug, Default)]
+struct EnergyState {
+    last_raw: u32,
+    last_time: Option<Instant>,
+    joules: f64,
+}
+
+/// Accumulates the 32-bit platform energy counter across wraparounds
+#[derive(Debug, Default)]
+pub struct PlatformEnergyCounter {
+    state: Mutex<EnergyState>,
+}
+
+impl PlatformEnergyCounter {
+    pub fn sample(
+        &self,
+        driver: &KernelDriver,
+        affinity: &GroupAffinity,
+    ) -> Result<PlatformPower, String> {
+        let (unit_eax, unit_edx) = driver.rdmsr_tx(MSR_RAPL_POWER_UNIT, affinity)?;
+        let (energy, _) = driver.rdmsr_tx(MSR_PLATFORM_ENERGY_COUNTER, affinity)?;
+        let now = Instant::now();
+
+        let joules_per_tick = RaplPowerUnit::from_raw(join(unit_eax, unit_edx)).joules_per_unit;
+
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| "Platform energy state poisoned")?;
+
+        let Some(last_time) = state.last_time else {
+            // Parts without PSys reporting leave the counter at zero
+            if energy == 0 {
+                return Err("Platform energy counter not supported".into());
+            }
+
+            state.last_raw = energy;
+            state.last_time = Some(now);
+            return Ok(PlatformPower {
+                watts: None,
+                joules: 0.0,
+            });
+        };
+
+        let delta = energy.wrapping_sub(state.last_raw) as f64 * joules_per_tick;
+        let elapsed = now.duration_since(last_time).as_secs_f64();
+
+        state.last_raw = energy;
+        state.last_time = Some(now);
+        state.joules += delta;
+
+        Ok(PlatformPower {
+            watts: (elapsed > 0.0).then(|| (delta / elapsed) as f32),
+            joules: state.joules,
+        })
+    }
+}
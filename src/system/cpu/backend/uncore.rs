@@ -0,0 +1,38 @@
+//! Intel uncore (ring/LLC) clock. Memory-bound workloads are limited by this
+//! clock rather than the core clock.
+
+use crate::system::{cpu::group_affinity::GroupAffinity, kernal_driver::KernelDriver};
+
+pub const MSR_UNCORE_RATIO_LIMIT: u32 = 0x620;
+pub const MSR_UNCORE_PERF_STATUS: u32 = 0x621;
+
+/// Uncore ratios are in 100 MHz steps
+const UNCORE_RATIO_MHZ: u32 = 100;
+
+/// Current uncore frequency and the limits it may move between
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UncoreFrequency {
+    pub current_mhz: u32,
+    pub min_mhz: u32,
+    pub max_mhz: u32,
+}
+
+/// Read the uncore frequency of the package the affinity belongs to
+pub fn read_uncore_frequency(
+    driver: &KernelDriver,
+    affinity: &GroupAffinity,
+) -> Result<UncoreFrequency, String> {
+    let (status, _) = driver.rdmsr_tx(MSR_UNCORE_PERF_STATUS, affinity)?;
+    let (limit, _) = driver.rdmsr_tx(MSR_UNCORE_RATIO_LIMIT, affinity)?;
+
+    let current = status & 0x7F;
+    if current == 0 {
+        return Err("Uncore frequency not reported".into());
+    }
+
+    Ok(UncoreFrequency {
+        current_mhz: current * UNCORE_RATIO_MHZ,
+        min_mhz: ((limit >> 8) & 0x7F) * UNCORE_RATIO_MHZ,
+        max_mhz: (limit & 0x7F) * UNCORE_RATIO_MHZ,
+    })
+}
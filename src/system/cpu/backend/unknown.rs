@@ -7,27 +7,28 @@ use crate::system::{
 
 #[derive(Debug)]
 pub struct UnknownBackend {
+    #[allow(dead_code)]
     driver: Arc<KernelDriver>,
 }
 
 impl CpuBackend for UnknownBackend {
-    fn read_package_temp(&self, affinity: &GroupAffinity) -> Result<f32, String> {
+    fn read_package_temp(&self, _affinity: &GroupAffinity) -> Result<f32, String> {
         Err("Not implmented".into())
     }
 
-    fn read_core_temp(&self, core_id: u32) -> Option<f32> {
+    fn read_core_temp(&self, _affinity: &GroupAffinity) -> Option<f32> {
         None
     }
 
-    fn read_thread_load(&self, thread_id: u32) -> Option<f32> {
+    fn read_thread_load(&self, _thread_id: u32) -> Option<f32> {
         None
     }
 
-    fn read_power(&self, package_id: u32) -> Option<f32> {
+    fn read_power(&self, _affinity: &GroupAffinity) -> Option<f32> {
         None
     }
 
-    fn read_voltage(&self, core_id: u32) -> Option<f32> {
+    fn read_voltage(&self, _core_id: u32) -> Option<f32> {
         None
     }
 }
@@ -0,0 +1,267 @@
+//! Shared sensor logic for Zen-derived cores (AMD family 17h+ and Hygon Dhyana).
+//! For more information see the Linux k10temp and rapl drivers.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use raw_cpuid::{CpuId, CpuIdReader, CpuIdReaderNative};
+use x86::msr::{IA32_APERF, IA32_MPERF};
+
+use crate::system::{
+    cpu::{
+        counters::CounterSample,
+        group_affinity::{with_affinity, GroupAffinity},
+        msr::{join, RaplPowerUnit},
+    },
+    kernal_driver::{KernelDriver, MsrGuard, MsrRestoreLog},
+    pci::{host_bridges, PciAddress, PciConfig, PciDevice},
+};
+
+pub const MSR_RAPL_POWER_UNIT: u32 = 0xC001_0299;
+pub const MSR_CORE_ENERGY_STATUS: u32 = 0xC001_029A;
+pub const MSR_PKG_ENERGY_STATUS: u32 = 0xC001_029B;
+
+/// SMN index/data registers on the root complex
+const SMN_INDEX_REGISTER: u16 = 0x60;
+const SMN_DATA_REGISTER: u16 = 0x64;
+
+/// SMN address of the reported temperature control register
+const SMN_REPORTED_TEMP_CTRL: u32 = 0x0005_9800;
+const CUR_TEMP_RANGE_SEL: u32 = 1 << 19;
+
+/// Data fabric devices start at 00:18.0, one device per node
+const DF_FIRST_DEVICE: u8 = 0x18;
+const MAX_NODES: u8 = 8;
+
+/// The SMN index/data pair is shared by every reader of a root complex
+static SMN_LOCK: Mutex<()> = Mutex::new(());
+
+/// Find the root complex that serves a node. Every node owns an equal share of
+/// the root complexes, ordered by bus, and its SMN window is the first of them.
+pub fn find_root_complex(devices: &[PciDevice], vendor_id: u16, node: u8) -> Option<PciAddress> {
+    // Root complexes sit at device 0, function 0 of their bus
+    let mut roots: Vec<PciAddress> = host_bridges(devices, vendor_id)
+        .map(|d| d.address)
+        .filter(|a| a.device == 0 && a.function == 0)
+        .collect();
+    roots.sort_by_key(|a| a.bus);
+
+    let nodes = devices
+        .iter()
+        .filter(|d| d.vendor_id == vendor_id && d.address.bus == 0 && d.address.function == 0)
+        .filter(|d| (DF_FIRST_DEVICE..DF_FIRST_DEVICE + MAX_NODES).contains(&d.address.device))
+        .count()
+        .max(1);
+    let roots_per_node = (roots.len() / nodes).max(1);
+
+    roots.get(node as usize * roots_per_node).copied()
+}
+
+/// Node id of the logical processors in `affinity`, CPUID Fn8000_001E ECX[7:0]
+pub fn node_id(affinity: &GroupAffinity) -> Result<u8, String> {
+    with_affinity(affinity, || {
+        CpuId::new()
+            .get_processor_topology_info()
+            .map(|info| info.node_id())
+            .ok_or_else(|| "CPUID Fn8000_001E not supported".to_string())
+    })
+}
+
+/// Read a register from the System Management Network through the root complex
+pub fn read_smn(driver: &KernelDriver, root: PciAddress, address: u32) -> Result<u32, String> {
+    let _guard = SMN_LOCK
+        .lock()
+        .map_err(|_| "SMN lock poisoned".to_string())?;
+
+    driver.write_u32(root, SMN_INDEX_REGISTER, address)?;
+    driver.read_u32(root, SMN_DATA_REGISTER)
+}
+
+/// Read Tctl in degrees Celsius. `offset` is subtracted for parts that report
+/// Tctl above the real die temperature.
+pub fn read_tctl(driver: &KernelDriver, root: PciAddress, offset: f32) -> Result<f32, String> {
+    let value = read_smn(driver, root, SMN_REPORTED_TEMP_CTRL)?;
+
+    let mut temp = ((value >> 21) & 0x7FF) as f32 * 0.125;
+    if value & CUR_TEMP_RANGE_SEL != 0 {
+        temp -= 49.0;
+    }
+
+    Ok(temp - offset)
+}
+
+/// Tracks a 32-bit RAPL energy counter between reads to derive power
+#[derive(Debug, Default)]
+pub struct EnergyCounter {
+    last: Mutex<Option<(u32, Instant)>>,
+}
+
+impl EnergyCounter {
+    /// Read the counter and return the average power in watts since the last call.
+    /// The first call only records a sample and returns `None`.
+    pub fn sample(
+        &self,
+        driver: &KernelDriver,
+        index: u32,
+        affinity: &GroupAffinity,
+    ) -> Option<f32> {
+        let (unit_eax, unit_edx) = driver.rdmsr_tx(MSR_RAPL_POWER_UNIT, affinity).ok()?;
+        let (energy, _) = driver.rdmsr_tx(index, affinity).ok()?;
+        let now = Instant::now();
+
+        let joules_per_tick = RaplPowerUnit::from_raw(join(unit_eax, unit_edx)).joules_per_unit;
+
+        let mut last = self.last.lock().ok()?;
+        let previous = last.replace((energy, now));
+
+        let (prev_energy, prev_time) = previous?;
+        let elapsed = now.duration_since(prev_time).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+
+        let ticks = energy.wrapping_sub(prev_energy);
+        Some((ticks as f64 * joules_per_tick / elapsed) as f32)
+    }
+}
+
+/// First of the eight P-state definition MSRs (0xC0010064..=0xC001006B)
+pub const MSR_PSTATE_DEF_BASE: u32 = 0xC001_0064;
+pub const PSTATE_COUNT: u32 = 8;
+
+/// CPPC capability register with the per-core performance levels
+pub const MSR_CPPC_CAP1: u32 = 0xC001_02B0;
+
+/// A single entry of the P-state definition table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PState {
+    pub index: u32,
+    pub enabled: bool,
+    pub frequency_mhz: f32,
+    pub voltage: f32,
+}
+
+impl PState {
+    /// Decode a P-state definition MSR for the given family and model
+    pub fn from_raw(index: u32, value: u64, family: u8, model: u8) -> Self {
+        let frequency_mhz = if family >= 0x1A {
+            // CpuFid[11:0] in 5 MHz steps
+            (value & 0xFFF) as f32 * 5.0
+        } else {
+            // CoreCOF = CpuFid / CpuDfsId * 200 MHz
+            let fid = (value & 0xFF) as f32;
+            let dfs_id = ((value >> 8) & 0x3F) as f32;
+            if dfs_id == 0.0 {
+                0.0
+            } else {
+                fid / dfs_id * 200.0
+            }
+        };
+
+        let vid = (value >> 14) & 0xFF;
+        let voltage = if family >= 0x1A || (family == 0x19 && model >= 0x10) {
+            // SVI3: 9-bit VID with the high bit in CpuVid[8]
+            let vid = vid | (((value >> 32) & 1) << 8);
+            0.245 + vid as f32 * 0.005
+        } else {
+            // SVI2
+            1.55 - vid as f32 * 0.00625
+        };
+
+        Self {
+            index,
+            enabled: value & (1 << 63) != 0,
+            frequency_mhz,
+            voltage,
+        }
+    }
+}
+
+/// Read the complete P-state definition table
+pub fn read_pstates(
+    driver: &KernelDriver,
+    affinity: &GroupAffinity,
+    family: u8,
+    model: u8,
+) -> Result<Vec<PState>, String> {
+    (0..PSTATE_COUNT)
+        .map(|index| {
+            let (eax, edx) = driver.rdmsr_tx(MSR_PSTATE_DEF_BASE + index, affinity)?;
+            let value = join(eax, edx);
+
+            Ok(PState::from_raw(index, value, family, model))
+        })
+        .collect()
+}
+
+/// CPUID Fn8000_0008 EBX[27] advertises the CPPC MSRs. raw-cpuid does not
+/// decode this bit, so it is read from the leaf once the leaf is known to exist.
+pub fn has_cppc() -> bool {
+    CpuId::new().get_processor_capacity_feature_info().is_some()
+        && CpuIdReaderNative.cpuid1(0x8000_0008).ebx & (1 << 27) != 0
+}
+
+/// Read the highest performance level of a core. Firmware raises this value
+/// on the cores it prefers, so higher means a better core.
+pub fn read_cppc_highest_perf(
+    driver: &KernelDriver,
+    affinity: &GroupAffinity,
+) -> Result<u8, String> {
+    let (eax, _) = driver.rdmsr_tx(MSR_CPPC_CAP1, affinity)?;
+
+    Ok((eax >> 24) as u8)
+}
+
+/// Hardware configuration register, bit 30 enables IRPERF
+pub const MSR_HWCR: u32 = 0xC001_0015;
+const HWCR_IRPERF_EN: u64 = 1 << 30;
+
+/// Instructions retired counter
+pub const MSR_IRPERF: u32 = 0xC000_00E9;
+
+/// CPUID Fn8000_0008 EBX[1] advertises the IRPERF counter
+pub fn has_irperf() -> bool {
+    CpuId::new()
+        .get_processor_capacity_feature_info()
+        .is_some_and(|info| info.has_inst_ret_cntr_msr())
+}
+
+/// Turn on IRPERF. APERF and MPERF always run.
+pub fn enable_counters(
+    driver: &Arc<KernelDriver>,
+    affinity: &GroupAffinity,
+    log: &Arc<MsrRestoreLog>,
+) -> Result<Vec<MsrGuard>, String> {
+    let (eax, edx) = driver.rdmsr_tx(MSR_HWCR, affinity)?;
+    let value = join(eax, edx);
+
+    if value & HWCR_IRPERF_EN != 0 {
+        return Ok(Vec::new());
+    }
+
+    let guard = MsrGuard::write_unchecked(driver, log, MSR_HWCR, value | HWCR_IRPERF_EN, affinity)?;
+    Ok(vec![guard])
+}
+
+/// Read IRPERF, APERF and MPERF with a single affinity switch
+pub fn read_counters(
+    driver: &KernelDriver,
+    affinity: &GroupAffinity,
+) -> Result<CounterSample, String> {
+    let values = driver.rdmsr_on(affinity, &[MSR_IRPERF, IA32_APERF, IA32_MPERF])?;
+    let timestamp = Instant::now();
+
+    let mut values = values
+        .into_iter()
+        .map(|v| v.map(|(eax, edx)| join(eax, edx)));
+
+    Ok(CounterSample {
+        instructions: values.next().ok_or("Missing counter")??,
+        cycles: values.next().ok_or("Missing counter")??,
+        ref_cycles: values.next().ok_or("Missing counter")??,
+        width: 64,
+        timestamp,
+    })
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use raw_cpuid::CpuId;
+
+use crate::system::{
+    cpu::{backend::CpuBackend, group_affinity::GroupAffinity, vendor::get_family_model},
+    kernal_driver::KernelDriver,
+};
+
+/// Per-core temperature MSRs. For more information see the Linux via-cputemp driver.
+const MSR_C7_TEMPERATURE: u32 = 0x1169;
+const MSR_NANO_TEMPERATURE: u32 = 0x1423;
+
+/// Backend for Centaur (VIA) and Zhaoxin CPUs, which share the Centaur lineage
+#[derive(Debug)]
+pub struct ZhaoxinBackend {
+    driver: Arc<KernelDriver>,
+    temp_msr: Option<u32>,
+}
+
+impl CpuBackend for ZhaoxinBackend {
+    fn read_package_temp(&self, _affinity: &GroupAffinity) -> Result<f32, String> {
+        Err("Package temperature not supported".into())
+    }
+
+    fn read_core_temp(&self, affinity: &GroupAffinity) -> Option<f32> {
+        let (eax, _) = self.driver.rdmsr_tx(self.temp_msr?, affinity).ok()?;
+
+        // The sensor reports whole degrees Celsius
+        Some((eax & 0x00FF_FFFF) as f32)
+    }
+
+    fn read_thread_load(&self, _thread_id: u32) -> Option<f32> {
+        None
+    }
+
+    fn read_power(&self, _affinity: &GroupAffinity) -> Option<f32> {
+        None
+    }
+
+    fn read_voltage(&self, _core_id: u32) -> Option<f32> {
+        None
+    }
+}
+
+impl ZhaoxinBackend {
+    pub fn new(driver: Arc<KernelDriver>) -> Self {
+        let temp_msr = match get_family_model(&CpuId::new()) {
+            // C7-A and C7-D
+            (0x6, 0xA) | (0x6, 0xD) => Some(MSR_C7_TEMPERATURE),
+            // Nano and the Zhaoxin families derived from it
+            (0x6, 0xF) | (0x7, _) => Some(MSR_NANO_TEMPERATURE),
+            _ => None,
+        };
+
+        Self { driver, temp_msr }
+    }
+}
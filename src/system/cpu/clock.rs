@@ -0,0 +1,90 @@
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use raw_cpuid::{CpuId, CpuIdReader};
+
+/// How long to sample the TSC when CPUID does not enumerate its frequency
+const CALIBRATION_PERIOD: Duration = Duration::from_millis(50);
+
+/// Where the TSC frequency came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TscSource {
+    /// CPUID leaf 0x15 (crystal clock ratio), possibly with the crystal from leaf 0x16
+    Cpuid,
+    /// Measured against the monotonic clock
+    Calibrated,
+}
+
+/// Reference clocks of a package
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clocks {
+    pub tsc_hz: u64,
+    pub tsc_source: TscSource,
+    /// The TSC ticks at a constant rate in all P-, C- and T-states
+    pub invariant_tsc: bool,
+    pub base_mhz: Option<u32>,
+    pub max_mhz: Option<u32>,
+    pub bus_mhz: Option<u32>,
+}
+
+/// Detect the reference clocks of the processor the current thread runs on
+pub fn detect_clocks() -> Clocks {
+    let cpuid = CpuId::new();
+
+    let frequency = cpuid.get_processor_frequency_info();
+    let non_zero = |mhz: u16| (mhz != 0).then_some(mhz as u32);
+
+    let base_mhz = frequency
+        .as_ref()
+        .and_then(|f| non_zero(f.processor_base_frequency()));
+    let max_mhz = frequency
+        .as_ref()
+        .and_then(|f| non_zero(f.processor_max_frequency()));
+    let bus_mhz = frequency.as_ref().and_then(|f| non_zero(f.bus_frequency()));
+
+    let invariant_tsc = cpuid
+        .get_advanced_power_mgmt_info()
+        .is_some_and(|apm| apm.has_invariant_tsc());
+
+    let (tsc_hz, tsc_source) = match cpuid_tsc_frequency(&cpuid, base_mhz) {
+        Some(hz) => (hz, TscSource::Cpuid),
+        None => (calibrate_tsc(), TscSource::Calibrated),
+    };
+
+    Clocks {
+        tsc_hz,
+        tsc_source,
+        invariant_tsc,
+        base_mhz,
+        max_mhz,
+        bus_mhz,
+    }
+}
+
+// TSC = crystal * EBX / EAX. Some parts enumerate the ratio but not the crystal,
+// in which case the TSC runs at the base frequency from leaf 0x16.
+fn cpuid_tsc_frequency<R: CpuIdReader>(cpuid: &CpuId<R>, base_mhz: Option<u32>) -> Option<u64> {
+    let info = cpuid.get_tsc_info()?;
+
+    if info.numerator() == 0 || info.denominator() == 0 {
+        return None;
+    }
+
+    info.tsc_frequency()
+        .or_else(|| base_mhz.map(|mhz| mhz as u64 * 1_000_000))
+}
+
+// Measure TSC ticks over a fixed wall-clock period
+fn calibrate_tsc() -> u64 {
+    let start = Instant::now();
+    let tsc_start = unsafe { x86::time::rdtsc() };
+
+    sleep(CALIBRATION_PERIOD);
+
+    let tsc_end = unsafe { x86::time::rdtsc() };
+    let elapsed = start.elapsed();
+
+    (tsc_end.wrapping_sub(tsc_start) as f64 / elapsed.as_secs_f64()) as u64
+}
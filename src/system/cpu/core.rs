@@ -1,6 +1,10 @@
 use std::sync::Arc;
 
-use crate::system::cpu::{backend::CpuBackend, thread::Thread};
+use crate::system::cpu::{
+    backend::CpuBackend,
+    group_affinity::{with_affinity, AffinityTarget, GroupAffinity},
+    thread::Thread,
+};
 
 pub struct Core {
     backend: Arc<dyn CpuBackend + Send + Sync>,
@@ -18,7 +22,45 @@ impl Core {
     }
 
     pub fn temperature(&self) -> Option<f32> {
-        self.backend.read_core_temp(self.core_id)
+        self.backend.read_core_temp(&self.online_thread()?.affinity)
+    }
+
+    /// A core is online while at least one of its threads is
+    pub fn is_online(&self) -> bool {
+        self.threads.iter().any(|t| t.is_online())
+    }
+
+    fn online_thread(&self) -> Option<&Thread> {
+        self.threads.iter().find(|t| t.is_online())
+    }
+
+    /// CPPC highest performance of this core. Higher values mark the cores the
+    /// firmware prefers for single-threaded work.
+    pub fn cppc_rank(&self) -> Result<u8, String> {
+        let thread = self
+            .online_thread()
+            .ok_or_else(|| format!("Core {} is offline", self.core_id))?;
+
+        self.backend.read_cppc_highest_perf(&thread.affinity)
+    }
+
+    /// Run a closure pinned to the first online thread of this core
+    pub fn run_on<F, R>(&self, f: F) -> Result<R, String>
+    where
+        F: FnOnce() -> Result<R, String>,
+    {
+        let thread = self
+            .online_thread()
+            .ok_or_else(|| format!("Core {} is offline", self.core_id))?;
+
+        with_affinity(&thread.affinity, f)
+    }
+}
+
+/// Jobs for a core run on its first online thread
+impl AffinityTarget for Core {
+    fn target_affinity(&self) -> Option<&GroupAffinity> {
+        self.online_thread().map(|t| &t.affinity)
     }
 }
 
@@ -0,0 +1,148 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[cfg(target_os = "linux")]
+use crate::system::cpu::perf_event::PerfCounters;
+#[cfg(windows)]
+use crate::system::kernal_driver::MsrGuard;
+use crate::system::{
+    cpu::{backend::CpuBackend, group_affinity::GroupAffinity},
+    kernal_driver::MsrRestoreLog,
+};
+
+/// Cumulative fixed-function counter values of a logical processor.
+///
+/// On Intel these are IA32_FIXED_CTR0-2, on AMD IRPERF, APERF and MPERF,
+/// and on Linux they may come from perf_event_open instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterSample {
+    pub instructions: u64,
+    /// Unhalted core cycles at the actual clock
+    pub cycles: u64,
+    /// Unhalted cycles at the reference (TSC) clock
+    pub ref_cycles: u64,
+    /// Counter width in bits, used to handle wrap-around
+    pub width: u32,
+    pub timestamp: Instant,
+}
+
+impl CounterSample {
+    /// Activity between an earlier sample and this one
+    pub fn since(&self, earlier: &CounterSample) -> CounterDelta {
+        let width = self.width.min(earlier.width);
+        let mask = if width >= 64 {
+            u64::MAX
+        } else {
+            (1u64 << width) - 1
+        };
+        let delta = |new: u64, old: u64| new.wrapping_sub(old) & mask;
+
+        CounterDelta {
+            instructions: delta(self.instructions, earlier.instructions),
+            cycles: delta(self.cycles, earlier.cycles),
+            ref_cycles: delta(self.ref_cycles, earlier.ref_cycles),
+            elapsed: self.timestamp.duration_since(earlier.timestamp),
+        }
+    }
+}
+
+/// Counter activity between two samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterDelta {
+    pub instructions: u64,
+    pub cycles: u64,
+    pub ref_cycles: u64,
+    pub elapsed: Duration,
+}
+
+impl CounterDelta {
+    /// Instructions per unhalted core cycle. `None` when the thread was idle.
+    pub fn ipc(&self) -> Option<f64> {
+        (self.cycles != 0).then(|| self.instructions as f64 / self.cycles as f64)
+    }
+
+    /// Average clock while not halted, relative to the reference clock.
+    /// Multiply by the TSC frequency to get the effective frequency.
+    pub fn clock_ratio(&self) -> Option<f64> {
+        (self.ref_cycles != 0).then(|| self.cycles as f64 / self.ref_cycles as f64)
+    }
+}
+
+/// Where a thread reads its counters from
+#[derive(Debug, Default)]
+enum CounterSource {
+    #[default]
+    Unopened,
+    #[cfg(target_os = "linux")]
+    Perf(PerfCounters),
+    /// The guards restore the counter controls once the state is dropped
+    #[cfg(windows)]
+    Msr { _guards: Vec<MsrGuard> },
+}
+
+/// Per-thread counter state: the open source and the previous sample
+#[derive(Debug, Default)]
+pub struct CounterState {
+    source: CounterSource,
+    last: Option<CounterSample>,
+}
+
+impl CounterState {
+    /// Read the cumulative counters, opening a source on first use.
+    /// On Linux they come from perf_event_open only, as the kernel owns the
+    /// counter control MSRs; on Windows the counters are enabled directly.
+    pub fn read(
+        &mut self,
+        backend: &(dyn CpuBackend + Send + Sync),
+        affinity: &GroupAffinity,
+        log: &Arc<MsrRestoreLog>,
+    ) -> Result<CounterSample, String> {
+        if let CounterSource::Unopened = self.source {
+            self.source = open_source(backend, affinity, log)?;
+        }
+
+        match &mut self.source {
+            #[cfg(target_os = "linux")]
+            CounterSource::Perf(perf) => perf.read(),
+            _ => backend.read_counters(affinity),
+        }
+    }
+
+    /// Read the counters and return the activity since the previous call.
+    /// The first call only records a sample and returns `None`.
+    pub fn sample(
+        &mut self,
+        backend: &(dyn CpuBackend + Send + Sync),
+        affinity: &GroupAffinity,
+        log: &Arc<MsrRestoreLog>,
+    ) -> Result<Option<CounterDelta>, String> {
+        let sample = self.read(backend, affinity, log)?;
+
+        Ok(self
+            .last
+            .replace(sample)
+            .map(|earlier| sample.since(&earlier)))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_source(
+    _backend: &(dyn CpuBackend + Send + Sync),
+    affinity: &GroupAffinity,
+    _log: &Arc<MsrRestoreLog>,
+) -> Result<CounterSource, String> {
+    PerfCounters::open(affinity.cpu_index()).map(CounterSource::Perf)
+}
+
+#[cfg(windows)]
+fn open_source(
+    backend: &(dyn CpuBackend + Send + Sync),
+    affinity: &GroupAffinity,
+    log: &Arc<MsrRestoreLog>,
+) -> Result<CounterSource, String> {
+    Ok(CounterSource::Msr {
+        _guards: backend.enable_counters(affinity, log)?,
+    })
+}
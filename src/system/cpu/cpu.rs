@@ -1,26 +1,41 @@
-use std::sync::Arc;
+use std::{cmp::Reverse, sync::Arc};
 
 use raw_cpuid::{CpuId, CpuIdReader};
 
 use crate::system::{
     cpu::{
-        backend::{amd::AmdBackend, intel::IntelBackend, unknown::UnknownBackend, CpuBackend},
+        backend::{
+            amd::AmdBackend, guest::GuestBackend, hygon::HygonBackend, intel::IntelBackend,
+            oc_mailbox::VoltagePlane, psys::PlatformPower, uncore::UncoreFrequency,
+            unknown::UnknownBackend, zen::PState, zhaoxin::ZhaoxinBackend, CpuBackend,
+        },
+        clock::{detect_clocks, Clocks},
         core::Core,
-        group_affinity::{get_all_group_affinities, run_on_all_affinities, GroupAffinity},
+        group_affinity::{
+            get_all_group_affinities, run_on_all_affinities, with_affinity, AffinityTarget,
+            GroupAffinity,
+        },
+        hypervisor::{get_hypervisor, Hypervisor},
+        msr::{self, DecodeContext, MsrDump},
         thread::Thread,
-        topology::{get_legacy_info, get_topology_info},
+        topology::{get_legacy_info, get_topology_info, TopologyInfo},
         vendor::{get_vendor, Vendor},
     },
-    kernal_driver::KernelDriver,
+    kernal_driver::{KernelDriver, MsrRestoreLog},
+    pci::{PciAddress, PciDevice},
 };
 
 pub struct Cpu {
     backend: Arc<dyn CpuBackend + Send + Sync>,
+    driver: Arc<KernelDriver>,
     pub package_id: u32,
     pub vendor: Vendor,
     pub model: String,
     pub cores: Vec<Core>,
-    affinity: GroupAffinity,
+    pub clocks: Clocks,
+    /// Set when running as a virtual machine guest
+    pub hypervisor: Option<Hypervisor>,
+    pub(crate) affinity: GroupAffinity,
 }
 
 impl Cpu {
@@ -28,9 +43,95 @@ impl Cpu {
         self.backend.read_package_temp(&self.affinity)
     }
 
+    /// Reader for the package temperature, for use on another thread
+    pub fn package_temp_source(&self) -> impl Fn() -> Result<f32, String> + Send + 'static {
+        let backend = self.backend.clone();
+        let affinity = self.affinity.clone();
+
+        move || backend.read_package_temp(&affinity)
+    }
+
+    /// PCI host bridge of the package: the AMD root complex or the Intel IMC
+    pub fn host_bridge(&self) -> Option<PciAddress> {
+        self.backend.host_bridge()
+    }
+
+    /// Package power in watts averaged since the previous call
+    pub fn power(&self) -> Option<f32> {
+        self.backend.read_power(&self.affinity)
+    }
+
+    /// Whole-platform power and cumulative energy since the first call (Intel PSys)
+    pub fn platform_power(&self) -> Result<PlatformPower, String> {
+        self.backend.read_platform_power(&self.affinity)
+    }
+
+    /// Uncore (ring) clock of the package (Intel)
+    pub fn uncore_frequency(&self) -> Result<UncoreFrequency, String> {
+        self.backend.read_uncore_frequency(&self.affinity)
+    }
+
     pub fn cores(&self) -> &[Core] {
         &self.cores
     }
+
+    /// P-state definition table (AMD and Hygon)
+    pub fn pstates(&self) -> Result<Vec<PState>, String> {
+        self.backend.read_pstates(&self.affinity)
+    }
+
+    /// Voltage offset of a plane in millivolts, negative when undervolted (Intel)
+    pub fn voltage_offset(&self, plane: VoltagePlane) -> Result<f32, String> {
+        self.backend.read_voltage_offset(&self.affinity, plane)
+    }
+
+    /// Voltage offsets of all planes in millivolts (Intel)
+    pub fn voltage_offsets(&self) -> Vec<(VoltagePlane, Result<f32, String>)> {
+        VoltagePlane::ALL
+            .iter()
+            .map(|&plane| (plane, self.voltage_offset(plane)))
+            .collect()
+    }
+
+    /// Read and decode every well-known MSR of this vendor on the package
+    pub fn dump_msrs(&self) -> Result<MsrDump, String> {
+        if let Some(hypervisor) = &self.hypervisor {
+            return Err(format!("MSR dump not supported under {}", hypervisor));
+        }
+
+        let context = with_affinity(&self.affinity, || Ok(DecodeContext::detect()))?;
+
+        msr::dump(&self.driver, &self.affinity, &self.vendor, &context)
+    }
+
+    /// Run a closure pinned to the package's lowest logical processor
+    pub fn run_on<F, R>(&self, f: F) -> Result<R, String>
+    where
+        F: FnOnce() -> Result<R, String>,
+    {
+        with_affinity(&self.affinity, f)
+    }
+
+    /// Cores ordered from most to least preferred by the firmware's CPPC ranking
+    pub fn preferred_cores(&self) -> Result<Vec<&Core>, String> {
+        let mut ranked = self
+            .cores
+            .iter()
+            .filter(|core| core.is_online())
+            .map(|core| core.cppc_rank().map(|rank| (rank, core)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        ranked.sort_by_key(|(rank, _)| Reverse(*rank));
+
+        Ok(ranked.into_iter().map(|(_, core)| core).collect())
+    }
+}
+
+/// Jobs for a package run on its lowest logical processor
+impl AffinityTarget for Cpu {
+    fn target_affinity(&self) -> Option<&GroupAffinity> {
+        Some(&self.affinity)
+    }
 }
 
 impl std::fmt::Debug for Cpu {
@@ -40,29 +141,42 @@ impl std::fmt::Debug for Cpu {
             .field("vendor", &self.vendor)
             .field("model", &self.model)
             .field("cores", &self.cores)
+            .field("clocks", &self.clocks)
+            .field("hypervisor", &self.hypervisor)
             .field("affinity", &self.affinity)
             .finish()
     }
 }
 
-pub fn gather_cpus(driver: &Arc<KernelDriver>) -> Result<Vec<Cpu>, String> {
+/// Discover every package. `msr_restore` records the counter controls the
+/// threads change, so they can be put back on close. The backends look up
+/// their root complex or memory controller in `devices`.
+pub fn gather_cpus(
+    driver: &Arc<KernelDriver>,
+    msr_restore: &Arc<MsrRestoreLog>,
+    devices: &[PciDevice],
+) -> Result<Vec<Cpu>, String> {
     let affinities = get_all_group_affinities()?;
+    let hypervisor = get_hypervisor(&CpuId::new());
     let mut cpus = Vec::new();
 
-    let results = run_on_all_affinities(affinities, |affinity| detect_cpu(affinity))?;
+    let results = run_on_all_affinities(affinities, detect_cpu)?;
     for (affinity, info) in results {
-        insert_cpu_info(&mut cpus, affinity, info?, driver);
+        insert_cpu_info(
+            &mut cpus,
+            affinity,
+            info?,
+            hypervisor.as_ref(),
+            driver,
+            msr_restore,
+            devices,
+        )?;
     }
 
     Ok(cpus)
 }
 
-fn detect_cpu(
-    affinity: GroupAffinity,
-) -> (
-    GroupAffinity,
-    Result<(u32, u32, u32, Vendor, String), String>,
-) {
+fn detect_cpu(affinity: GroupAffinity) -> (GroupAffinity, Result<TopologyInfo, String>) {
     let cpuid = CpuId::new();
     let vendor = get_vendor(&cpuid);
     let model = get_model(&cpuid);
@@ -86,9 +200,12 @@ fn get_model<R: CpuIdReader>(cpuid: &CpuId<R>) -> String {
 fn insert_cpu_info(
     cpus: &mut Vec<Cpu>,
     affinity: GroupAffinity,
-    info: (u32, u32, u32, Vendor, String),
+    info: TopologyInfo,
+    hypervisor: Option<&Hypervisor>,
     driver: &Arc<KernelDriver>,
-) {
+    msr_restore: &Arc<MsrRestoreLog>,
+    devices: &[PciDevice],
+) -> Result<(), String> {
     let (package_id, core_id, smt_id, vendor, model) = info;
 
     if let Some(cpu) = cpus.iter_mut().find(|c| c.package_id == package_id) {
@@ -97,36 +214,66 @@ fn insert_cpu_info(
         }
 
         if let Some(core) = cpu.cores.iter_mut().find(|c| c.core_id == core_id) {
-            core.threads
-                .push(Thread::new(smt_id, affinity, cpu.backend.clone()));
+            core.threads.push(Thread::new(
+                smt_id,
+                affinity,
+                cpu.backend.clone(),
+                driver.clone(),
+                msr_restore.clone(),
+            ));
         } else {
             let mut core = Core::new(core_id, cpu.backend.clone());
-            core.threads
-                .push(Thread::new(smt_id, affinity, cpu.backend.clone()));
+            core.threads.push(Thread::new(
+                smt_id,
+                affinity,
+                cpu.backend.clone(),
+                driver.clone(),
+                msr_restore.clone(),
+            ));
             cpu.cores.push(core);
         }
     } else {
-        let backend: Arc<dyn CpuBackend + Send + Sync> = match vendor {
-            Vendor::Intel => Arc::new(IntelBackend::new(driver.clone())),
-            Vendor::Amd => Arc::new(AmdBackend::new(driver.clone())),
+        let mut backend: Arc<dyn CpuBackend + Send + Sync> = match vendor {
+            Vendor::Intel => Arc::new(IntelBackend::new(driver.clone(), devices)),
+            Vendor::Amd => Arc::new(AmdBackend::new(driver.clone(), &model, &affinity, devices)),
+            Vendor::Hygon => Arc::new(HygonBackend::new(driver.clone(), &affinity, devices)),
+            Vendor::Centaur | Vendor::Zhaoxin => Arc::new(ZhaoxinBackend::new(driver.clone())),
             Vendor::Unknown(_) => Arc::new(UnknownBackend::new(driver.clone())),
         };
 
+        // MSR based sensors are trapped or faked in a guest
+        if let Some(hypervisor) = hypervisor {
+            backend = Arc::new(GuestBackend::new(backend, hypervisor.clone()));
+        }
+
         let mut core = Core::new(core_id, backend.clone());
-        core.threads
-            .push(Thread::new(smt_id, affinity.clone(), backend.clone()));
+        core.threads.push(Thread::new(
+            smt_id,
+            affinity.clone(),
+            backend.clone(),
+            driver.clone(),
+            msr_restore.clone(),
+        ));
+
+        // Calibration has to run on the package it describes
+        let clocks = with_affinity(&affinity, || Ok(detect_clocks()))?;
 
         cpus.push(Cpu {
             backend,
+            driver: driver.clone(),
             package_id,
             vendor,
             model,
             affinity,
             cores: vec![core],
+            clocks,
+            hypervisor: hypervisor.cloned(),
         });
     }
+
+    Ok(())
 }
 
-fn is_lower_affinity(a: &GroupAffinity, b: &GroupAffinity) -> bool {
+pub(crate) fn is_lower_affinity(a: &GroupAffinity, b: &GroupAffinity) -> bool {
     (a.group < b.group) || (a.group == b.group && a.mask < b.mask)
 }
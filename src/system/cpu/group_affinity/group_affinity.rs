@@ -1,5 +1,23 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct GroupAffinity {
     pub mask: usize,
     pub group: u16,
 }
+
+impl GroupAffinity {
+    /// Affinity of a single logical processor by its global index
+    pub fn from_cpu_index(cpu: usize) -> Self {
+        let bits = usize::BITS as usize;
+
+        Self {
+            mask: 1 << (cpu % bits),
+            group: (cpu / bits) as u16,
+        }
+    }
+
+    /// Global index of the lowest logical processor in the mask. Groups hold
+    /// up to `usize::BITS` processors each.
+    pub fn cpu_index(&self) -> usize {
+        self.group as usize * usize::BITS as usize + self.mask.trailing_zeros() as usize
+    }
+}
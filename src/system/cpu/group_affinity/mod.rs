@@ -1,7 +1,10 @@
+#[allow(clippy::module_inception)]
 pub mod group_affinity;
+pub mod pool;
 pub mod system;
 pub mod thread;
 
 pub use group_affinity::GroupAffinity;
+pub use pool::{AffinityPool, AffinityTarget};
 pub use system::get_all_group_affinities;
-pub use thread::{run_on_all_affinities, with_affinity};
+pub use thread::{pin_current_thread, run_on_all_affinities, with_affinity};
@@ -0,0 +1,184 @@
+use std::{
+    collections::HashMap,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use crate::system::cpu::group_affinity::{pin_current_thread, GroupAffinity};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Anything that maps to a logical processor: a `Thread`, `Core` or `Cpu`
+pub trait AffinityTarget {
+    fn target_affinity(&self) -> Option<&GroupAffinity>;
+}
+
+impl AffinityTarget for GroupAffinity {
+    fn target_affinity(&self) -> Option<&GroupAffinity> {
+        Some(self)
+    }
+}
+
+struct Worker {
+    sender: Option<Sender<Job>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// A pool with one long-lived worker permanently pinned to each logical processor.
+///
+/// Jobs run on the worker of the requested processor and hand their result back
+/// through a channel, so the caller's own thread is never migrated.
+///
+/// # Example
+///
+/// ```ignore
+/// let pool = system.worker_pool()?;
+/// let receiver = pool.submit(&cpu.cores()[0], || read_something())?;
+/// let value = receiver.recv().unwrap();
+/// ```
+pub struct AffinityPool {
+    workers: HashMap<GroupAffinity, Worker>,
+}
+
+impl AffinityPool {
+    /// Spawn and pin one worker per affinity
+    pub fn new(affinities: Vec<GroupAffinity>) -> Result<Self, String> {
+        let mut workers = HashMap::new();
+        let mut ready = Vec::new();
+
+        for affinity in affinities {
+            if workers.contains_key(&affinity) {
+                continue;
+            }
+
+            let (sender, receiver) = channel::<Job>();
+            let (ready_tx, ready_rx) = channel();
+            let pinned = affinity.clone();
+
+            let handle = thread::Builder::new()
+                .name(format!("hwmonitor-{}-{:x}", affinity.group, affinity.mask))
+                .spawn(move || {
+                    let pin = pin_current_thread(&pinned);
+                    let ok = pin.is_ok();
+                    let _ = ready_tx.send(pin);
+                    if !ok {
+                        return;
+                    }
+
+                    // Runs until the pool drops the sender
+                    for job in receiver {
+                        let _ = catch_unwind(AssertUnwindSafe(job));
+                    }
+                })
+                .map_err(|e| format!("Failed to spawn worker: {}", e))?;
+
+            workers.insert(
+                affinity.clone(),
+                Worker {
+                    sender: Some(sender),
+                    handle: Some(handle),
+                },
+            );
+            ready.push((affinity, ready_rx));
+        }
+
+        let pool = Self { workers };
+
+        // Wait until every worker is pinned, dropping the pool on failure joins the rest
+        for (affinity, ready_rx) in ready {
+            ready_rx
+                .recv()
+                .map_err(|_| format!("Worker for {:?} exited during startup", affinity))?
+                .map_err(|e| format!("Failed to pin worker to {:?}: {}", affinity, e))?;
+        }
+
+        Ok(pool)
+    }
+
+    /// Run `f` on the worker pinned to `target`. The result arrives on the
+    /// returned channel; it disconnects without a value if `f` panics.
+    pub fn submit<T, F, R>(&self, target: &T, f: F) -> Result<Receiver<R>, String>
+    where
+        T: AffinityTarget + ?Sized,
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let affinity = target
+            .target_affinity()
+            .ok_or("Target has no logical processor")?;
+
+        let sender = self
+            .workers
+            .get(affinity)
+            .and_then(|w| w.sender.as_ref())
+            .ok_or_else(|| format!("No worker pinned to {:?}", affinity))?;
+
+        let (result_tx, result_rx) = channel();
+        sender
+            .send(Box::new(move || {
+                let _ = result_tx.send(f());
+            }))
+            .map_err(|_| format!("Worker for {:?} has stopped", affinity))?;
+
+        Ok(result_rx)
+    }
+
+    /// Run `f` on `target` and wait for the result
+    pub fn run<T, F, R>(&self, target: &T, f: F) -> Result<R, String>
+    where
+        T: AffinityTarget + ?Sized,
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.submit(target, f)?
+            .recv()
+            .map_err(|_| "Worker job panicked".to_string())
+    }
+
+    /// Run `f` on every worker in parallel
+    pub fn broadcast<F, R>(&self, f: F) -> Result<Vec<(GroupAffinity, Receiver<R>)>, String>
+    where
+        F: Fn(&GroupAffinity) -> R + Send + Sync + Clone + 'static,
+        R: Send + 'static,
+    {
+        self.workers
+            .keys()
+            .map(|affinity| {
+                let f = f.clone();
+                let pinned = affinity.clone();
+                let receiver = self.submit(affinity, move || f(&pinned))?;
+
+                Ok((affinity.clone(), receiver))
+            })
+            .collect()
+    }
+
+    /// The logical processors this pool has workers on
+    pub fn affinities(&self) -> impl Iterator<Item = &GroupAffinity> {
+        self.workers.keys()
+    }
+}
+
+impl Drop for AffinityPool {
+    fn drop(&mut self) {
+        // Closing the channels ends the worker loops
+        for worker in self.workers.values_mut() {
+            worker.sender.take();
+        }
+
+        for worker in self.workers.values_mut() {
+            if let Some(handle) = worker.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for AffinityPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AffinityPool")
+            .field("workers", &self.workers.len())
+            .finish()
+    }
+}
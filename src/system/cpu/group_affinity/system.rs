@@ -1,3 +1,4 @@
+#[cfg(windows)]
 use windows::Win32::{
     Foundation::ERROR_INSUFFICIENT_BUFFER,
     System::SystemInformation::{
@@ -8,6 +9,7 @@ use windows::Win32::{
 
 use crate::system::cpu::group_affinity::GroupAffinity;
 
+#[cfg(windows)]
 pub fn get_all_group_affinities() -> Result<Vec<GroupAffinity>, String> {
     unsafe {
         // First call: get required buffer size
@@ -67,3 +69,35 @@ pub fn get_all_group_affinities() -> Result<Vec<GroupAffinity>, String> {
         Ok(affinities)
     }
 }
+
+/// Enumerate the online logical processors from sysfs
+#[cfg(target_os = "linux")]
+pub fn get_all_group_affinities() -> Result<Vec<GroupAffinity>, String> {
+    let online = std::fs::read_to_string("/sys/devices/system/cpu/online")
+        .map_err(|e| format!("Failed to read online CPUs: {}", e))?;
+
+    Ok(parse_cpu_list(&online)?
+        .into_iter()
+        .map(GroupAffinity::from_cpu_index)
+        .collect())
+}
+
+/// Parse a kernel CPU list such as "0-3,8,10-11"
+#[cfg(target_os = "linux")]
+pub fn parse_cpu_list(list: &str) -> Result<Vec<usize>, String> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|e| format!("Invalid CPU list {:?}: {}", list.trim(), e))
+    };
+
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => cpus.extend(parse(start)?..=parse(end)?),
+            None => cpus.push(parse(range)?),
+        }
+    }
+
+    Ok(cpus)
+}
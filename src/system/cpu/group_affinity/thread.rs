@@ -1,5 +1,6 @@
 use std::thread;
 
+#[cfg(windows)]
 use windows::Win32::System::{
     SystemInformation::GROUP_AFFINITY,
     Threading::{GetCurrentThread, GetThreadGroupAffinity, SetThreadGroupAffinity},
@@ -8,43 +9,69 @@ use windows::Win32::System::{
 use crate::system::cpu::group_affinity::GroupAffinity;
 
 /// Set thread affinity temporarily, run the closure, restore old affinity
+#[cfg(windows)]
 pub fn with_affinity<F, R>(aff: &GroupAffinity, f: F) -> Result<R, String>
 where
     F: FnOnce() -> Result<R, String>,
 {
-    unsafe {
-        let thread = GetCurrentThread();
-
-        // Save old affinity
+    // Save old affinity, restored even if `f` panics
+    let prev = unsafe {
         let mut prev: GROUP_AFFINITY = std::mem::zeroed();
-        if !GetThreadGroupAffinity(thread, &mut prev).as_bool() {
+        if !GetThreadGroupAffinity(GetCurrentThread(), &mut prev).as_bool() {
             return Err("GetThreadGroupAffinity failed".into());
         }
+        GroupAffinityGuard { prev: Some(prev) }
+    };
 
-        // Set new affinity
-        let new_aff = GROUP_AFFINITY {
-            Mask: aff.mask,
-            Group: aff.group,
-            Reserved: [0; 3],
-        };
-        if !SetThreadGroupAffinity(thread, &new_aff, Some(&mut prev)).as_bool() {
-            return Err("SetThreadGroupAffinity failed".into());
-        }
+    // Set new affinity
+    pin_current_thread(aff)?;
 
-        // Run the function
-        let result = f();
+    // Run the function
+    let result = f();
 
-        // Restore old affinity
-        if !SetThreadGroupAffinity(thread, &prev, None).as_bool() {
-            return Err("SetThreadGroupAffinity failed".into());
+    // Restore old affinity
+    prev.restore()?;
+
+    result
+}
+
+// Puts the saved group affinity back when dropped, including while unwinding
+#[cfg(windows)]
+struct GroupAffinityGuard {
+    prev: Option<GROUP_AFFINITY>,
+}
+
+#[cfg(windows)]
+impl GroupAffinityGuard {
+    fn restore(mut self) -> Result<(), String> {
+        match self.prev.take() {
+            Some(prev) => set_group_affinity(&prev),
+            None => Ok(()),
         }
+    }
+}
 
-        result
+#[cfg(windows)]
+impl Drop for GroupAffinityGuard {
+    fn drop(&mut self) {
+        if let Some(prev) = self.prev.take() {
+            let _ = set_group_affinity(&prev);
+        }
     }
 }
 
+#[cfg(windows)]
+fn set_group_affinity(group: &GROUP_AFFINITY) -> Result<(), String> {
+    if !unsafe { SetThreadGroupAffinity(GetCurrentThread(), group, None) }.as_bool() {
+        return Err("SetThreadGroupAffinity failed".into());
+    }
+
+    Ok(())
+}
+
 /// Run a closure for each group affinity in parallel,
 /// while preserving the main thread's original affinity.
+#[cfg(windows)]
 pub fn run_on_all_affinities<R, F>(affinities: Vec<GroupAffinity>, f: F) -> Result<Vec<R>, String>
 where
     F: Fn(GroupAffinity) -> R + Send + Sync + 'static + Copy,
@@ -62,7 +89,6 @@ where
         let handles: Vec<_> = affinities
             .into_iter()
             .map(|aff| {
-                let f = f;
                 thread::spawn(move || {
                     // Set this thread's affinity
                     let group = GROUP_AFFINITY {
@@ -92,3 +118,154 @@ where
         Ok(results)
     }
 }
+
+/// Permanently pin the calling thread to `affinity`
+#[cfg(windows)]
+pub fn pin_current_thread(affinity: &GroupAffinity) -> Result<(), String> {
+    set_group_affinity(&GROUP_AFFINITY {
+        Mask: affinity.mask,
+        Group: affinity.group,
+        Reserved: [0; 3],
+    })
+}
+
+/// Set thread affinity temporarily, run the closure, restore old affinity
+#[cfg(target_os = "linux")]
+pub fn with_affinity<F, R>(aff: &GroupAffinity, f: F) -> Result<R, String>
+where
+    F: FnOnce() -> Result<R, String>,
+{
+    // Save old affinity, restored even if `f` panics
+    let prev = CpuSetGuard {
+        prev: Some(get_cpu_set()?),
+    };
+
+    // Set new affinity
+    let mut new_set = empty_cpu_set();
+    add_to_cpu_set(&mut new_set, aff)?;
+    set_cpu_set(&new_set)?;
+
+    // Run the function
+    let result = f();
+
+    // Restore old affinity
+    prev.restore()?;
+
+    result
+}
+
+// Puts the saved CPU set back when dropped, including while unwinding
+#[cfg(target_os = "linux")]
+struct CpuSetGuard {
+    prev: Option<libc::cpu_set_t>,
+}
+
+#[cfg(target_os = "linux")]
+impl CpuSetGuard {
+    fn restore(mut self) -> Result<(), String> {
+        match self.prev.take() {
+            Some(prev) => set_cpu_set(&prev),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for CpuSetGuard {
+    fn drop(&mut self) {
+        if let Some(prev) = self.prev.take() {
+            let _ = set_cpu_set(&prev);
+        }
+    }
+}
+
+/// Run a closure for each group affinity in parallel.
+/// The calling thread is never migrated.
+#[cfg(target_os = "linux")]
+pub fn run_on_all_affinities<R, F>(affinities: Vec<GroupAffinity>, f: F) -> Result<Vec<R>, String>
+where
+    F: Fn(GroupAffinity) -> R + Send + Sync + 'static + Copy,
+    R: Send + 'static,
+{
+    // Spawn threads for each affinity
+    let handles: Vec<_> = affinities
+        .into_iter()
+        .map(|aff| {
+            thread::spawn(move || -> Result<R, String> {
+                pin_current_thread(&aff)?;
+
+                // Run the closure
+                Ok(f(aff))
+            })
+        })
+        .collect();
+
+    // Join threads and collect results
+    let mut results = Vec::with_capacity(handles.len());
+    for h in handles {
+        results.push(h.join().map_err(|_| "Thread panicked".to_string())??);
+    }
+
+    Ok(results)
+}
+
+/// Permanently pin the calling thread to `affinity`
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(affinity: &GroupAffinity) -> Result<(), String> {
+    let mut set = empty_cpu_set();
+    add_to_cpu_set(&mut set, affinity)?;
+    set_cpu_set(&set)
+}
+
+#[cfg(target_os = "linux")]
+fn empty_cpu_set() -> libc::cpu_set_t {
+    unsafe { std::mem::zeroed() }
+}
+
+// Add every processor of the affinity mask. A cpu_set_t holds 1024 processors,
+// so machines with more than 64 CPUs use several groups.
+#[cfg(target_os = "linux")]
+fn add_to_cpu_set(set: &mut libc::cpu_set_t, affinity: &GroupAffinity) -> Result<(), String> {
+    let capacity = std::mem::size_of::<libc::cpu_set_t>() * 8;
+    let mut mask = affinity.mask;
+
+    while mask != 0 {
+        let bit = mask.trailing_zeros() as usize;
+        let cpu = affinity.group as usize * usize::BITS as usize + bit;
+        if cpu >= capacity {
+            return Err(format!("CPU {} does not fit in cpu_set_t", cpu));
+        }
+
+        unsafe { libc::CPU_SET(cpu, set) };
+        mask &= !(1 << bit);
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn get_cpu_set() -> Result<libc::cpu_set_t, String> {
+    let mut set = empty_cpu_set();
+
+    if unsafe { libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) } != 0
+    {
+        return Err(format!(
+            "sched_getaffinity failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(set)
+}
+
+#[cfg(target_os = "linux")]
+fn set_cpu_set(set: &libc::cpu_set_t) -> Result<(), String> {
+    if unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), set) } != 0 {
+        return Err(format!(
+            "sched_setaffinity failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(())
+}
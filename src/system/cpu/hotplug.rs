@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+
+use crate::system::cpu::{
+    cpu::{is_lower_affinity, Cpu},
+    group_affinity::{get_all_group_affinities, GroupAffinity},
+};
+
+/// Logical processors whose state changed since the topology was gathered
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopologyChanges {
+    /// Known threads that are no longer online
+    pub went_offline: Vec<GroupAffinity>,
+    /// Known threads that are online again
+    pub came_online: Vec<GroupAffinity>,
+    /// Online processors that are not part of the gathered topology.
+    /// Call `System::refresh_topology` to pick them up.
+    pub unknown: Vec<GroupAffinity>,
+}
+
+impl TopologyChanges {
+    pub fn is_empty(&self) -> bool {
+        self.went_offline.is_empty() && self.came_online.is_empty() && self.unknown.is_empty()
+    }
+}
+
+/// Compare the gathered topology with the processors that are online now.
+/// Threads are marked offline or online again, and packages whose target
+/// processor went offline move to their lowest online thread.
+pub fn update_online_state(cpus: &mut [Cpu]) -> Result<TopologyChanges, String> {
+    let online: HashSet<GroupAffinity> = get_all_group_affinities()?.into_iter().collect();
+    let mut known = HashSet::new();
+    let mut changes = TopologyChanges::default();
+
+    for cpu in cpus.iter_mut() {
+        for thread in cpu.cores.iter_mut().flat_map(|c| c.threads.iter_mut()) {
+            known.insert(thread.affinity.clone());
+
+            let is_online = online.contains(&thread.affinity);
+            if thread.is_online() && !is_online {
+                changes.went_offline.push(thread.affinity.clone());
+            } else if !thread.is_online() && is_online {
+                changes.came_online.push(thread.affinity.clone());
+            }
+            thread.set_online(is_online);
+        }
+
+        retarget(cpu);
+    }
+
+    changes.unknown = online.into_iter().filter(|a| !known.contains(a)).collect();
+
+    Ok(changes)
+}
+
+/// Carry threads of the previous topology that are offline now over into a
+/// freshly gathered one, so ids and objects stay stable across a refresh.
+pub fn merge_offline(cpus: &mut Vec<Cpu>, previous: Vec<Cpu>) {
+    for mut old_cpu in previous {
+        let Some(cpu) = cpus.iter_mut().find(|c| c.package_id == old_cpu.package_id) else {
+            // The whole package is offline
+            for thread in old_cpu.cores.iter_mut().flat_map(|c| c.threads.iter_mut()) {
+                thread.set_online(false);
+            }
+            cpus.push(old_cpu);
+            continue;
+        };
+
+        for mut old_core in old_cpu.cores {
+            let Some(core) = cpu.cores.iter_mut().find(|c| c.core_id == old_core.core_id) else {
+                for thread in old_core.threads.iter_mut() {
+                    thread.set_online(false);
+                }
+                cpu.cores.push(old_core);
+                continue;
+            };
+
+            for mut old_thread in old_core.threads {
+                if !core
+                    .threads
+                    .iter()
+                    .any(|t| t.thread_id == old_thread.thread_id)
+                {
+                    old_thread.set_online(false);
+                    core.threads.push(old_thread);
+                }
+            }
+        }
+    }
+}
+
+// Point package-wide reads at the lowest online thread
+fn retarget(cpu: &mut Cpu) {
+    let lowest = cpu
+        .cores
+        .iter()
+        .flat_map(|c| &c.threads)
+        .filter(|t| t.is_online())
+        .map(|t| &t.affinity)
+        .fold(None::<&GroupAffinity>, |lowest, a| match lowest {
+            Some(l) if !is_lower_affinity(a, l) => Some(l),
+            _ => Some(a),
+        });
+
+    if let Some(lowest) = lowest {
+        cpu.affinity = lowest.clone();
+    }
+}
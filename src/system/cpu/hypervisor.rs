@@ -0,0 +1,60 @@
+use std::fmt;
+
+use raw_cpuid::{CpuId, CpuIdReader};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hypervisor {
+    Kvm,
+    HyperV,
+    VMware,
+    Xen,
+    Unknown(Option<String>),
+}
+
+impl fmt::Display for Hypervisor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hypervisor::Kvm => write!(f, "KVM"),
+            Hypervisor::HyperV => write!(f, "Hyper-V"),
+            Hypervisor::VMware => write!(f, "VMware"),
+            Hypervisor::Xen => write!(f, "Xen"),
+            Hypervisor::Unknown(Some(name)) => write!(f, "{}", name),
+            Hypervisor::Unknown(None) => write!(f, "unknown hypervisor"),
+        }
+    }
+}
+
+// Detect a hypervisor from the CPUID hypervisor bit and its vendor leaf
+pub fn get_hypervisor<R: CpuIdReader>(cpuid: &CpuId<R>) -> Option<Hypervisor> {
+    let present = cpuid.get_feature_info().is_some_and(|f| f.has_hypervisor());
+    if !present {
+        return None;
+    }
+
+    // Some hypervisors set the bit without publishing a vendor leaf
+    let Some(info) = cpuid.get_hypervisor_info() else {
+        return Some(Hypervisor::Unknown(None));
+    };
+
+    Some(match info.identify() {
+        raw_cpuid::Hypervisor::KVM => Hypervisor::Kvm,
+        raw_cpuid::Hypervisor::HyperV => Hypervisor::HyperV,
+        raw_cpuid::Hypervisor::VMware => Hypervisor::VMware,
+        raw_cpuid::Hypervisor::Xen => Hypervisor::Xen,
+        raw_cpuid::Hypervisor::QEMU => Hypervisor::Unknown(Some("QEMU".into())),
+        raw_cpuid::Hypervisor::Bhyve => Hypervisor::Unknown(Some("bhyve".into())),
+        raw_cpuid::Hypervisor::QNX => Hypervisor::Unknown(Some("QNX".into())),
+        raw_cpuid::Hypervisor::ACRN => Hypervisor::Unknown(Some("ACRN".into())),
+        raw_cpuid::Hypervisor::Unknown(ebx, ecx, edx) => {
+            let signature = [ebx, ecx, edx]
+                .iter()
+                .flat_map(|r| r.to_le_bytes())
+                .collect::<Vec<u8>>();
+
+            match String::from_utf8_lossy(&signature).trim_end_matches('\0') {
+                "" => Hypervisor::Unknown(None),
+                name => Hypervisor::Unknown(Some(name.to_owned())),
+            }
+        }
+    })
+}
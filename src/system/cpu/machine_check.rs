@@ -0,0 +1,205 @@
+//! Machine-check architecture (MCA) bank reader.
+//! For more information see the Intel SDM, Vol. 3B, chapter 16.
+
+use std::collections::HashMap;
+
+use x86::msr::IA32_MCG_CAP;
+
+use crate::system::{
+    cpu::cpu::Cpu,
+    kernal_driver::{KernelDriver, MsrRequest},
+};
+
+/// IA32_MC0_STATUS, banks are 4 MSRs apart (CTL, STATUS, ADDR, MISC)
+const IA32_MC0_STATUS: u32 = 0x401;
+const IA32_MC0_ADDR: u32 = 0x402;
+const IA32_MC0_MISC: u32 = 0x403;
+
+fn bank_msr(base: u32, bank: u32) -> u32 {
+    base + 4 * bank
+}
+
+/// Broad class of an architectural MCA error code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum McaErrorClass {
+    NoError,
+    Unclassified,
+    MicrocodeRomParity,
+    External,
+    FunctionalRedundancyCheck,
+    InternalParity,
+    InternalTimer,
+    GenericCacheHierarchy,
+    Tlb,
+    MemoryController,
+    CacheHierarchy,
+    Bus,
+    Other,
+}
+
+impl McaErrorClass {
+    /// Classify the low 16 bits of IA32_MCi_STATUS
+    pub fn from_code(code: u16) -> Self {
+        // Bit 12 is the correction report filtering flag, not part of the code
+        let code = code & !(1 << 12);
+
+        match code {
+            0x0000 => Self::NoError,
+            0x0001 => Self::Unclassified,
+            0x0002 => Self::MicrocodeRomParity,
+            0x0003 => Self::External,
+            0x0004 => Self::FunctionalRedundancyCheck,
+            0x0005 => Self::InternalParity,
+            0x0400 => Self::InternalTimer,
+            c if c & 0xFFFC == 0x000C => Self::GenericCacheHierarchy,
+            c if c & 0xFFF0 == 0x0010 => Self::Tlb,
+            c if c & 0xFF80 == 0x0080 => Self::MemoryController,
+            c if c & 0xFF00 == 0x0100 => Self::CacheHierarchy,
+            c if c & 0xF800 == 0x0800 => Self::Bus,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Decoded IA32_MCi_STATUS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct McaStatus {
+    pub valid: bool,
+    /// A second error arrived before software cleared the first
+    pub overflow: bool,
+    pub uncorrected: bool,
+    pub enabled: bool,
+    pub misc_valid: bool,
+    pub addr_valid: bool,
+    /// Processor context corrupt, the state may not be restartable
+    pub context_corrupt: bool,
+    /// Corrected errors counted by hardware, bits 52:38
+    pub corrected_count: u16,
+    pub mca_error_code: u16,
+    pub model_error_code: u16,
+    pub raw: u64,
+}
+
+impl McaStatus {
+    pub fn from_raw(value: u64) -> Self {
+        let bit = |n: u32| value & (1 << n) != 0;
+
+        Self {
+            valid: bit(63),
+            overflow: bit(62),
+            uncorrected: bit(61),
+            enabled: bit(60),
+            misc_valid: bit(59),
+            addr_valid: bit(58),
+            context_corrupt: bit(57),
+            corrected_count: ((value >> 38) & 0x7FFF) as u16,
+            mca_error_code: value as u16,
+            model_error_code: (value >> 16) as u16,
+            raw: value,
+        }
+    }
+
+    pub fn error_class(&self) -> McaErrorClass {
+        McaErrorClass::from_code(self.mca_error_code)
+    }
+}
+
+/// A machine-check record that appeared since the previous poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineCheckEvent {
+    pub package_id: u32,
+    pub bank: u32,
+    pub status: McaStatus,
+    /// IA32_MCi_ADDR, when the status marks it valid
+    pub address: Option<u64>,
+    /// IA32_MCi_MISC, when the status marks it valid
+    pub misc: Option<u64>,
+}
+
+/// Polls the MCA banks of every package and reports new records
+#[derive(Debug, Default)]
+pub struct MachineCheckMonitor {
+    /// Last seen raw status per (package, bank)
+    last: HashMap<(u32, u32), u64>,
+}
+
+impl MachineCheckMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read all banks and return the records that are new or changed since
+    /// the previous poll. The banks are not cleared.
+    pub fn poll(
+        &mut self,
+        driver: &KernelDriver,
+        cpus: &[Cpu],
+    ) -> Result<Vec<MachineCheckEvent>, String> {
+        let mut events = Vec::new();
+
+        for cpu in cpus {
+            let affinity = &cpu.affinity;
+
+            let (eax, _) = driver.rdmsr_tx(IA32_MCG_CAP, affinity)?;
+            let banks = eax & 0xFF;
+
+            // All status registers in one pinned context
+            let statuses = driver.rdmsr_on(
+                affinity,
+                &(0..banks)
+                    .map(|bank| bank_msr(IA32_MC0_STATUS, bank))
+                    .collect::<Vec<_>>(),
+            )?;
+
+            let mut changed = Vec::new();
+            for (bank, status) in (0..banks).zip(statuses) {
+                // Banks can be unimplemented or locked by firmware
+                let Ok((eax, edx)) = status else {
+                    continue;
+                };
+                let raw = ((edx as u64) << 32) | eax as u64;
+
+                let previous = self.last.insert((cpu.package_id, bank), raw);
+                let status = McaStatus::from_raw(raw);
+                if status.valid && previous != Some(raw) {
+                    changed.push((bank, status));
+                }
+            }
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            let requests: Vec<MsrRequest> = changed
+                .iter()
+                .map(|(bank, _)| MsrRequest {
+                    affinity: affinity.clone(),
+                    indices: vec![
+                        bank_msr(IA32_MC0_ADDR, *bank),
+                        bank_msr(IA32_MC0_MISC, *bank),
+                    ],
+                })
+                .collect();
+            let details = driver.rdmsr_batch(&requests)?;
+
+            for ((bank, status), values) in changed.into_iter().zip(details) {
+                let value = |i: usize| {
+                    values[i]
+                        .as_ref()
+                        .ok()
+                        .map(|(eax, edx)| ((*edx as u64) << 32) | *eax as u64)
+                };
+
+                events.push(MachineCheckEvent {
+                    package_id: cpu.package_id,
+                    bank,
+                    address: if status.addr_valid { value(0) } else { None },
+                    misc: if status.misc_valid { value(1) } else { None },
+                    status,
+                });
+            }
+        }
+
+        Ok(events)
+    }
+}
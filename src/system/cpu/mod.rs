@@ -1,9 +1,18 @@
-mod backend;
-mod core;
+pub mod backend;
+pub mod clock;
+pub mod core;
+pub mod counters;
+#[allow(clippy::module_inception)]
 pub mod cpu;
 pub mod group_affinity;
-mod thread;
+pub mod hotplug;
+pub mod hypervisor;
+pub mod machine_check;
+pub mod msr;
+#[cfg(target_os = "linux")]
+mod perf_event;
+pub mod thread;
 mod topology;
-mod vendor;
+pub mod vendor;
 
 // pub use cpu::;
@@ -0,0 +1,337 @@
+//! Typed decoders for well-known MSRs.
+//!
+//! Each decoder turns a raw 64-bit register into a struct with named fields
+//! in real units. The registry maps register indices to decoders per vendor so
+//! tooling can dump everything we know how to read.
+
+use std::fmt;
+
+use raw_cpuid::CpuId;
+use x86::msr::{
+    IA32_PACKAGE_THERM_STATUS, IA32_PERF_STATUS, IA32_THERM_STATUS, MSR_RAPL_POWER_UNIT,
+    MSR_TEMPERATURE_TARGET,
+};
+
+use crate::system::{
+    cpu::{
+        backend::{
+            hwp::{
+                HwpCapabilities, HwpRequest, HwpStatus, IA32_HWP_CAPABILITIES, IA32_HWP_REQUEST,
+                IA32_HWP_STATUS,
+            },
+            zen::{self, PState, MSR_PSTATE_DEF_BASE},
+        },
+        group_affinity::GroupAffinity,
+        vendor::{get_family_model, Vendor},
+    },
+    kernal_driver::KernelDriver,
+};
+
+/// Combine the halves returned by `rdmsr` into one register value
+pub fn join(eax: u32, edx: u32) -> u64 {
+    ((edx as u64) << 32) | eax as u64
+}
+
+/// Decoded IA32_THERM_STATUS or IA32_PACKAGE_THERM_STATUS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThermStatus {
+    /// The digital readout holds a valid temperature
+    pub reading_valid: bool,
+    /// Degrees Celsius below TjMax
+    pub delta_to_tj_max: u8,
+    /// Degrees Celsius per step of the readout (core status only, 0 otherwise)
+    pub resolution: u8,
+    pub prochot: bool,
+    pub critical_temperature: bool,
+    pub power_limit: bool,
+}
+
+impl ThermStatus {
+    pub fn from_raw(value: u64) -> Self {
+        Self {
+            reading_valid: value & (1 << 31) != 0,
+            delta_to_tj_max: ((value >> 16) & 0x7F) as u8,
+            resolution: ((value >> 27) & 0xF) as u8,
+            prochot: value & (1 << 2) != 0,
+            critical_temperature: value & (1 << 4) != 0,
+            power_limit: value & (1 << 10) != 0,
+        }
+    }
+
+    /// Temperature in degrees Celsius for the given TjMax
+    pub fn temperature(&self, tj_max: u8) -> Option<f32> {
+        self.reading_valid
+            .then_some(tj_max as f32 - self.delta_to_tj_max as f32)
+    }
+}
+
+/// Decoded MSR_TEMPERATURE_TARGET
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TemperatureTarget {
+    /// Degrees Celsius at which PROCHOT asserts
+    pub tj_max: u8,
+    /// Degrees Celsius below TjMax at which throttling starts
+    pub offset: u8,
+}
+
+impl TemperatureTarget {
+    pub fn from_raw(value: u64) -> Self {
+        Self {
+            tj_max: ((value >> 16) & 0xFF) as u8,
+            offset: ((value >> 24) & 0x3F) as u8,
+        }
+    }
+}
+
+/// Decoded IA32_PERF_STATUS
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerfStatus {
+    /// Current bus ratio
+    pub ratio: u8,
+    /// Core voltage in volts (Sandy Bridge and later)
+    pub voltage: f32,
+}
+
+impl PerfStatus {
+    pub fn from_raw(value: u64) -> Self {
+        Self {
+            ratio: ((value >> 8) & 0xFF) as u8,
+            voltage: ((value >> 32) & 0xFFFF) as f32 / 8192.0,
+        }
+    }
+}
+
+/// Decoded RAPL power unit register. Intel and AMD share the layout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaplPowerUnit {
+    pub watts_per_unit: f64,
+    pub joules_per_unit: f64,
+    pub seconds_per_unit: f64,
+}
+
+impl RaplPowerUnit {
+    pub fn from_raw(value: u64) -> Self {
+        let unit = |shift: u64, mask: u64| 1.0 / (1u64 << ((value >> shift) & mask)) as f64;
+
+        Self {
+            watts_per_unit: unit(0, 0xF),
+            joules_per_unit: unit(8, 0x1F),
+            seconds_per_unit: unit(16, 0xF),
+        }
+    }
+}
+
+/// CPU details some registers need to be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DecodeContext {
+    pub family: u8,
+    pub model: u8,
+    pub epp_supported: bool,
+}
+
+impl DecodeContext {
+    /// Build the context from CPUID of the current processor
+    pub fn detect() -> Self {
+        let cpuid = CpuId::new();
+        let (family, model) = get_family_model(&cpuid);
+
+        Self {
+            family,
+            model,
+            epp_supported: cpuid
+                .get_thermal_power_info()
+                .is_some_and(|p| p.has_hwp_energy_performance_preference()),
+        }
+    }
+}
+
+/// A register decoded into its typed form
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodedMsr {
+    ThermStatus(ThermStatus),
+    TemperatureTarget(TemperatureTarget),
+    PerfStatus(PerfStatus),
+    RaplPowerUnit(RaplPowerUnit),
+    HwpCapabilities(HwpCapabilities),
+    HwpRequest(HwpRequest),
+    HwpStatus(HwpStatus),
+    PState(PState),
+}
+
+impl fmt::Display for DecodedMsr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodedMsr::ThermStatus(s) => write!(
+                f,
+                "valid={} delta={} °C resolution={} °C prochot={} critical={} power_limit={}",
+                s.reading_valid,
+                s.delta_to_tj_max,
+                s.resolution,
+                s.prochot,
+                s.critical_temperature,
+                s.power_limit
+            ),
+            DecodedMsr::TemperatureTarget(t) => {
+                write!(f, "tj_max={} °C offset={} °C", t.tj_max, t.offset)
+            }
+            DecodedMsr::PerfStatus(p) => {
+                write!(f, "ratio={} voltage={:.4} V", p.ratio, p.voltage)
+            }
+            DecodedMsr::RaplPowerUnit(u) => write!(
+                f,
+                "power={} W energy={} J time={} s",
+                u.watts_per_unit, u.joules_per_unit, u.seconds_per_unit
+            ),
+            DecodedMsr::HwpCapabilities(c) => write!(
+                f,
+                "highest={} guaranteed={} most_efficient={} lowest={}",
+                c.highest, c.guaranteed, c.most_efficient, c.lowest
+            ),
+            DecodedMsr::HwpRequest(r) => {
+                write!(
+                    f,
+                    "min={} max={} desired={}",
+                    r.minimum, r.maximum, r.desired
+                )?;
+                if let Some(epp) = r.energy_performance_preference {
+                    write!(f, " epp={}", epp)?;
+                }
+                if let Some(window) = r.activity_window_us {
+                    write!(f, " window={} us", window)?;
+                }
+                write!(f, " package_control={}", r.package_control)
+            }
+            DecodedMsr::HwpStatus(s) => write!(
+                f,
+                "guaranteed_changed={} excursion_to_minimum={}",
+                s.guaranteed_performance_changed, s.excursion_to_minimum
+            ),
+            DecodedMsr::PState(p) => write!(
+                f,
+                "P{} enabled={} frequency={:.0} MHz voltage={:.4} V",
+                p.index, p.enabled, p.frequency_mhz, p.voltage
+            ),
+        }
+    }
+}
+
+/// Registry entry for a single register
+#[derive(Debug, Clone, Copy)]
+pub struct MsrDecoder {
+    pub index: u32,
+    pub name: &'static str,
+    decode: fn(u32, u64, &DecodeContext) -> DecodedMsr,
+}
+
+impl MsrDecoder {
+    pub fn decode(&self, value: u64, context: &DecodeContext) -> DecodedMsr {
+        (self.decode)(self.index, value, context)
+    }
+}
+
+const fn decoder(
+    index: u32,
+    name: &'static str,
+    decode: fn(u32, u64, &DecodeContext) -> DecodedMsr,
+) -> MsrDecoder {
+    MsrDecoder {
+        index,
+        name,
+        decode,
+    }
+}
+
+fn therm_status(_: u32, value: u64, _: &DecodeContext) -> DecodedMsr {
+    DecodedMsr::ThermStatus(ThermStatus::from_raw(value))
+}
+
+fn pstate(index: u32, value: u64, context: &DecodeContext) -> DecodedMsr {
+    DecodedMsr::PState(PState::from_raw(
+        index - MSR_PSTATE_DEF_BASE,
+        value,
+        context.family,
+        context.model,
+    ))
+}
+
+const INTEL_DECODERS: &[MsrDecoder] = &[
+    decoder(IA32_PERF_STATUS, "IA32_PERF_STATUS", |_, v, _| {
+        DecodedMsr::PerfStatus(PerfStatus::from_raw(v))
+    }),
+    decoder(IA32_THERM_STATUS, "IA32_THERM_STATUS", therm_status),
+    decoder(
+        MSR_TEMPERATURE_TARGET,
+        "MSR_TEMPERATURE_TARGET",
+        |_, v, _| DecodedMsr::TemperatureTarget(TemperatureTarget::from_raw(v)),
+    ),
+    decoder(
+        IA32_PACKAGE_THERM_STATUS,
+        "IA32_PACKAGE_THERM_STATUS",
+        therm_status,
+    ),
+    decoder(MSR_RAPL_POWER_UNIT, "MSR_RAPL_POWER_UNIT", |_, v, _| {
+        DecodedMsr::RaplPowerUnit(RaplPowerUnit::from_raw(v))
+    }),
+    decoder(IA32_HWP_CAPABILITIES, "IA32_HWP_CAPABILITIES", |_, v, _| {
+        DecodedMsr::HwpCapabilities(HwpCapabilities::from_raw(v))
+    }),
+    decoder(IA32_HWP_REQUEST, "IA32_HWP_REQUEST", |_, v, c| {
+        DecodedMsr::HwpRequest(HwpRequest::from_raw(v, c.epp_supported))
+    }),
+    decoder(IA32_HWP_STATUS, "IA32_HWP_STATUS", |_, v, _| {
+        DecodedMsr::HwpStatus(HwpStatus::from_raw(v))
+    }),
+];
+
+const AMD_DECODERS: &[MsrDecoder] = &[
+    decoder(zen::MSR_RAPL_POWER_UNIT, "MSR_RAPL_PWR_UNIT", |_, v, _| {
+        DecodedMsr::RaplPowerUnit(RaplPowerUnit::from_raw(v))
+    }),
+    decoder(MSR_PSTATE_DEF_BASE, "PStateDef0", pstate),
+    decoder(MSR_PSTATE_DEF_BASE + 1, "PStateDef1", pstate),
+    decoder(MSR_PSTATE_DEF_BASE + 2, "PStateDef2", pstate),
+    decoder(MSR_PSTATE_DEF_BASE + 3, "PStateDef3", pstate),
+    decoder(MSR_PSTATE_DEF_BASE + 4, "PStateDef4", pstate),
+    decoder(MSR_PSTATE_DEF_BASE + 5, "PStateDef5", pstate),
+    decoder(MSR_PSTATE_DEF_BASE + 6, "PStateDef6", pstate),
+    decoder(MSR_PSTATE_DEF_BASE + 7, "PStateDef7", pstate),
+];
+
+/// All decoders that apply to a vendor
+pub fn decoders(vendor: &Vendor) -> &'static [MsrDecoder] {
+    match vendor {
+        Vendor::Intel => INTEL_DECODERS,
+        Vendor::Amd | Vendor::Hygon => AMD_DECODERS,
+        _ => &[],
+    }
+}
+
+/// Find the decoder of a register for a vendor
+pub fn find_decoder(vendor: &Vendor, index: u32) -> Option<&'static MsrDecoder> {
+    decoders(vendor).iter().find(|d| d.index == index)
+}
+
+/// Every known register of a vendor with its decoded value or read error
+pub type MsrDump = Vec<(&'static MsrDecoder, Result<DecodedMsr, String>)>;
+
+/// Read and decode every register known for a vendor. Registers the CPU does
+/// not implement show up as errors.
+pub fn dump(
+    driver: &KernelDriver,
+    affinity: &GroupAffinity,
+    vendor: &Vendor,
+    context: &DecodeContext,
+) -> Result<MsrDump, String> {
+    let decoders = decoders(vendor);
+    let indices = decoders.iter().map(|d| d.index).collect::<Vec<_>>();
+    let values = driver.rdmsr_on(affinity, &indices)?;
+
+    Ok(decoders
+        .iter()
+        .zip(values)
+        .map(|(decoder, value)| {
+            let decoded = value.map(|(eax, edx)| decoder.decode(join(eax, edx), context));
+            (decoder, decoded)
+        })
+        .collect())
+}
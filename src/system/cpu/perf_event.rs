@@ -0,0 +1,119 @@
+use std::{
+    fs::File,
+    io::Read,
+    os::fd::{AsRawFd, FromRawFd},
+    time::Instant,
+};
+
+use crate::system::cpu::counters::CounterSample;
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+const PERF_COUNT_HW_REF_CPU_CYCLES: u64 = 9;
+
+/// Read every counter of a group at once: `nr` followed by the values
+const PERF_FORMAT_GROUP: u64 = 1 << 3;
+
+/// First published layout of `struct perf_event_attr` (PERF_ATTR_SIZE_VER0).
+/// The kernel zero-extends older layouts, so nothing newer is needed here.
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+/// Fixed-function counters of one logical processor through perf_event_open.
+/// Needs CAP_PERFMON or a `perf_event_paranoid` of 0 or lower.
+///
+/// The counters form one group led by cycles, so the kernel schedules them
+/// together and they cover the same time even when multiplexed.
+#[derive(Debug)]
+pub struct PerfCounters {
+    cycles: File,
+    _members: [File; 2],
+}
+
+impl PerfCounters {
+    /// Open and start the counters of `cpu`, counting all processes
+    pub fn open(cpu: usize) -> Result<Self, String> {
+        let cycles = open_counter(cpu, PERF_COUNT_HW_CPU_CYCLES, None)?;
+        let instructions = open_counter(cpu, PERF_COUNT_HW_INSTRUCTIONS, Some(&cycles))?;
+        let ref_cycles = open_counter(cpu, PERF_COUNT_HW_REF_CPU_CYCLES, Some(&cycles))?;
+
+        Ok(Self {
+            cycles,
+            _members: [instructions, ref_cycles],
+        })
+    }
+
+    pub fn read(&mut self) -> Result<CounterSample, String> {
+        // nr, then the values in the order the counters were opened
+        let mut buffer = [0u8; 32];
+        self.cycles
+            .read_exact(&mut buffer)
+            .map_err(|e| format!("Failed to read perf counters: {}", e))?;
+
+        let mut values = buffer
+            .chunks_exact(8)
+            .map(|chunk| u64::from_ne_bytes(chunk.try_into().unwrap_or_default()));
+        if values.next() != Some(3) {
+            return Err("Unexpected perf counter group size".to_string());
+        }
+
+        Ok(CounterSample {
+            cycles: values.next().unwrap_or_default(),
+            instructions: values.next().unwrap_or_default(),
+            ref_cycles: values.next().unwrap_or_default(),
+            width: 64,
+            timestamp: Instant::now(),
+        })
+    }
+}
+
+// Open a counter, as the leader of a new group or as a member of `group`
+fn open_counter(cpu: usize, config: u64, group: Option<&File>) -> Result<File, String> {
+    let attr = PerfEventAttr {
+        type_: PERF_TYPE_HARDWARE,
+        size: std::mem::size_of::<PerfEventAttr>() as u32,
+        config,
+        read_format: if group.is_none() {
+            PERF_FORMAT_GROUP
+        } else {
+            0
+        },
+        ..Default::default()
+    };
+    let group_fd = group.map_or(-1, |leader| leader.as_raw_fd());
+
+    // pid -1 with a cpu counts every process on that cpu
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_perf_event_open,
+            &attr as *const PerfEventAttr,
+            -1 as libc::pid_t,
+            cpu as libc::c_int,
+            group_fd as libc::c_int,
+            0 as libc::c_ulong,
+        )
+    };
+
+    if fd < 0 {
+        return Err(format!(
+            "perf_event_open failed on CPU {}: {}",
+            cpu,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(unsafe { File::from_raw_fd(fd as i32) })
+}
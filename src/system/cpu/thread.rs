@@ -1,11 +1,23 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use crate::system::cpu::{backend::CpuBackend, group_affinity::GroupAffinity};
+use crate::system::{
+    cpu::{
+        backend::{hwp::Hwp, CpuBackend},
+        counters::{CounterDelta, CounterSample, CounterState},
+        group_affinity::{with_affinity, AffinityTarget, GroupAffinity},
+    },
+    kernal_driver::{KernelDriver, MsrRestoreLog},
+};
 
 pub struct Thread {
     pub thread_id: u32,
     pub affinity: GroupAffinity,
     backend: Arc<dyn CpuBackend + Send + Sync>,
+    #[cfg_attr(not(feature = "unsafe-raw-access"), allow(dead_code))]
+    driver: Arc<KernelDriver>,
+    online: bool,
+    counters: Mutex<CounterState>,
+    msr_restore: Arc<MsrRestoreLog>,
 }
 
 impl Thread {
@@ -14,13 +26,88 @@ impl Thread {
         thread_id: u32,
         affinity: GroupAffinity,
         backend: Arc<dyn CpuBackend + Send + Sync>,
+        driver: Arc<KernelDriver>,
+        msr_restore: Arc<MsrRestoreLog>,
     ) -> Self {
         Self {
             thread_id,
             affinity,
             backend,
+            driver,
+            online: true,
+            counters: Mutex::new(CounterState::default()),
+            msr_restore,
         }
     }
+
+    /// False once the logical processor has been taken offline
+    pub fn is_online(&self) -> bool {
+        self.online
+    }
+
+    pub(crate) fn set_online(&mut self, online: bool) {
+        self.online = online;
+    }
+
+    /// Hardware P-state configuration programmed on this thread (Intel only)
+    pub fn hwp(&self) -> Result<Hwp, String> {
+        self.ensure_online()?;
+        self.backend.read_hwp(&self.affinity)
+    }
+
+    /// Cumulative instructions retired, unhalted cycles and reference cycles
+    pub fn read_counters(&self) -> Result<CounterSample, String> {
+        self.ensure_online()?;
+        self.counter_state()?
+            .read(self.backend.as_ref(), &self.affinity, &self.msr_restore)
+    }
+
+    /// Counter activity since the previous call, including IPC.
+    /// The first call starts the measurement and returns `None`.
+    pub fn sample_counters(&self) -> Result<Option<CounterDelta>, String> {
+        self.ensure_online()?;
+        self.counter_state()?
+            .sample(self.backend.as_ref(), &self.affinity, &self.msr_restore)
+    }
+
+    /// Run a closure pinned to this logical processor
+    pub fn run_on<F, R>(&self, f: F) -> Result<R, String>
+    where
+        F: FnOnce() -> Result<R, String>,
+    {
+        self.ensure_online()?;
+        with_affinity(&self.affinity, f)
+    }
+
+    /// Read any MSR on this logical processor as a 64-bit value.
+    /// Unknown indices can fault on some parts; prefer the typed sensors.
+    #[cfg(feature = "unsafe-raw-access")]
+    pub fn read_msr(&self, index: u32) -> Result<u64, String> {
+        self.ensure_online()?;
+        let (eax, edx) = self.driver.rdmsr_tx(index, &self.affinity)?;
+
+        Ok(((edx as u64) << 32) | eax as u64)
+    }
+
+    fn counter_state(&self) -> Result<std::sync::MutexGuard<'_, CounterState>, String> {
+        self.counters
+            .lock()
+            .map_err(|_| "Counter state poisoned".to_string())
+    }
+
+    fn ensure_online(&self) -> Result<(), String> {
+        if !self.online {
+            return Err(format!("Thread {} is offline", self.thread_id));
+        }
+
+        Ok(())
+    }
+}
+
+impl AffinityTarget for Thread {
+    fn target_affinity(&self) -> Option<&GroupAffinity> {
+        self.online.then_some(&self.affinity)
+    }
 }
 
 impl std::fmt::Debug for Thread {
@@ -28,6 +115,7 @@ impl std::fmt::Debug for Thread {
         f.debug_struct("Thread")
             .field("thread_id", &self.thread_id)
             .field("affinity", &self.affinity)
+            .field("online", &self.online)
             .finish()
     }
 }
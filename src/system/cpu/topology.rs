@@ -2,6 +2,9 @@ use raw_cpuid::{CpuId, CpuIdReader, ExtendedTopologyLevel, TopologyType};
 
 use crate::system::cpu::vendor::Vendor;
 
+/// (package id, core id, SMT id, vendor, model) of a logical processor
+pub type TopologyInfo = (u32, u32, u32, Vendor, String);
+
 fn cpuid_bits_needed(count: u8) -> u8 {
     let mut mask: u8 = 0x80;
     let mut cnt: u8 = 8;
@@ -19,7 +22,7 @@ pub fn get_topology_info(
     topoiter: impl Iterator<Item = ExtendedTopologyLevel>,
     vendor: Vendor,
     model: &str,
-) -> Result<(u32, u32, u32, Vendor, String), String> {
+) -> Result<TopologyInfo, String> {
     let topology: Vec<ExtendedTopologyLevel> = topoiter.collect();
 
     let mut smt_x2apic_shift = 0;
@@ -36,9 +39,9 @@ pub fn get_topology_info(
     // Use the first element of the topology vector for x2apic_id
     let x2apic_id = topology.first().map(|l| l.x2apic_id()).unwrap_or(0);
 
-    let smt_select_mask = !(u32::max_value() << smt_x2apic_shift);
-    let core_select_mask = (!((u32::max_value()) << core_x2apic_shift)) ^ smt_select_mask;
-    let pkg_select_mask = u32::max_value() << core_x2apic_shift;
+    let smt_select_mask = !(u32::MAX << smt_x2apic_shift);
+    let core_select_mask = (!((u32::MAX) << core_x2apic_shift)) ^ smt_select_mask;
+    let pkg_select_mask = u32::MAX << core_x2apic_shift;
 
     let smt_id = x2apic_id & smt_select_mask;
     let core_id = (x2apic_id & core_select_mask) >> smt_x2apic_shift;
@@ -57,9 +60,9 @@ pub fn get_legacy_info<R: CpuIdReader>(
     cpuid: &CpuId<R>,
     vendor: Vendor,
     model: &str,
-) -> Result<(u32, u32, u32, Vendor, String), String> {
+) -> Result<TopologyInfo, String> {
     let (max_logical_processor_ids, smt_max_cores_for_package) = match vendor {
-        Vendor::Intel => {
+        Vendor::Intel | Vendor::Centaur | Vendor::Zhaoxin => {
             let cparams = cpuid
                 .get_cache_parameters()
                 .ok_or("Intel CPU: missing cache parameters")?;
@@ -72,13 +75,13 @@ pub fn get_legacy_info<R: CpuIdReader>(
                 .next()
                 .ok_or("Intel CPU: no cache parameter entries")?
                 .max_cores_for_package() as u8;
-            (max_logical as u8, smt_cores)
+            (max_logical, smt_cores)
         }
-        Vendor::Amd => {
+        Vendor::Amd | Vendor::Hygon => {
             let info = cpuid
                 .get_processor_capacity_feature_info()
                 .ok_or("AMD CPU: missing processor capacity info")?;
-            (info.num_phys_threads() as u8, info.apic_id_size() as u8)
+            (info.num_phys_threads() as u8, info.apic_id_size())
         }
         Vendor::Unknown(_) => return Err("Unsupported CPU vendor".to_string()),
     };
@@ -86,11 +89,11 @@ pub fn get_legacy_info<R: CpuIdReader>(
     let smt_mask_width = cpuid_bits_needed(
         (max_logical_processor_ids.next_power_of_two() / smt_max_cores_for_package) - 1,
     );
-    let smt_select_mask = !(u8::max_value() << smt_mask_width);
+    let smt_select_mask = !(u8::MAX << smt_mask_width);
     let core_mask_width = cpuid_bits_needed(smt_max_cores_for_package - 1);
     let core_only_select_mask =
-        (!(u8::max_value() << (core_mask_width + smt_mask_width))) ^ smt_select_mask;
-    let pkg_select_mask = u8::max_value() << (core_mask_width + smt_mask_width);
+        (!(u8::MAX << (core_mask_width + smt_mask_width))) ^ smt_select_mask;
+    let pkg_select_mask = u8::MAX << (core_mask_width + smt_mask_width);
 
     let xapic_id = cpuid
         .get_feature_info()
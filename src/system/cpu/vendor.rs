@@ -4,6 +4,9 @@ use raw_cpuid::{CpuId, CpuIdReader};
 pub enum Vendor {
     Intel,
     Amd,
+    Hygon,
+    Centaur,
+    Zhaoxin,
     Unknown(Option<String>),
 }
 
@@ -13,8 +16,19 @@ pub fn get_vendor<R: CpuIdReader>(cpuid: &CpuId<R>) -> Vendor {
         .get_vendor_info()
         .map(|v| match v.as_str() {
             "GenuineIntel" => Vendor::Intel,
-            "AuthenticAMD" | "HygonGenuine" => Vendor::Amd,
+            "AuthenticAMD" => Vendor::Amd,
+            "HygonGenuine" => Vendor::Hygon,
+            "CentaurHauls" => Vendor::Centaur,
+            "  Shanghai  " => Vendor::Zhaoxin,
             name => Vendor::Unknown(Some(name.to_owned())),
         })
         .unwrap_or(Vendor::Unknown(None))
 }
+
+// Extract (family, model) from CPUID, including the extended fields
+pub fn get_family_model<R: CpuIdReader>(cpuid: &CpuId<R>) -> (u8, u8) {
+    cpuid
+        .get_feature_info()
+        .map(|f| (f.family_id(), f.model_id()))
+        .unwrap_or((0, 0))
+}
@@ -0,0 +1,75 @@
+//! Memory error counters from the Linux EDAC subsystem
+//! (`/sys/devices/system/edac/mc`). The kernel owns the MCA banks on Linux
+//! and clears them, so this is the more reliable source there.
+
+use std::{collections::HashMap, fs, path::Path};
+
+const EDAC_MC_PATH: &str = "/sys/devices/system/edac/mc";
+
+/// New errors on a memory controller since the previous poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdacEvent {
+    pub controller: u32,
+    pub corrected: u64,
+    pub uncorrected: u64,
+}
+
+/// Polls the EDAC memory controller counters
+#[derive(Debug, Default)]
+pub struct EdacMonitor {
+    /// Last seen (ce_count, ue_count) per controller
+    last: HashMap<u32, (u64, u64)>,
+}
+
+impl EdacMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the controllers whose error counters increased since the
+    /// previous poll. The first poll reports all non-zero counters.
+    pub fn poll(&mut self) -> Result<Vec<EdacEvent>, String> {
+        let entries = fs::read_dir(EDAC_MC_PATH)
+            .map_err(|e| format!("Failed to read {}: {}", EDAC_MC_PATH, e))?;
+
+        let mut events = Vec::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(controller) = name
+                .to_str()
+                .and_then(|n| n.strip_prefix("mc"))
+                .and_then(|n| n.parse::<u32>().ok())
+            else {
+                continue;
+            };
+
+            let path = entry.path();
+            let counts = (
+                read_count(&path.join("ce_count"))?,
+                read_count(&path.join("ue_count"))?,
+            );
+
+            let (ce, ue) = self.last.insert(controller, counts).unwrap_or((0, 0));
+            let event = EdacEvent {
+                controller,
+                corrected: counts.0.saturating_sub(ce),
+                uncorrected: counts.1.saturating_sub(ue),
+            };
+
+            if event.corrected > 0 || event.uncorrected > 0 {
+                events.push(event);
+            }
+        }
+
+        events.sort_by_key(|e| e.controller);
+        Ok(events)
+    }
+}
+
+fn read_count(path: &Path) -> Result<u64, String> {
+    fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid count in {}: {}", path.display(), e))
+}
@@ -0,0 +1,304 @@
+use std::cell::RefCell;
+use std::collections::{hash_map::Entry, HashMap};
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use crate::system::{
+    cpu::group_affinity::GroupAffinity, kernal_driver::MsrReadings, pci::PciAddress,
+};
+
+/// Access to MSRs and PCI configuration space through the Linux `msr` driver
+/// (`/dev/cpu/*/msr`) and sysfs. Requires root, or CAP_SYS_RAWIO for MSRs.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut driver = KernelDriver::new();
+/// driver.open().unwrap();
+///
+/// // Read MSR_TEMPERATURE_TARGET on intel CPUs
+/// let (eax, _) = driver.rdmsr_tx(0x1a2, &affinity).unwrap();
+/// let temp_target = (eax >> 16) & 0xff;
+/// ```
+#[derive(Debug, Default)]
+pub struct KernelDriver {
+    opened: AtomicBool,
+    msr_files: Mutex<HashMap<usize, File>>,
+}
+
+impl KernelDriver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Nothing to install, the `msr` module is loaded by the system
+    pub fn install(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Nothing to uninstall
+    pub fn uninstall(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Check that the MSR device of the first processor can be opened
+    pub fn open(&mut self) -> Result<(), String> {
+        if self.opened() {
+            return Err("Driver already opened".to_string());
+        }
+
+        if !Path::new("/dev/cpu/0/msr").exists() {
+            return Err("/dev/cpu/0/msr not found, is the msr module loaded?".to_string());
+        }
+
+        self.with_msr_file(0, |_| Ok(()))?;
+        self.opened.store(true, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// Check to see if the MSR devices are open
+    pub fn opened(&self) -> bool {
+        self.opened.load(Ordering::SeqCst)
+    }
+
+    /// Close all open MSR devices and give up the I/O ports the calling
+    /// thread was granted
+    pub fn close(&self) -> Result<(), String> {
+        if !self.opened() {
+            return Err("Driver not opened".to_string());
+        }
+
+        self.msr_files
+            .lock()
+            .map_err(|_| "MSR file cache poisoned".to_string())?
+            .clear();
+        release_port_access();
+        self.opened.store(false, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// Read MSR on the processor the calling thread is pinned to
+    pub fn rdmsr(&self, index: u32) -> Result<(u32, u32), String> {
+        self.rdmsr_cpu(pinned_cpu()?, index)
+    }
+
+    /// Read MSR on the processor of `affinity`. The MSR device targets the
+    /// processor directly, so the calling thread is not migrated.
+    pub fn rdmsr_tx(&self, index: u32, affinity: &GroupAffinity) -> Result<(u32, u32), String> {
+        self.rdmsr_cpu(affinity.cpu_index(), index)
+    }
+
+    /// Write MSR on the processor the calling thread is pinned to, without
+    /// checking the allowlist.
+    /// Callers are responsible for only touching registers they own.
+    pub(crate) fn wrmsr_unchecked(&self, index: u32, value: u64) -> Result<(), String> {
+        self.wrmsr_cpu(pinned_cpu()?, index, value)
+    }
+
+    /// Write MSR on the processor of `affinity`, without checking the allowlist
+    pub(crate) fn wrmsr_tx_unchecked(
+        &self,
+        index: u32,
+        value: u64,
+        affinity: &GroupAffinity,
+    ) -> Result<(), String> {
+        self.wrmsr_cpu(affinity.cpu_index(), index, value)
+    }
+
+    fn wrmsr_cpu(&self, cpu: usize, index: u32, value: u64) -> Result<(), String> {
+        let path = format!("/dev/cpu/{}/msr", cpu);
+
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path, e))?
+            .write_all_at(&value.to_le_bytes(), index as u64)
+            .map_err(|e| format!("Failed to write MSR {:#x}: {}", index, e))
+    }
+
+    /// Read several MSRs on one logical processor through a single open device
+    pub fn rdmsr_on(
+        &self,
+        affinity: &GroupAffinity,
+        indices: &[u32],
+    ) -> Result<MsrReadings, String> {
+        self.with_msr_file(affinity.cpu_index(), |file| {
+            Ok(indices
+                .iter()
+                .map(|&index| read_msr_file(file, index))
+                .collect())
+        })
+    }
+
+    /// Read `buffer.len()` bytes from PCI configuration space
+    pub fn read_pci_config(
+        &self,
+        address: PciAddress,
+        offset: u16,
+        buffer: &mut [u8],
+    ) -> Result<(), String> {
+        open_pci_config(address, false)?
+            .read_exact_at(buffer, offset as u64)
+            .map_err(|e| format!("Failed to read PCI config of {}: {}", address, e))
+    }
+
+    /// Write `data` to PCI configuration space
+    pub fn write_pci_config(
+        &self,
+        address: PciAddress,
+        offset: u16,
+        data: &[u8],
+    ) -> Result<(), String> {
+        open_pci_config(address, true)?
+            .write_all_at(data, offset as u64)
+            .map_err(|e| format!("Failed to write PCI config of {}: {}", address, e))
+    }
+
+    /// Read `buffer.len()` bytes (1, 2 or 4) from an I/O port
+    pub fn read_io_port(&self, port: u16, buffer: &mut [u8]) -> Result<(), String> {
+        request_port_access(port, buffer.len())?;
+
+        // Safety: the kernel granted access to the port range above
+        unsafe {
+            match buffer.len() {
+                1 => buffer.copy_from_slice(&x86::io::inb(port).to_le_bytes()),
+                2 => buffer.copy_from_slice(&x86::io::inw(port).to_le_bytes()),
+                4 => buffer.copy_from_slice(&x86::io::inl(port).to_le_bytes()),
+                width => return Err(format!("Unsupported I/O port access width {}", width)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write `data` (1, 2 or 4 bytes) to an I/O port
+    pub fn write_io_port(&self, port: u16, data: &[u8]) -> Result<(), String> {
+        request_port_access(port, data.len())?;
+
+        // Safety: the kernel granted access to the port range above
+        unsafe {
+            match *data {
+                [b0] => x86::io::outb(port, b0),
+                [b0, b1] => x86::io::outw(port, u16::from_le_bytes([b0, b1])),
+                [b0, b1, b2, b3] => x86::io::outl(port, u32::from_le_bytes([b0, b1, b2, b3])),
+                _ => return Err(format!("Unsupported I/O port access width {}", data.len())),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rdmsr_cpu(&self, cpu: usize, index: u32) -> Result<(u32, u32), String> {
+        self.with_msr_file(cpu, |file| read_msr_file(file, index))
+    }
+
+    // Run `f` with the (cached) MSR device of `cpu`
+    fn with_msr_file<R>(
+        &self,
+        cpu: usize,
+        f: impl FnOnce(&File) -> Result<R, String>,
+    ) -> Result<R, String> {
+        let mut files = self
+            .msr_files
+            .lock()
+            .map_err(|_| "MSR file cache poisoned".to_string())?;
+
+        let file = match files.entry(cpu) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let path = format!("/dev/cpu/{}/msr", cpu);
+                let file =
+                    File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+                entry.insert(file)
+            }
+        };
+
+        f(file)
+    }
+}
+
+/// The processor the calling thread is pinned to. A thread that may migrate
+/// could read one processor and act on another, so it has to be pinned first,
+/// e.g. with `with_affinity`, or use the `_tx` variants instead.
+fn pinned_cpu() -> Result<usize, String> {
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) } != 0
+    {
+        return Err(format!(
+            "sched_getaffinity failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    if unsafe { libc::CPU_COUNT(&set) } != 1 {
+        return Err("Calling thread is not pinned to a single processor".to_string());
+    }
+
+    (0..libc::CPU_SETSIZE as usize)
+        .find(|&cpu| unsafe { libc::CPU_ISSET(cpu, &set) })
+        .ok_or_else(|| "Empty CPU set".to_string())
+}
+
+fn read_msr_file(file: &File, index: u32) -> Result<(u32, u32), String> {
+    let mut buffer = [0u8; 8];
+    file.read_exact_at(&mut buffer, index as u64)
+        .map_err(|e| format!("Failed to read MSR {:#x}: {}", index, e))?;
+
+    let value = u64::from_le_bytes(buffer);
+    Ok(((value & 0xFFFF_FFFF) as u32, (value >> 32) as u32))
+}
+
+thread_local! {
+    /// Port ranges (first port, width) granted to this thread
+    static GRANTED_PORTS: RefCell<Vec<(u16, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Grant the calling thread a port range with ioperm, once per range. The I/O
+/// bitmap covers every port, so the I/O privilege level is never raised.
+/// Requires CAP_SYS_RAWIO.
+fn request_port_access(port: u16, width: usize) -> Result<(), String> {
+    GRANTED_PORTS.with_borrow_mut(|granted| {
+        if granted.contains(&(port, width)) {
+            return Ok(());
+        }
+
+        if unsafe { libc::ioperm(port as libc::c_ulong, width as libc::c_ulong, 1) } != 0 {
+            return Err(format!(
+                "No access to I/O port {:#x}: {}",
+                port,
+                std::io::Error::last_os_error()
+            ));
+        }
+
+        granted.push((port, width));
+        Ok(())
+    })
+}
+
+// Revoke the ports granted to the calling thread. Other threads keep theirs
+// until they exit.
+fn release_port_access() {
+    GRANTED_PORTS.with_borrow_mut(|granted| {
+        for (port, width) in granted.drain(..) {
+            unsafe { libc::ioperm(port as libc::c_ulong, width as libc::c_ulong, 0) };
+        }
+    });
+}
+
+fn open_pci_config(address: PciAddress, write: bool) -> Result<File, String> {
+    let path = format!("/sys/bus/pci/devices/0000:{}/config", address);
+
+    OpenOptions::new()
+        .read(true)
+        .write(write)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path, e))
+}
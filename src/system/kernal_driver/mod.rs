@@ -0,0 +1,84 @@
+#[cfg(target_os = "linux")]
+mod linux;
+mod msr_write;
+#[cfg(windows)]
+mod winring0;
+
+use std::collections::HashMap;
+
+#[cfg(target_os = "linux")]
+pub use linux::*;
+pub use msr_write::{writable_msrs, MsrGuard, MsrRestoreLog};
+#[cfg(windows)]
+pub use winring0::*;
+
+use crate::system::cpu::group_affinity::GroupAffinity;
+
+/// A set of MSRs to read on one logical processor
+#[derive(Debug, Clone)]
+pub struct MsrRequest {
+    pub affinity: GroupAffinity,
+    pub indices: Vec<u32>,
+}
+
+/// The (eax, edx) value of a single MSR. Each MSR can fail on its own,
+/// e.g. when it is not implemented by the CPU.
+pub type MsrReading = Result<(u32, u32), String>;
+
+/// The values read for an [MsrRequest], in the order of its indices
+pub type MsrReadings = Vec<MsrReading>;
+
+impl KernelDriver {
+    /// Read a batch of MSRs across many logical processors.
+    ///
+    /// Requests for the same logical processor are merged so every processor is
+    /// visited once. The result has one entry per request, in request order.
+    /// A processor that cannot be visited fails only its own readings.
+    pub fn rdmsr_batch(&self, requests: &[MsrRequest]) -> Result<Vec<MsrReadings>, String> {
+        // Group the requested indices per logical processor
+        let mut groups: Vec<(&GroupAffinity, Vec<u32>)> = Vec::new();
+        let mut group_of: HashMap<&GroupAffinity, usize> = HashMap::new();
+
+        for request in requests {
+            let slot = *group_of.entry(&request.affinity).or_insert_with(|| {
+                groups.push((&request.affinity, Vec::new()));
+                groups.len() - 1
+            });
+
+            let indices = &mut groups[slot].1;
+            for index in &request.indices {
+                if !indices.contains(index) {
+                    indices.push(*index);
+                }
+            }
+        }
+
+        // One pinned context per logical processor
+        let mut values: HashMap<(&GroupAffinity, u32), MsrReading> = HashMap::new();
+        for (affinity, indices) in &groups {
+            match self.rdmsr_on(affinity, indices) {
+                Ok(readings) => {
+                    for (index, value) in indices.iter().zip(readings) {
+                        values.insert((*affinity, *index), value);
+                    }
+                }
+                Err(e) => {
+                    for index in indices {
+                        values.insert((*affinity, *index), Err(e.clone()));
+                    }
+                }
+            }
+        }
+
+        Ok(requests
+            .iter()
+            .map(|request| {
+                request
+                    .indices
+                    .iter()
+                    .map(|index| values[&(&request.affinity, *index)].clone())
+                    .collect()
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,212 @@
+//! MSR writes for callers outside the crate.
+//!
+//! Only registers on a per-vendor allowlist can be written: power limits,
+//! performance requests and thermal interrupt thresholds. A [MsrGuard] records
+//! the original value and writes it back when dropped or when the
+//! [MsrRestoreLog] it belongs to is unwound, e.g. by `System::close`.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use raw_cpuid::CpuId;
+use x86::msr::{
+    IA32_ENERGY_PERF_BIAS, IA32_PACKAGE_THERM_INTERRUPT, IA32_THERM_INTERRUPT,
+    MSR_DRAM_POWER_LIMIT, MSR_PKG_POWER_LIMIT, MSR_PP0_POWER_LIMIT, MSR_PP1_POWER_LIMIT,
+};
+
+use crate::system::{
+    cpu::{
+        backend::hwp::IA32_HWP_REQUEST,
+        group_affinity::GroupAffinity,
+        msr::join,
+        vendor::{get_vendor, Vendor},
+    },
+    kernal_driver::KernelDriver,
+};
+
+const IA32_HWP_REQUEST_PKG: u32 = 0x772;
+
+const AMD_PSTATE_CTL: u32 = 0xC001_0062;
+const AMD_CPPC_REQUEST: u32 = 0xC001_02B3;
+
+const INTEL_WRITABLE: &[u32] = &[
+    MSR_PKG_POWER_LIMIT,
+    MSR_DRAM_POWER_LIMIT,
+    MSR_PP0_POWER_LIMIT,
+    MSR_PP1_POWER_LIMIT,
+    IA32_HWP_REQUEST,
+    IA32_HWP_REQUEST_PKG,
+    IA32_ENERGY_PERF_BIAS,
+    IA32_THERM_INTERRUPT,
+    IA32_PACKAGE_THERM_INTERRUPT,
+];
+
+const AMD_WRITABLE: &[u32] = &[AMD_PSTATE_CTL, AMD_CPPC_REQUEST];
+
+/// Registers of a vendor that may be written through the public API
+pub fn writable_msrs(vendor: &Vendor) -> &'static [u32] {
+    match vendor {
+        Vendor::Intel => INTEL_WRITABLE,
+        Vendor::Amd | Vendor::Hygon => AMD_WRITABLE,
+        _ => &[],
+    }
+}
+
+fn check_writable(index: u32) -> Result<(), String> {
+    let vendor = get_vendor(&CpuId::new());
+
+    if !writable_msrs(&vendor).contains(&index) {
+        return Err(format!(
+            "MSR {:#x} is not writable on {:?} processors",
+            index, vendor
+        ));
+    }
+
+    Ok(())
+}
+
+impl KernelDriver {
+    /// Write an allowlisted MSR without changing thread affinity
+    pub fn wrmsr(&self, index: u32, value: u64) -> Result<(), String> {
+        check_writable(index)?;
+        self.wrmsr_unchecked(index, value)
+    }
+
+    /// Write an allowlisted MSR on the processor of `affinity`
+    pub fn wrmsr_tx(&self, index: u32, value: u64, affinity: &GroupAffinity) -> Result<(), String> {
+        check_writable(index)?;
+        self.wrmsr_tx_unchecked(index, value, affinity)
+    }
+}
+
+#[derive(Debug)]
+struct SavedMsr {
+    id: u64,
+    index: u32,
+    affinity: GroupAffinity,
+    original: u64,
+}
+
+/// Original values of every MSR changed through a guard that is still alive
+#[derive(Debug, Default)]
+pub struct MsrRestoreLog {
+    next_id: AtomicU64,
+    saved: Mutex<Vec<SavedMsr>>,
+}
+
+impl MsrRestoreLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write back every recorded value, newest first. Guards dropped afterwards
+    /// no longer touch the register.
+    pub fn restore_all(&self, driver: &KernelDriver) -> Result<(), String> {
+        let saved =
+            std::mem::take(&mut *self.saved.lock().map_err(|_| "MSR restore log poisoned")?);
+
+        let mut result = Ok(());
+        for entry in saved.into_iter().rev() {
+            if let Err(e) = driver.wrmsr_tx_unchecked(entry.index, entry.original, &entry.affinity)
+            {
+                result = Err(format!("Failed to restore MSR {:#x}: {}", entry.index, e));
+            }
+        }
+
+        result
+    }
+
+    fn take(&self, id: u64) -> Option<SavedMsr> {
+        let mut saved = self.saved.lock().ok()?;
+        let position = saved.iter().position(|entry| entry.id == id)?;
+
+        Some(saved.remove(position))
+    }
+}
+
+/// Restores an MSR to its original value when dropped
+#[derive(Debug)]
+pub struct MsrGuard {
+    driver: Arc<KernelDriver>,
+    log: Arc<MsrRestoreLog>,
+    id: u64,
+}
+
+impl MsrGuard {
+    /// Record the current value of an allowlisted MSR, then write `value`
+    pub fn write(
+        driver: &Arc<KernelDriver>,
+        log: &Arc<MsrRestoreLog>,
+        index: u32,
+        value: u64,
+        affinity: &GroupAffinity,
+    ) -> Result<Self, String> {
+        check_writable(index)?;
+        Self::write_unchecked(driver, log, index, value, affinity)
+    }
+
+    /// Like [MsrGuard::write] for registers the crate itself programs, such
+    /// as the performance counter controls, which are not on the allowlist
+    pub(crate) fn write_unchecked(
+        driver: &Arc<KernelDriver>,
+        log: &Arc<MsrRestoreLog>,
+        index: u32,
+        value: u64,
+        affinity: &GroupAffinity,
+    ) -> Result<Self, String> {
+        // A second guard would record the first one's value as the original.
+        // The log stays locked until the new entry is recorded.
+        let mut saved = log.saved.lock().map_err(|_| "MSR restore log poisoned")?;
+        if saved
+            .iter()
+            .any(|entry| entry.index == index && entry.affinity == *affinity)
+        {
+            return Err(format!(
+                "MSR {:#x} is already guarded on {:?}",
+                index, affinity
+            ));
+        }
+
+        let (eax, edx) = driver.rdmsr_tx(index, affinity)?;
+        let original = join(eax, edx);
+
+        driver.wrmsr_tx_unchecked(index, value, affinity)?;
+
+        let id = log.next_id.fetch_add(1, Ordering::Relaxed);
+        saved.push(SavedMsr {
+            id,
+            index,
+            affinity: affinity.clone(),
+            original,
+        });
+
+        Ok(Self {
+            driver: driver.clone(),
+            log: log.clone(),
+            id,
+        })
+    }
+
+    /// Write the original value back now
+    pub fn restore(self) -> Result<(), String> {
+        self.restore_entry()
+    }
+
+    fn restore_entry(&self) -> Result<(), String> {
+        match self.log.take(self.id) {
+            Some(entry) => {
+                self.driver
+                    .wrmsr_tx_unchecked(entry.index, entry.original, &entry.affinity)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for MsrGuard {
+    fn drop(&mut self) {
+        let _ = self.restore_entry();
+    }
+}
@@ -26,6 +26,8 @@ use windows_service::{
 use crate::system::cpu::group_affinity::with_affinity;
 use crate::system::cpu::group_affinity::GroupAffinity;
 use crate::system::ioctl::IOCTL;
+use crate::system::kernal_driver::MsrReadings;
+use crate::system::pci::PciAddress;
 
 /// IO Method
 #[repr(u32)]
@@ -56,7 +58,7 @@ pub enum Access {
 /// * `access`      - The access level (read/write/any) to use
 ///
 /// # Example
-/// ```
+/// ```ignore
 /// let device = 0x00000022; // FILE_DEVICE_UNKNOWN
 /// let function = 0x800; // Some function code defined by the driver
 ///
@@ -75,7 +77,7 @@ pub const fn io_control_code(
 /// Use this to build a kernel driver object you can interact with
 ///
 /// # Example
-/// ```
+/// ```ignore
 /// let driver_bin = include_bytes!("../winRing0.sys");
 /// let driver = DriverBuilder::new()
 ///              .set_device_description("winRing0 driver")
@@ -174,7 +176,7 @@ impl DriverBuilder {
 ///
 /// # Example
 ///
-/// ```
+/// ```ignore
 /// let driver_bin = include_bytes!("../winRing0.sys");
 /// let driver = DriverBuilder::new()
 ///              .set_device_description("winRing0 driver")
@@ -449,6 +451,203 @@ impl KernelDriver {
     pub fn rdmsr_tx(&self, index: u32, affinity: &GroupAffinity) -> Result<(u32, u32), String> {
         with_affinity(affinity, || self.rdmsr(index))
     }
+
+    /// Write MSR without changing thread affinity or checking the allowlist.
+    /// Callers are responsible for only touching registers they own.
+    pub(crate) fn wrmsr_unchecked(&self, index: u32, value: u64) -> Result<(), String> {
+        if !self.opened() {
+            return Err("Driver not opened!".to_string());
+        }
+
+        let input = MsrWriteInput {
+            register: index,
+            value,
+        };
+
+        self.io::<_, ()>(IOCTL::OLS_WRITE_MSR as u32, Some(&input), None)
+    }
+
+    /// Write MSR with a temporary affinity, without checking the allowlist
+    pub(crate) fn wrmsr_tx_unchecked(
+        &self,
+        index: u32,
+        value: u64,
+        affinity: &GroupAffinity,
+    ) -> Result<(), String> {
+        with_affinity(affinity, || self.wrmsr_unchecked(index, value))
+    }
+
+    /// Read several MSRs on one logical processor with a single affinity switch
+    pub fn rdmsr_on(
+        &self,
+        affinity: &GroupAffinity,
+        indices: &[u32],
+    ) -> Result<MsrReadings, String> {
+        with_affinity(affinity, || {
+            Ok(indices.iter().map(|&index| self.rdmsr(index)).collect())
+        })
+    }
+
+    /// Read `buffer.len()` bytes (1, 2 or 4) from PCI configuration space.
+    /// The driver derives the access width from the output buffer size.
+    pub fn read_pci_config(
+        &self,
+        address: PciAddress,
+        offset: u16,
+        buffer: &mut [u8],
+    ) -> Result<(), String> {
+        let input = PciConfigInput {
+            pci_address: address.bdf(),
+            offset: offset as u32,
+        };
+        let code = IOCTL::OLS_READ_PCI_CONFIG as u32;
+
+        match buffer.len() {
+            1 => {
+                let mut value = 0u8;
+                self.io(code, Some(&input), Some(&mut value))?;
+                buffer.copy_from_slice(&value.to_le_bytes());
+            }
+            2 => {
+                let mut value = 0u16;
+                self.io(code, Some(&input), Some(&mut value))?;
+                buffer.copy_from_slice(&value.to_le_bytes());
+            }
+            4 => {
+                let mut value = 0u32;
+                self.io(code, Some(&input), Some(&mut value))?;
+                buffer.copy_from_slice(&value.to_le_bytes());
+            }
+            width => return Err(format!("Unsupported PCI config access width {}", width)),
+        }
+
+        Ok(())
+    }
+
+    /// Write `data` (1, 2 or 4 bytes) to PCI configuration space.
+    /// The driver derives the access width from the input buffer size.
+    pub fn write_pci_config(
+        &self,
+        address: PciAddress,
+        offset: u16,
+        data: &[u8],
+    ) -> Result<(), String> {
+        match *data {
+            [b0] => self.write_pci_value(address, offset, b0),
+            [b0, b1] => self.write_pci_value(address, offset, u16::from_le_bytes([b0, b1])),
+            [b0, b1, b2, b3] => {
+                self.write_pci_value(address, offset, u32::from_le_bytes([b0, b1, b2, b3]))
+            }
+            _ => Err(format!(
+                "Unsupported PCI config access width {}",
+                data.len()
+            )),
+        }
+    }
+
+    /// Read `buffer.len()` bytes (1, 2 or 4) from an I/O port
+    pub fn read_io_port(&self, port: u16, buffer: &mut [u8]) -> Result<(), String> {
+        let input = port as u32;
+
+        match buffer.len() {
+            1 => {
+                let mut value = 0u8;
+                self.io(
+                    IOCTL::OLS_READ_IO_PORT_BYTE as u32,
+                    Some(&input),
+                    Some(&mut value),
+                )?;
+                buffer.copy_from_slice(&value.to_le_bytes());
+            }
+            2 => {
+                let mut value = 0u16;
+                self.io(
+                    IOCTL::OLS_READ_IO_PORT_WORD as u32,
+                    Some(&input),
+                    Some(&mut value),
+                )?;
+                buffer.copy_from_slice(&value.to_le_bytes());
+            }
+            4 => {
+                let mut value = 0u32;
+                self.io(
+                    IOCTL::OLS_READ_IO_PORT_DWORD as u32,
+                    Some(&input),
+                    Some(&mut value),
+                )?;
+                buffer.copy_from_slice(&value.to_le_bytes());
+            }
+            width => return Err(format!("Unsupported I/O port access width {}", width)),
+        }
+
+        Ok(())
+    }
+
+    /// Write `data` (1, 2 or 4 bytes) to an I/O port
+    pub fn write_io_port(&self, port: u16, data: &[u8]) -> Result<(), String> {
+        match *data {
+            [b0] => self.write_port_value(IOCTL::OLS_WRITE_IO_PORT_BYTE, port, b0),
+            [b0, b1] => self.write_port_value(
+                IOCTL::OLS_WRITE_IO_PORT_WORD,
+                port,
+                u16::from_le_bytes([b0, b1]),
+            ),
+            [b0, b1, b2, b3] => self.write_port_value(
+                IOCTL::OLS_WRITE_IO_PORT_DWORD,
+                port,
+                u32::from_le_bytes([b0, b1, b2, b3]),
+            ),
+            _ => Err(format!("Unsupported I/O port access width {}", data.len())),
+        }
+    }
+
+    fn write_port_value<T>(&self, ioctl: IOCTL, port: u16, value: T) -> Result<(), String> {
+        let input = IoPortWriteInput {
+            port: port as u32,
+            value,
+        };
+
+        self.io::<_, ()>(ioctl as u32, Some(&input), None)
+    }
+
+    fn write_pci_value<T>(&self, address: PciAddress, offset: u16, value: T) -> Result<(), String> {
+        let input = PciConfigWriteInput {
+            pci_address: address.bdf(),
+            offset: offset as u32,
+            value,
+        };
+
+        self.io::<_, ()>(IOCTL::OLS_WRITE_PCI_CONFIG as u32, Some(&input), None)
+    }
+}
+
+/// Input buffer of OLS_WRITE_MSR
+#[repr(C, packed(4))]
+struct MsrWriteInput {
+    register: u32,
+    value: u64,
+}
+
+/// Input buffer of OLS_READ_PCI_CONFIG
+#[repr(C)]
+struct PciConfigInput {
+    pci_address: u32,
+    offset: u32,
+}
+
+/// Input buffer of OLS_WRITE_IO_PORT_*, sized to the access width
+#[repr(C, packed)]
+struct IoPortWriteInput<T> {
+    port: u32,
+    value: T,
+}
+
+/// Input buffer of OLS_WRITE_PCI_CONFIG, sized to the access width
+#[repr(C, packed)]
+struct PciConfigWriteInput<T> {
+    pci_address: u32,
+    offset: u32,
+    value: T,
 }
 
 unsafe impl Send for KernelDriver {}
@@ -1,4 +1,12 @@
-mod cpu;
+pub mod cpu;
+#[cfg(target_os = "linux")]
+pub mod edac;
+#[cfg(windows)]
 mod ioctl;
-mod kernal_driver;
+pub mod kernal_driver;
+pub mod motherboard;
+pub mod pci;
+pub mod port_io;
+pub mod superio;
+#[allow(clippy::module_inception)]
 pub mod system;
@@ -0,0 +1,427 @@
+//! Manual fan control.
+//!
+//! A [FanControl] records the mode and duty of an output before switching it
+//! to manual, and puts them back when it is dropped, when the
+//! [FanRestoreLog] it belongs to is unwound (`System::close`), or when any
+//! thread panics. Duties are clamped to a minimum and writes are rate-limited
+//! so a bug in the caller cannot stop a fan or hammer the chip.
+//!
+//! The panic hook is installed when the first log is created and calls the
+//! hook that was set before it. A hook set later replaces it. A panic does not
+//! always end the process, e.g. one caught by a worker pool, so the controls
+//! stay alive and put their duty back on their next write.
+
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Once, Weak,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::system::{
+    motherboard::pwm::{PwmId, PwmOutput},
+    superio::pwm::PwmState,
+};
+
+/// Logs that still have outputs to restore, for the panic hook
+static LIVE_LOGS: Mutex<Vec<Weak<FanRestoreLog>>> = Mutex::new(Vec::new());
+static PANIC_HOOK: Once = Once::new();
+
+/// Bounds of a controlled fan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FanLimits {
+    /// Lowest duty in percent that will be written
+    pub min_duty: f32,
+    /// Shortest time between two writes
+    pub min_interval: Duration,
+}
+
+impl Default for FanLimits {
+    fn default() -> Self {
+        Self {
+            min_duty: 20.0,
+            min_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SavedPwm {
+    id: u64,
+    output: Arc<dyn PwmOutput>,
+    original: PwmState,
+    /// Put back by the panic hook while the control is still alive
+    restored: bool,
+}
+
+/// Original state of every output taken over by a [FanControl] that is
+/// still alive
+#[derive(Debug, Default)]
+pub struct FanRestoreLog {
+    next_id: AtomicU64,
+    saved: Mutex<Vec<SavedPwm>>,
+}
+
+impl FanRestoreLog {
+    /// Create a log and register it with the panic hook
+    pub fn new() -> Arc<Self> {
+        PANIC_HOOK.call_once(install_panic_hook);
+
+        let log = Arc::new(Self::default());
+        if let Ok(mut live) = LIVE_LOGS.lock() {
+            live.retain(|log| log.strong_count() > 0);
+            live.push(Arc::downgrade(&log));
+        }
+
+        log
+    }
+
+    /// Put back every recorded output, newest first. Controls dropped
+    /// afterwards no longer touch the output.
+    pub fn restore_all(&self) -> Result<(), String> {
+        let saved =
+            std::mem::take(&mut *self.saved.lock().map_err(|_| "Fan restore log poisoned")?);
+
+        restore_entries(saved)
+    }
+
+    fn controls(&self, output: &PwmId) -> bool {
+        self.saved
+            .lock()
+            .is_ok_and(|saved| saved.iter().any(|entry| entry.output.id() == *output))
+    }
+
+    fn take(&self, id: u64) -> Option<SavedPwm> {
+        let mut saved = self.saved.lock().ok()?;
+        let position = saved.iter().position(|entry| entry.id == id)?;
+
+        Some(saved.remove(position))
+    }
+}
+
+fn restore_entries(saved: Vec<SavedPwm>) -> Result<(), String> {
+    let mut result = Ok(());
+    for entry in saved.into_iter().rev().filter(|entry| !entry.restored) {
+        if let Err(e) = entry.output.restore(entry.original) {
+            result = Err(format!("Failed to restore {}: {}", entry.output.label(), e));
+        }
+    }
+
+    result
+}
+
+// Restore every live log before the previous hook reports the panic. Locks are
+// only tried, as the panicking thread may hold one of them.
+fn install_panic_hook() {
+    let previous = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        if let Ok(live) = LIVE_LOGS.try_lock() {
+            for log in live.iter().filter_map(Weak::upgrade) {
+                if let Ok(mut saved) = log.saved.try_lock() {
+                    for entry in saved.iter_mut().rev().filter(|entry| !entry.restored) {
+                        let _ = entry.output.restore(entry.original);
+                        entry.restored = true;
+                    }
+                }
+            }
+        }
+
+        previous(info);
+    }));
+}
+
+/// A fan output in manual mode, restored when dropped
+#[derive(Debug)]
+pub struct FanControl {
+    output: Arc<dyn PwmOutput>,
+    log: Arc<FanRestoreLog>,
+    id: u64,
+    limits: FanLimits,
+    last_write: Option<Instant>,
+    /// Duty in percent written last
+    duty: Option<f32>,
+}
+
+impl FanControl {
+    /// Record the current state of an output. It stays in its original mode
+    /// until the first [FanControl::set_duty]. Fails while another control of
+    /// the same output is alive, as it would record that control's state.
+    pub fn take(
+        output: Arc<dyn PwmOutput>,
+        log: &Arc<FanRestoreLog>,
+        limits: FanLimits,
+    ) -> Result<Self, String> {
+        if !(0.0..=100.0).contains(&limits.min_duty) {
+            return Err(format!("Invalid minimum duty {}%", limits.min_duty));
+        }
+
+        // Holding the registry keeps two takes from both finding the output free
+        let live = LIVE_LOGS
+            .lock()
+            .map_err(|_| "Fan restore log registry poisoned")?;
+        let output_id = output.id();
+        let taken = live
+            .iter()
+            .filter_map(Weak::upgrade)
+            .chain([log.clone()])
+            .any(|log| log.controls(&output_id));
+        if taken {
+            return Err(format!("{} is already controlled", output.label()));
+        }
+
+        let original = output.state()?;
+
+        let id = log.next_id.fetch_add(1, Ordering::Relaxed);
+        log.saved
+            .lock()
+            .map_err(|_| "Fan restore log poisoned")?
+            .push(SavedPwm {
+                id,
+                output: output.clone(),
+                original,
+                restored: false,
+            });
+
+        Ok(Self {
+            output,
+            log: log.clone(),
+            id,
+            limits,
+            last_write: None,
+            duty: None,
+        })
+    }
+
+    pub fn label(&self) -> String {
+        self.output.label()
+    }
+
+    pub fn limits(&self) -> FanLimits {
+        self.limits
+    }
+
+    /// Drive the fan at `percent`, raised to the minimum duty. Returns the
+    /// duty in effect: when called again within the minimum interval nothing
+    /// is written and the previous duty is returned.
+    pub fn set_duty(&mut self, percent: f32) -> Result<f32, String> {
+        if percent.is_nan() {
+            return Err("Fan duty is not a number".into());
+        }
+        let percent = percent.clamp(self.limits.min_duty, 100.0);
+
+        // Once restored through the log, e.g. by `System::close`, the output
+        // is no longer ours to drive. Holding the log until the write is done
+        // keeps a concurrent restore from running in between.
+        let mut saved = self
+            .log
+            .saved
+            .lock()
+            .map_err(|_| "Fan restore log poisoned")?;
+        let Some(entry) = saved.iter_mut().find(|entry| entry.id == self.id) else {
+            return Err(format!("{} has been restored", self.output.label()));
+        };
+
+        // After the panic hook put the output back, take it over again at once
+        if let (Some(last), Some(duty), false) = (self.last_write, self.duty, entry.restored) {
+            if last.elapsed() < self.limits.min_interval {
+                return Ok(duty);
+            }
+        }
+
+        let raw = (percent / 100.0 * 255.0).round() as u8;
+        self.output.set_manual(raw)?;
+        entry.restored = false;
+        drop(saved);
+
+        self.last_write = Some(Instant::now());
+        self.duty = Some(percent);
+        Ok(percent)
+    }
+
+    /// Duty in percent last written, `None` before the first write
+    pub fn duty(&self) -> Option<f32> {
+        self.duty
+    }
+
+    /// Duty in percent the output is driving right now, read back from it
+    pub fn output_duty(&self) -> Result<f32, String> {
+        Ok(self.output.duty()? as f32 / 255.0 * 100.0)
+    }
+
+    /// Put the original mode and duty back now
+    pub fn restore(self) -> Result<(), String> {
+        self.restore_entry()
+    }
+
+    fn restore_entry(&self) -> Result<(), String> {
+        match self.log.take(self.id) {
+            Some(entry) => entry.output.restore(entry.original),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for FanControl {
+    fn drop(&mut self) {
+        let _ = self.restore_entry();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{
+        motherboard::pwm::SuperIoPwm,
+        port_io::FakePortIo,
+        superio::{FakeHwm, HwmBank, SuperIoChip, SuperIoFamily},
+    };
+
+    // The panic hook restores the logs of every test, so they run one at a time
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// Mode and manual duty of the first NCT6798D output
+    const MODE: u16 = 0x102;
+    const COMMAND: u16 = 0x109;
+
+    fn nuvoton_output() -> (Arc<dyn PwmOutput>, FakeHwm) {
+        let hwm = FakeHwm::new(SuperIoFamily::Nuvoton, 0x0290);
+        hwm.set(MODE, 0x50);
+        hwm.set(COMMAND, 0x60);
+
+        let io = Arc::new(FakePortIo::new());
+        io.attach(hwm.ports(), hwm.clone());
+
+        let chip = SuperIoChip {
+            name: "NCT6798D",
+            family: SuperIoFamily::Nuvoton,
+            id: 0xD428,
+            config_port: 0x2E,
+            hwm: HwmBank::new(SuperIoFamily::Nuvoton, 0x0290),
+        };
+        (Arc::new(SuperIoPwm::new(io, chip, 0)), hwm)
+    }
+
+    fn limits(min_duty: f32, min_interval: Duration) -> FanLimits {
+        FanLimits {
+            min_duty,
+            min_interval,
+        }
+    }
+
+    #[test]
+    fn restores_on_drop() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (output, hwm) = nuvoton_output();
+        let log = FanRestoreLog::new();
+
+        let mut control = FanControl::take(output, &log, FanLimits::default()).unwrap();
+        // Nothing is written before the first duty
+        assert_eq!(hwm.get(MODE), 0x50);
+
+        assert_eq!(control.set_duty(50.0), Ok(50.0));
+        assert_eq!(hwm.get(MODE), 0x00);
+        assert_eq!(hwm.get(COMMAND), 128);
+
+        drop(control);
+        assert_eq!(hwm.get(MODE), 0x50);
+        assert_eq!(hwm.get(COMMAND), 0x60);
+    }
+
+    #[test]
+    fn restore_all_ends_control() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (output, hwm) = nuvoton_output();
+        let log = FanRestoreLog::new();
+
+        let mut control = FanControl::take(output, &log, FanLimits::default()).unwrap();
+        control.set_duty(80.0).unwrap();
+
+        // What System::close does
+        log.restore_all().unwrap();
+        assert_eq!(hwm.get(MODE), 0x50);
+        assert_eq!(hwm.get(COMMAND), 0x60);
+
+        assert!(control.set_duty(80.0).is_err());
+        // A dropped control no longer touches the output
+        hwm.set(MODE, 0x20);
+        drop(control);
+        assert_eq!(hwm.get(MODE), 0x20);
+    }
+
+    #[test]
+    fn clamps_to_min_duty() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (output, hwm) = nuvoton_output();
+        let log = FanRestoreLog::new();
+
+        let mut control = FanControl::take(output, &log, limits(30.0, Duration::ZERO)).unwrap();
+
+        assert_eq!(control.set_duty(5.0), Ok(30.0));
+        assert_eq!(hwm.get(COMMAND), 77);
+        assert_eq!(control.set_duty(150.0), Ok(100.0));
+        assert_eq!(hwm.get(COMMAND), 255);
+        assert!(control.set_duty(f32::NAN).is_err());
+
+        let (other, _) = nuvoton_output();
+        assert!(FanControl::take(other, &log, limits(120.0, Duration::ZERO)).is_err());
+    }
+
+    #[test]
+    fn rate_limits_writes() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (output, hwm) = nuvoton_output();
+        let log = FanRestoreLog::new();
+
+        let mut control =
+            FanControl::take(output, &log, limits(20.0, Duration::from_secs(3600))).unwrap();
+
+        assert_eq!(control.set_duty(40.0), Ok(40.0));
+        assert_eq!(control.set_duty(90.0), Ok(40.0));
+        assert_eq!(hwm.get(COMMAND), 102);
+        assert_eq!(control.duty(), Some(40.0));
+    }
+
+    #[test]
+    fn rejects_second_control_of_an_output() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (output, _hwm) = nuvoton_output();
+        let log = FanRestoreLog::new();
+        let other_log = FanRestoreLog::new();
+
+        let control = FanControl::take(output.clone(), &log, FanLimits::default()).unwrap();
+        assert!(FanControl::take(output.clone(), &log, FanLimits::default()).is_err());
+        assert!(FanControl::take(output.clone(), &other_log, FanLimits::default()).is_err());
+
+        drop(control);
+        assert!(FanControl::take(output, &other_log, FanLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn reapplies_duty_after_caught_panic() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (output, hwm) = nuvoton_output();
+        let log = FanRestoreLog::new();
+
+        let mut control =
+            FanControl::take(output, &log, limits(20.0, Duration::from_secs(3600))).unwrap();
+        control.set_duty(40.0).unwrap();
+
+        let caught = std::thread::spawn(|| panic::catch_unwind(|| panic!("worker panic")))
+            .join()
+            .unwrap();
+        assert!(caught.is_err());
+        assert_eq!(hwm.get(MODE), 0x50);
+        assert_eq!(hwm.get(COMMAND), 0x60);
+
+        // Written again despite the rate limit
+        assert_eq!(control.set_duty(40.0), Ok(40.0));
+        assert_eq!(hwm.get(MODE), 0x00);
+        assert_eq!(hwm.get(COMMAND), 102);
+
+        drop(control);
+        assert_eq!(hwm.get(MODE), 0x50);
+    }
+}
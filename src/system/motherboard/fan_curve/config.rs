@@ -0,0 +1,319 @@
+//! Fan curve configuration files.
+//!
+//! ```text
+//! # Seconds between updates, for all curves
+//! interval = 2
+//!
+//! [cpu]
+//! source = cpu
+//! output = NCT6798D PWM #2
+//! points = 40:25, 60:45, 75:80, 85:100
+//! hysteresis = 3
+//! max_step = 10
+//! spin_up = 80 for 2
+//! fallback = 100
+//! min_duty = 20
+//! ```
+//!
+//! `interval` may not be shorter than the write rate limit of a fan control.
+//! `source` is `cpu` or `cpu.<package id>` for a package temperature, or
+//! `board.<label>` for a motherboard temperature. `output` is the label of a
+//! PWM output. `points` pairs a temperature with a duty in percent;
+//! `spin_up` is a duty in percent and a time in seconds. Everything after the
+//! points is optional.
+
+use std::{fs, path::Path, time::Duration};
+
+use crate::system::motherboard::{
+    fan_control::FanLimits,
+    fan_curve::curve::{FanCurve, SpinUp},
+};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_HYSTERESIS: f32 = 2.0;
+
+/// One curve of a configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurveConfig {
+    pub name: String,
+    pub source: String,
+    pub output: String,
+    pub curve: FanCurve,
+    pub min_duty: f32,
+}
+
+/// All curves of a configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanCurveConfig {
+    pub interval: Duration,
+    pub curves: Vec<CurveConfig>,
+}
+
+#[derive(Default)]
+struct Section {
+    name: String,
+    line: usize,
+    source: Option<String>,
+    output: Option<String>,
+    points: Option<Vec<(f32, f32)>>,
+    hysteresis: Option<f32>,
+    max_step: Option<f32>,
+    spin_up: Option<SpinUp>,
+    fallback: Option<f32>,
+    min_duty: Option<f32>,
+}
+
+impl FanCurveConfig {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut interval = DEFAULT_INTERVAL;
+        let mut sections: Vec<Section> = Vec::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let number = index + 1;
+            // Only whole-line comments, output labels contain '#'
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                sections.push(Section {
+                    name: name.trim().to_owned(),
+                    line: number,
+                    ..Default::default()
+                });
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("Line {}: expected key = value", number))?;
+            let error = |e: String| format!("Line {}: {}", number, e);
+
+            let Some(section) = sections.last_mut() else {
+                match key {
+                    "interval" => interval = parse_interval(value).map_err(error)?,
+                    _ => return Err(error(format!("unknown setting {}", key))),
+                }
+                continue;
+            };
+
+            match key {
+                "source" => section.source = Some(value.to_owned()),
+                "output" => section.output = Some(value.to_owned()),
+                "points" => section.points = Some(parse_points(value).map_err(error)?),
+                "hysteresis" => section.hysteresis = Some(parse_number(value).map_err(error)?),
+                "max_step" => section.max_step = Some(parse_number(value).map_err(error)?),
+                "spin_up" => section.spin_up = Some(parse_spin_up(value).map_err(error)?),
+                "fallback" => section.fallback = Some(parse_number(value).map_err(error)?),
+                "min_duty" => section.min_duty = Some(parse_number(value).map_err(error)?),
+                _ => return Err(error(format!("unknown setting {}", key))),
+            }
+        }
+
+        let curves = sections
+            .into_iter()
+            .map(Section::finish)
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self { interval, curves })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        Self::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+impl Section {
+    fn finish(self) -> Result<CurveConfig, String> {
+        let missing =
+            |key: &str| format!("Curve {} (line {}) has no {}", self.name, self.line, key);
+
+        let curve = FanCurve {
+            points: self.points.clone().ok_or_else(|| missing("points"))?,
+            hysteresis: self.hysteresis.unwrap_or(DEFAULT_HYSTERESIS),
+            max_step: self.max_step,
+            spin_up: self.spin_up,
+            fallback_duty: self.fallback.unwrap_or(100.0),
+        };
+        curve
+            .validate()
+            .map_err(|e| format!("Curve {}: {}", self.name, e))?;
+
+        let min_duty = self.min_duty.unwrap_or(FanLimits::default().min_duty);
+        if !(0.0..=100.0).contains(&min_duty) {
+            return Err(format!(
+                "Curve {}: invalid minimum duty {}",
+                self.name, min_duty
+            ));
+        }
+
+        Ok(CurveConfig {
+            source: self.source.clone().ok_or_else(|| missing("source"))?,
+            output: self.output.clone().ok_or_else(|| missing("output"))?,
+            name: self.name,
+            curve,
+            min_duty,
+        })
+    }
+}
+
+fn parse_number(value: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|n| n.is_finite())
+        .ok_or_else(|| format!("invalid number {}", value))
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    Duration::try_from_secs_f32(parse_number(value)?)
+        .ok()
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(|| format!("invalid time {}", value))
+}
+
+fn parse_interval(value: &str) -> Result<Duration, String> {
+    let interval = parse_seconds(value)?;
+    let limit = FanLimits::default().min_interval;
+    if interval < limit {
+        return Err(format!(
+            "interval {} is below the fan write limit of {:?}",
+            value, limit
+        ));
+    }
+
+    Ok(interval)
+}
+
+// "40:25, 60:45"
+fn parse_points(value: &str) -> Result<Vec<(f32, f32)>, String> {
+    value
+        .split(',')
+        .map(|point| {
+            let (celsius, duty) = point
+                .split_once(':')
+                .ok_or_else(|| format!("invalid point {}", point.trim()))?;
+            Ok((parse_number(celsius.trim())?, parse_number(duty.trim())?))
+        })
+        .collect()
+}
+
+// "80 for 2"
+fn parse_spin_up(value: &str) -> Result<SpinUp, String> {
+    let (duty, seconds) = value
+        .split_once(" for ")
+        .ok_or_else(|| format!("expected <duty> for <seconds>, got {}", value))?;
+
+    Ok(SpinUp {
+        duty: parse_number(duty.trim())?,
+        duration: parse_seconds(seconds.trim())?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+# Seconds between updates, for all curves
+interval = 2
+
+[cpu]
+source = cpu
+output = NCT6798D PWM #2
+points = 40:25, 60:45, 75:80, 85:100
+hysteresis = 3
+max_step = 10
+spin_up = 80 for 1.5
+fallback = 95
+min_duty = 15
+
+[case]
+source = board.System
+output = NCT6798D PWM #1
+points = 30:20, 50:60
+";
+
+    #[test]
+    fn parses_curves_and_defaults() {
+        let config = FanCurveConfig::parse(CONFIG).unwrap();
+
+        assert_eq!(config.interval, Duration::from_secs(2));
+        assert_eq!(config.curves.len(), 2);
+
+        let cpu = &config.curves[0];
+        assert_eq!(cpu.name, "cpu");
+        assert_eq!(cpu.source, "cpu");
+        assert_eq!(cpu.output, "NCT6798D PWM #2");
+        assert_eq!(
+            cpu.curve.points,
+            [(40.0, 25.0), (60.0, 45.0), (75.0, 80.0), (85.0, 100.0)]
+        );
+        assert_eq!(cpu.curve.hysteresis, 3.0);
+        assert_eq!(cpu.curve.max_step, Some(10.0));
+        assert_eq!(
+            cpu.curve.spin_up,
+            Some(SpinUp {
+                duty: 80.0,
+                duration: Duration::from_millis(1500),
+            })
+        );
+        assert_eq!(cpu.curve.fallback_duty, 95.0);
+        assert_eq!(cpu.min_duty, 15.0);
+
+        let case = &config.curves[1];
+        assert_eq!(case.source, "board.System");
+        assert_eq!(case.curve.hysteresis, DEFAULT_HYSTERESIS);
+        assert_eq!(case.curve.max_step, None);
+        assert_eq!(case.curve.spin_up, None);
+        assert_eq!(case.curve.fallback_duty, 100.0);
+        assert_eq!(case.min_duty, FanLimits::default().min_duty);
+    }
+
+    #[test]
+    fn defaults_interval() {
+        let config = FanCurveConfig::parse("").unwrap();
+
+        assert_eq!(config.interval, DEFAULT_INTERVAL);
+        assert!(config.curves.is_empty());
+    }
+
+    #[test]
+    fn reports_line_of_bad_settings() {
+        let unknown = FanCurveConfig::parse("[cpu]\nsource = cpu\nspeed = 3\n").unwrap_err();
+        let number = FanCurveConfig::parse("[cpu]\nhysteresis = warm\n").unwrap_err();
+        let spin_up = FanCurveConfig::parse("[cpu]\nspin_up = 80\n").unwrap_err();
+        let syntax = FanCurveConfig::parse("interval 2\n").unwrap_err();
+
+        assert!(unknown.starts_with("Line 3:"), "{}", unknown);
+        assert!(number.starts_with("Line 2:"), "{}", number);
+        assert!(spin_up.starts_with("Line 2:"), "{}", spin_up);
+        assert!(syntax.starts_with("Line 1:"), "{}", syntax);
+    }
+
+    #[test]
+    fn rejects_incomplete_or_invalid_curves() {
+        let no_output = FanCurveConfig::parse("[cpu]\nsource = cpu\npoints = 40:30\n");
+        let no_points = FanCurveConfig::parse("[cpu]\nsource = cpu\noutput = PWM\n");
+        let unsorted =
+            FanCurveConfig::parse("[cpu]\nsource = cpu\noutput = PWM\npoints = 60:40, 40:20\n");
+        let min_duty = FanCurveConfig::parse(
+            "[cpu]\nsource = cpu\noutput = PWM\npoints = 40:30\nmin_duty = 150\n",
+        );
+        let interval = FanCurveConfig::parse("interval = 0\n");
+        let fast = FanCurveConfig::parse("interval = 0.001\n");
+
+        assert!(no_output.unwrap_err().contains("has no output"));
+        assert!(no_points.unwrap_err().contains("has no points"));
+        assert!(unsorted.is_err());
+        assert!(min_duty.is_err());
+        assert!(interval.is_err());
+        assert!(fast.unwrap_err().starts_with("Line 1:"));
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::system::motherboard::{
+    fan_control::FanControl,
+    fan_curve::curve::{CurveState, FanCurve},
+};
+
+/// Reads a temperature in degrees Celsius
+pub type TemperatureSource = Box<dyn Fn() -> Result<f32, String> + Send>;
+
+/// A curve with the temperature it follows and the fan it drives
+pub struct BoundCurve {
+    pub name: String,
+    pub source: TemperatureSource,
+    pub curve: FanCurve,
+    pub control: FanControl,
+}
+
+/// Outcome of the latest update of a curve
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CurveStatus {
+    pub name: String,
+    pub temperature: Option<f32>,
+    /// Duty in effect in percent
+    pub duty: Option<f32>,
+    /// Why the temperature could not be read or the duty not set
+    pub error: Option<String>,
+}
+
+/// Runs fan curves on a background thread. The fans go back to their
+/// original mode when the controller is stopped or dropped.
+#[derive(Debug)]
+pub struct FanCurveController {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+    status: Arc<Mutex<Vec<CurveStatus>>>,
+}
+
+impl FanCurveController {
+    /// Start updating every curve each `interval`
+    pub fn start(curves: Vec<BoundCurve>, interval: Duration) -> Result<Self, String> {
+        let status = Arc::new(Mutex::new(
+            curves
+                .iter()
+                .map(|c| CurveStatus {
+                    name: c.name.clone(),
+                    ..Default::default()
+                })
+                .collect(),
+        ));
+        let (stop, stopped) = mpsc::channel();
+
+        let thread_status = status.clone();
+        let thread = thread::Builder::new()
+            .name("fan-curves".into())
+            .spawn(move || run(curves, interval, stopped, thread_status))
+            .map_err(|e| format!("Failed to start fan curve thread: {}", e))?;
+
+        Ok(Self {
+            stop: Some(stop),
+            thread: Some(thread),
+            status,
+        })
+    }
+
+    pub fn status(&self) -> Vec<CurveStatus> {
+        self.status
+            .lock()
+            .map(|status| status.clone())
+            .unwrap_or_default()
+    }
+
+    /// Stop the thread and wait until the fans are restored
+    pub fn stop(mut self) -> Result<(), String> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<(), String> {
+        // Disconnecting the channel wakes the thread
+        self.stop.take();
+
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| "Fan curve thread panicked".to_string()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for FanCurveController {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+fn run(
+    mut curves: Vec<BoundCurve>,
+    interval: Duration,
+    stopped: mpsc::Receiver<()>,
+    status: Arc<Mutex<Vec<CurveStatus>>>,
+) {
+    let mut states: Vec<CurveState> = curves
+        .iter()
+        .map(|c| CurveState::new(c.curve.clone()))
+        .collect();
+
+    loop {
+        for (index, (curve, state)) in curves.iter_mut().zip(&mut states).enumerate() {
+            let reading = (curve.source)();
+            // Until the first write the fan runs at whatever its original mode drives
+            let current = curve
+                .control
+                .duty()
+                .or_else(|| curve.control.output_duty().ok());
+            let duty = state.update(reading.clone(), current, Instant::now());
+            let applied = curve.control.set_duty(duty);
+
+            if let Ok(mut status) = status.lock() {
+                let entry = &mut status[index];
+                entry.temperature = reading.as_ref().ok().copied();
+                entry.duty = applied.as_ref().ok().copied().or(entry.duty);
+                entry.error = reading.err().or(applied.err());
+            }
+        }
+
+        match stopped.recv_timeout(interval) {
+            Err(RecvTimeoutError::Timeout) => continue,
+            _ => break,
+        }
+    }
+
+    // Dropping the controls restores the fans
+    drop(curves);
+}
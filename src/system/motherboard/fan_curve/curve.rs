@@ -0,0 +1,271 @@
+use std::time::{Duration, Instant};
+
+/// Short burst at a high duty that gets a stopped fan turning
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpinUp {
+    pub duty: f32,
+    pub duration: Duration,
+}
+
+/// Temperature to duty mapping and how the duty may move
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanCurve {
+    /// (degrees Celsius, duty percent), sorted by temperature. Between points
+    /// the duty is interpolated, outside them it is held at the end points.
+    pub points: Vec<(f32, f32)>,
+    /// Degrees the temperature must fall below the one that set the duty
+    /// before the duty is lowered
+    pub hysteresis: f32,
+    /// Largest change of the duty per update in percent
+    pub max_step: Option<f32>,
+    pub spin_up: Option<SpinUp>,
+    /// Duty used while the temperature cannot be read
+    pub fallback_duty: f32,
+}
+
+impl FanCurve {
+    /// Check that the curve has points, sorted by temperature, with duties
+    /// between 0 and 100
+    pub fn validate(&self) -> Result<(), String> {
+        if self.points.is_empty() {
+            return Err("Fan curve has no points".into());
+        }
+        if self.points.windows(2).any(|w| w[0].0 >= w[1].0) {
+            return Err("Fan curve temperatures must increase".into());
+        }
+
+        let duties = self.points.iter().map(|&(_, duty)| duty);
+        let spin_up = self.spin_up.map(|s| s.duty);
+        if duties
+            .chain(spin_up)
+            .chain([self.fallback_duty])
+            .any(|duty| !(0.0..=100.0).contains(&duty))
+        {
+            return Err("Fan curve duties must be between 0 and 100".into());
+        }
+
+        if self.hysteresis < 0.0 || self.max_step.is_some_and(|step| step <= 0.0) {
+            return Err("Fan curve hysteresis and step must be positive".into());
+        }
+
+        Ok(())
+    }
+
+    /// Duty of the curve at a temperature
+    pub fn duty_at(&self, celsius: f32) -> f32 {
+        let (Some(&first), Some(&last)) = (self.points.first(), self.points.last()) else {
+            return self.fallback_duty;
+        };
+
+        if celsius <= first.0 {
+            return first.1;
+        }
+        if celsius >= last.0 {
+            return last.1;
+        }
+
+        self.points
+            .windows(2)
+            .find(|w| celsius <= w[1].0)
+            .map(|w| {
+                let ((t0, d0), (t1, d1)) = (w[0], w[1]);
+                d0 + (d1 - d0) * (celsius - t0) / (t1 - t0)
+            })
+            .unwrap_or(last.1)
+    }
+}
+
+/// A curve together with the state it needs between updates
+#[derive(Debug, Clone)]
+pub struct CurveState {
+    pub curve: FanCurve,
+    /// Temperature that set the current target
+    anchor: Option<f32>,
+    target: f32,
+    duty: Option<f32>,
+    boost_until: Option<Instant>,
+}
+
+impl CurveState {
+    pub fn new(curve: FanCurve) -> Self {
+        Self {
+            curve,
+            anchor: None,
+            target: 0.0,
+            duty: None,
+            boost_until: None,
+        }
+    }
+
+    /// Duty to apply for a temperature reading taken at `now`. `applied` is
+    /// the duty in percent the fan is running at, `None` when it is unknown;
+    /// only a fan known to be stopped gets the spin-up boost.
+    pub fn update(
+        &mut self,
+        reading: Result<f32, String>,
+        applied: Option<f32>,
+        now: Instant,
+    ) -> f32 {
+        let Ok(celsius) = reading else {
+            // Go straight to the fallback, and start over once readings return
+            self.anchor = None;
+            self.boost_until = None;
+            self.duty = Some(self.curve.fallback_duty);
+            return self.curve.fallback_duty;
+        };
+
+        let rising = self.anchor.is_none_or(|anchor| celsius > anchor);
+        let fallen = self
+            .anchor
+            .is_some_and(|anchor| celsius <= anchor - self.curve.hysteresis);
+        if rising || fallen {
+            self.anchor = Some(celsius);
+            self.target = self.curve.duty_at(celsius);
+        }
+
+        if let Some(spin_up) = self.curve.spin_up {
+            let boosting = self.boost_until.is_some_and(|until| now < until);
+            if !boosting && applied == Some(0.0) && self.target > 0.0 {
+                self.boost_until = Some(now + spin_up.duration);
+            }
+            if self.boost_until.is_some_and(|until| now < until) {
+                let duty = spin_up.duty.max(self.target);
+                self.duty = Some(duty);
+                return duty;
+            }
+        }
+        self.boost_until = None;
+
+        let duty = match (self.duty, self.curve.max_step) {
+            (Some(duty), Some(step)) => self.target.clamp(duty - step, duty + step),
+            _ => self.target,
+        };
+
+        self.duty = Some(duty);
+        duty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> FanCurve {
+        FanCurve {
+            points: vec![(40.0, 20.0), (60.0, 40.0), (80.0, 100.0)],
+            hysteresis: 3.0,
+            max_step: None,
+            spin_up: None,
+            fallback_duty: 90.0,
+        }
+    }
+
+    #[test]
+    fn interpolates_and_holds_end_points() {
+        let curve = curve();
+
+        assert_eq!(curve.duty_at(20.0), 20.0);
+        assert_eq!(curve.duty_at(50.0), 30.0);
+        assert_eq!(curve.duty_at(70.0), 70.0);
+        assert_eq!(curve.duty_at(95.0), 100.0);
+    }
+
+    #[test]
+    fn rejects_invalid_curves() {
+        let unsorted = FanCurve {
+            points: vec![(60.0, 40.0), (40.0, 20.0)],
+            ..curve()
+        };
+        let out_of_range = FanCurve {
+            fallback_duty: 120.0,
+            ..curve()
+        };
+        let empty = FanCurve {
+            points: Vec::new(),
+            ..curve()
+        };
+
+        assert!(curve().validate().is_ok());
+        assert!(unsorted.validate().is_err());
+        assert!(out_of_range.validate().is_err());
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn lowers_duty_only_past_hysteresis() {
+        let mut state = CurveState::new(curve());
+        let now = Instant::now();
+
+        assert_eq!(state.update(Ok(60.0), Some(50.0), now), 40.0);
+        assert_eq!(state.update(Ok(58.0), Some(40.0), now), 40.0);
+        assert_eq!(state.update(Ok(57.0), Some(40.0), now), 37.0);
+        assert_eq!(state.update(Ok(59.0), Some(37.0), now), 39.0);
+    }
+
+    #[test]
+    fn limits_step_per_update() {
+        let mut state = CurveState::new(FanCurve {
+            max_step: Some(10.0),
+            ..curve()
+        });
+        let now = Instant::now();
+
+        assert_eq!(state.update(Ok(40.0), Some(20.0), now), 20.0);
+        assert_eq!(state.update(Ok(80.0), Some(20.0), now), 30.0);
+        assert_eq!(state.update(Ok(80.0), Some(30.0), now), 40.0);
+    }
+
+    #[test]
+    fn falls_back_while_unreadable() {
+        let mut state = CurveState::new(curve());
+        let now = Instant::now();
+
+        assert_eq!(state.update(Ok(50.0), Some(30.0), now), 30.0);
+        assert_eq!(state.update(Err("timeout".into()), Some(30.0), now), 90.0);
+        // Starts over instead of holding the fallback through the hysteresis
+        assert_eq!(state.update(Ok(50.0), Some(90.0), now), 30.0);
+    }
+
+    #[test]
+    fn spins_up_stopped_fan() {
+        let spin_up = SpinUp {
+            duty: 80.0,
+            duration: Duration::from_secs(2),
+        };
+        let mut state = CurveState::new(FanCurve {
+            spin_up: Some(spin_up),
+            ..curve()
+        });
+        let start = Instant::now();
+
+        assert_eq!(state.update(Ok(50.0), Some(0.0), start), 80.0);
+        assert_eq!(
+            state.update(Ok(50.0), Some(80.0), start + Duration::from_secs(1)),
+            80.0
+        );
+        assert_eq!(
+            state.update(Ok(50.0), Some(80.0), start + Duration::from_secs(3)),
+            30.0
+        );
+    }
+
+    #[test]
+    fn no_spin_up_for_running_or_unknown_fan() {
+        let spin_up = SpinUp {
+            duty: 80.0,
+            duration: Duration::from_secs(2),
+        };
+        let curve = FanCurve {
+            spin_up: Some(spin_up),
+            ..curve()
+        };
+        let now = Instant::now();
+
+        // First update of a fan that was already running
+        let mut running = CurveState::new(curve.clone());
+        assert_eq!(running.update(Ok(50.0), Some(45.0), now), 30.0);
+
+        let mut unknown = CurveState::new(curve);
+        assert_eq!(unknown.update(Ok(50.0), None, now), 30.0);
+    }
+}
@@ -0,0 +1,15 @@
+//! Temperature-driven fan control.
+//!
+//! Each curve maps a temperature source to a PWM output. The duty follows a
+//! piecewise-linear curve, only drops after the temperature fell by the
+//! hysteresis, moves by at most the step limit per update, starts stopped
+//! fans with a spin-up boost and falls back to a fixed duty while the
+//! temperature cannot be read.
+
+pub mod config;
+pub mod controller;
+pub mod curve;
+
+pub use config::{CurveConfig, FanCurveConfig};
+pub use controller::{BoundCurve, CurveStatus, FanCurveController, TemperatureSource};
+pub use curve::{CurveState, FanCurve, SpinUp};
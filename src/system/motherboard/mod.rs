@@ -0,0 +1,16 @@
+pub mod fan_control;
+pub mod fan_curve;
+#[allow(clippy::module_inception)]
+pub mod motherboard;
+pub mod profile;
+pub mod pwm;
+pub mod smbios;
+
+pub use fan_control::{FanControl, FanLimits, FanRestoreLog};
+pub use fan_curve::{FanCurveConfig, FanCurveController};
+pub use motherboard::{gather_motherboard, FanSpeed, Motherboard, SensorReading};
+pub use profile::{find_profile, BoardProfile, ChannelProfile};
+#[cfg(target_os = "linux")]
+pub use pwm::{chip_hwmon_device, chip_hwmon_outputs, hwmon_pwm_outputs, HwmonPwm};
+pub use pwm::{PwmId, PwmOutput, SuperIoPwm};
+pub use smbios::{read_board_info, BoardInfo};
@@ -0,0 +1,318 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::system::{
+    kernal_driver::KernelDriver,
+    motherboard::{
+        profile::{find_profile, BoardProfile},
+        pwm::{PwmOutput, SuperIoPwm},
+        smbios::{read_board_info, BoardInfo},
+    },
+    port_io::PortIo,
+    superio::{
+        detect,
+        fan::{fan_count, read_fans},
+        pwm::pwm_count,
+        temperature::read_temperatures,
+        voltage::read_voltages,
+        SuperIoChip,
+    },
+};
+
+/// Speed of one fan header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FanSpeed {
+    /// Index of the chip in [Motherboard::chips]
+    pub chip: usize,
+    /// Header on the chip, starting at 0
+    pub channel: usize,
+    /// `None` when the header reports no usable count
+    pub rpm: Option<f32>,
+}
+
+impl FanSpeed {
+    pub fn is_spinning(&self) -> bool {
+        self.rpm.is_some_and(|rpm| rpm > 0.0)
+    }
+}
+
+/// A labelled voltage or temperature input
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorReading {
+    /// Index of the chip in [Motherboard::chips]
+    pub chip: usize,
+    /// Input on the chip, starting at 0
+    pub channel: usize,
+    pub label: String,
+    /// Volts or degrees Celsius, `None` when the input has no reading
+    pub value: Option<f32>,
+}
+
+/// Sensors of the motherboard's Super I/O chips
+#[derive(Debug)]
+pub struct Motherboard {
+    driver: Arc<KernelDriver>,
+    /// Baseboard as reported by SMBIOS
+    pub board: Option<BoardInfo>,
+    pub chips: Vec<SuperIoChip>,
+    /// Input labels and scaling, one per chip
+    profiles: Vec<&'static BoardProfile>,
+    /// hwmon directory of the Linux driver bound to each chip. The driver
+    /// owns the registers of those chips, so they are not accessed directly.
+    owners: Vec<Option<PathBuf>>,
+    /// (chip, channel) of every fan that was spinning when gathered
+    connected_fans: Vec<(usize, usize)>,
+}
+
+impl Motherboard {
+    /// Current speed of every fan header, read through the hwmon driver when
+    /// one owns the chip
+    pub fn fans(&self) -> Result<Vec<FanSpeed>, String> {
+        let mut fans = Vec::new();
+
+        for (chip, superio) in self.chips.iter().enumerate() {
+            let readings = match &self.owners[chip] {
+                Some(path) => hwmon_fans(path, fan_count(superio)),
+                None => read_fans(self.driver.as_ref(), superio)?,
+            };
+            fans.extend(
+                readings
+                    .into_iter()
+                    .enumerate()
+                    .map(|(channel, rpm)| FanSpeed { chip, channel, rpm }),
+            );
+        }
+
+        Ok(fans)
+    }
+
+    /// Board voltages, scaled and labelled by the board profile. Inputs the
+    /// profile hides and chips owned by a hwmon driver are left out.
+    pub fn voltages(&self) -> Result<Vec<SensorReading>, String> {
+        let mut voltages = Vec::new();
+
+        for (chip, superio) in self.chips.iter().enumerate() {
+            if self.owners[chip].is_some() {
+                continue;
+            }
+            let profile = self.profiles[chip];
+
+            for (channel, volts) in read_voltages(self.driver.as_ref(), superio)?
+                .into_iter()
+                .enumerate()
+            {
+                let (label, value) = match profile.voltage(channel) {
+                    Some(input) if input.hidden => continue,
+                    Some(input) => (input.label.to_owned(), volts.map(|v| input.apply(v))),
+                    None => (format!("Voltage #{}", channel + 1), volts),
+                };
+
+                voltages.push(SensorReading {
+                    chip,
+                    channel,
+                    label,
+                    value,
+                });
+            }
+        }
+
+        Ok(voltages)
+    }
+
+    /// Board temperatures (system, chipset, VRM), labelled by the board
+    /// profile or by the source the chip reports. Chips owned by a hwmon
+    /// driver are left out.
+    pub fn temperatures(&self) -> Result<Vec<SensorReading>, String> {
+        let mut temperatures = Vec::new();
+
+        for (chip, superio) in self.chips.iter().enumerate() {
+            if self.owners[chip].is_some() {
+                continue;
+            }
+            temperatures.extend(chip_temperatures(
+                self.driver.as_ref(),
+                chip,
+                superio,
+                self.profiles[chip],
+            )?);
+        }
+
+        Ok(temperatures)
+    }
+
+    /// Reader for the board temperature with `label` (case-insensitive), for
+    /// use outside of the motherboard, e.g. on a fan curve thread
+    pub fn temperature_source(
+        &self,
+        label: &str,
+    ) -> Result<impl Fn() -> Result<f32, String> + Send + 'static, String> {
+        let reading = self
+            .temperatures()?
+            .into_iter()
+            .find(|t| t.label.eq_ignore_ascii_case(label))
+            .ok_or_else(|| format!("No board temperature named {}", label))?;
+
+        let driver = self.driver.clone();
+        let superio = self.chips[reading.chip];
+        let profile = self.profiles[reading.chip];
+
+        Ok(move || {
+            chip_temperatures(driver.as_ref(), reading.chip, &superio, profile)?
+                .into_iter()
+                .find(|t| t.channel == reading.channel)
+                .and_then(|t| t.value)
+                .ok_or_else(|| format!("{} has no reading", reading.label))
+        })
+    }
+
+    /// Input labels and scaling in use for a chip
+    pub fn profile(&self, chip: usize) -> Option<&'static BoardProfile> {
+        self.profiles.get(chip).copied()
+    }
+
+    /// Fan outputs that can be put under manual control. On Linux a chip that
+    /// is bound to its hwmon driver is controlled through that driver, as it
+    /// owns the chip registers.
+    pub fn pwm_outputs(&self) -> Vec<Arc<dyn PwmOutput>> {
+        self.chips
+            .iter()
+            .flat_map(|chip| {
+                #[cfg(target_os = "linux")]
+                if let Some(hwmon) = crate::system::motherboard::pwm::chip_hwmon_outputs(chip) {
+                    return hwmon
+                        .into_iter()
+                        .map(|output| Arc::new(output) as Arc<dyn PwmOutput>)
+                        .collect::<Vec<_>>();
+                }
+
+                (0..pwm_count(chip))
+                    .map(|channel| {
+                        Arc::new(SuperIoPwm::new(self.driver.clone(), *chip, channel))
+                            as Arc<dyn PwmOutput>
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Fans that were spinning when the subsystem was gathered but have
+    /// stopped or no longer report a speed
+    pub fn stalled_fans(&self) -> Result<Vec<FanSpeed>, String> {
+        Ok(self
+            .fans()?
+            .into_iter()
+            .filter(|fan| {
+                self.connected_fans.contains(&(fan.chip, fan.channel)) && !fan.is_spinning()
+            })
+            .collect())
+    }
+}
+
+// fanN_input of a hwmon driver, `None` when an input is missing or unreadable
+fn hwmon_fans(path: &Path, count: usize) -> Vec<Option<f32>> {
+    (1..=count)
+        .map(|index| {
+            fs::read_to_string(path.join(format!("fan{}_input", index)))
+                .ok()
+                .and_then(|rpm| rpm.trim().parse().ok())
+        })
+        .collect()
+}
+
+fn chip_temperatures(
+    io: &dyn PortIo,
+    chip: usize,
+    superio: &SuperIoChip,
+    profile: &BoardProfile,
+) -> Result<Vec<SensorReading>, String> {
+    let mut temperatures = Vec::new();
+
+    for (channel, input) in read_temperatures(io, superio)?.into_iter().enumerate() {
+        let (label, value) = match profile.temperature(channel) {
+            Some(entry) if entry.hidden => continue,
+            Some(entry) => (
+                entry.label.to_owned(),
+                input.celsius.map(|c| entry.apply(c)),
+            ),
+            None => (
+                input
+                    .source
+                    .map_or_else(|| format!("Temperature #{}", channel + 1), str::to_owned),
+                input.celsius,
+            ),
+        };
+
+        temperatures.push(SensorReading {
+            chip,
+            channel,
+            label,
+            value,
+        });
+    }
+
+    Ok(temperatures)
+}
+
+/// Detect the Super I/O chips and note which fan headers are in use.
+///
+/// On Linux a chip bound to the `it87`, `nct6775` or `f71882fg` hwmon driver
+/// is only read through that driver after detection, as its bank selects
+/// would interleave with ours.
+pub fn gather_motherboard(driver: &Arc<KernelDriver>) -> Result<Motherboard, String> {
+    let chips = detect(driver.as_ref());
+    if chips.is_empty() {
+        return Err("No supported Super I/O chip found".into());
+    }
+
+    #[cfg(target_os = "linux")]
+    let owners = chips
+        .iter()
+        .map(crate::system::motherboard::pwm::chip_hwmon_device)
+        .collect();
+    #[cfg(not(target_os = "linux"))]
+    let owners = vec![None; chips.len()];
+
+    let board = read_board_info();
+    let profiles = chips
+        .iter()
+        .map(|chip| find_profile(board.as_ref(), chip))
+        .collect();
+
+    let mut motherboard = Motherboard {
+        driver: driver.clone(),
+        board,
+        chips,
+        profiles,
+        owners,
+        connected_fans: Vec::new(),
+    };
+    motherboard.connected_fans = motherboard
+        .fans()?
+        .iter()
+        .filter(|fan| fan.is_spinning())
+        .map(|fan| (fan.chip, fan.channel))
+        .collect();
+
+    Ok(motherboard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_fans_of_driver_owned_chip() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("fan1_input"), "1250\n").unwrap();
+        fs::write(dir.path().join("fan2_input"), "0\n").unwrap();
+        fs::write(dir.path().join("fan4_input"), "n/a\n").unwrap();
+
+        assert_eq!(
+            hwmon_fans(dir.path(), 4),
+            vec![Some(1250.0), Some(0.0), None, None]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Labels and scaling of Super I/O inputs per motherboard.
+//!
+//! Rails above the ADC range reach the chip through a resistor divider, and
+//! which rail is wired to which input is up to the board vendor. A profile
+//! names the inputs of one board and undoes its dividers; boards without a
+//! profile get generic labels per chip family.
+
+use crate::system::{
+    motherboard::smbios::BoardInfo,
+    superio::{SuperIoChip, SuperIoFamily},
+};
+
+/// Label and scaling of one input. The reported value is
+/// `raw * multiplier + offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelProfile {
+    pub channel: usize,
+    pub label: &'static str,
+    pub multiplier: f32,
+    pub offset: f32,
+    /// Unconnected or meaningless on this board
+    pub hidden: bool,
+}
+
+impl ChannelProfile {
+    pub fn apply(&self, raw: f32) -> f32 {
+        raw * self.multiplier + self.offset
+    }
+}
+
+const fn input(channel: usize, label: &'static str) -> ChannelProfile {
+    scaled(channel, label, 1.0)
+}
+
+const fn scaled(channel: usize, label: &'static str, multiplier: f32) -> ChannelProfile {
+    ChannelProfile {
+        channel,
+        label,
+        multiplier,
+        offset: 0.0,
+        hidden: false,
+    }
+}
+
+/// Input behind a divider of `top` over `bottom` ohms
+const fn divided(channel: usize, label: &'static str, top: f32, bottom: f32) -> ChannelProfile {
+    scaled(channel, label, 1.0 + top / bottom)
+}
+
+const fn hidden(channel: usize) -> ChannelProfile {
+    ChannelProfile {
+        channel,
+        label: "",
+        multiplier: 1.0,
+        offset: 0.0,
+        hidden: true,
+    }
+}
+
+/// Inputs of one Super I/O chip on one board
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardProfile {
+    /// Matched case-insensitively as a prefix of the SMBIOS board vendor
+    pub vendor: &'static str,
+    /// Matched exactly against the SMBIOS board name
+    pub model: &'static str,
+    pub chip: &'static str,
+    pub voltages: &'static [ChannelProfile],
+    pub temperatures: &'static [ChannelProfile],
+}
+
+impl BoardProfile {
+    pub fn voltage(&self, channel: usize) -> Option<&ChannelProfile> {
+        self.voltages.iter().find(|c| c.channel == channel)
+    }
+
+    pub fn temperature(&self, channel: usize) -> Option<&ChannelProfile> {
+        self.temperatures.iter().find(|c| c.channel == channel)
+    }
+
+    fn matches(&self, board: &BoardInfo, chip: &SuperIoChip) -> bool {
+        self.chip == chip.name
+            && self.model == board.model
+            && board
+                .vendor
+                .to_lowercase()
+                .starts_with(&self.vendor.to_lowercase())
+    }
+}
+
+const GENERIC_ITE: BoardProfile = BoardProfile {
+    vendor: "",
+    model: "",
+    chip: "",
+    voltages: &[input(0, "Vcore"), input(7, "3VSB"), input(8, "VBAT")],
+    temperatures: &[],
+};
+
+const GENERIC_NUVOTON: BoardProfile = BoardProfile {
+    vendor: "",
+    model: "",
+    chip: "",
+    voltages: &[
+        input(0, "Vcore"),
+        input(2, "AVCC"),
+        input(3, "+3.3V"),
+        input(7, "3VSB"),
+        input(8, "VBAT"),
+        input(9, "VTT"),
+    ],
+    temperatures: &[],
+};
+
+const GENERIC_FINTEK: BoardProfile = BoardProfile {
+    vendor: "",
+    model: "",
+    chip: "",
+    voltages: &[
+        input(0, "+3.3V"),
+        input(1, "Vcore"),
+        input(7, "3VSB"),
+        input(8, "VBAT"),
+    ],
+    temperatures: &[],
+};
+
+const BOARD_PROFILES: &[BoardProfile] = &[
+    BoardProfile {
+        vendor: "ASUSTeK",
+        model: "ROG STRIX Z690-A GAMING WIFI D4",
+        chip: "NCT6798D",
+        voltages: &[
+            input(0, "Vcore"),
+            divided(1, "+5V", 4.0, 1.0),
+            input(2, "AVCC"),
+            input(3, "+3.3V"),
+            divided(4, "+12V", 11.0, 1.0),
+            hidden(5),
+            hidden(6),
+            input(7, "3VSB"),
+            input(8, "VBAT"),
+            input(9, "VTT"),
+            hidden(10),
+            hidden(11),
+            hidden(12),
+            hidden(13),
+            hidden(14),
+        ],
+        temperatures: &[
+            hidden(0),
+            input(1, "System"),
+            input(2, "CPU Socket"),
+            input(3, "VRM"),
+        ],
+    },
+    BoardProfile {
+        vendor: "Micro-Star",
+        model: "MAG B550 TOMAHAWK (MS-7C91)",
+        chip: "NCT6797D",
+        voltages: &[
+            input(0, "Vcore"),
+            divided(1, "+5V", 4.0, 1.0),
+            input(2, "AVCC"),
+            input(3, "+3.3V"),
+            divided(4, "+12V", 11.0, 1.0),
+            input(5, "CPU SoC"),
+            scaled(6, "DRAM", 2.0),
+            input(7, "3VSB"),
+            input(8, "VBAT"),
+            hidden(9),
+            input(10, "CPU VDDP"),
+            hidden(11),
+            hidden(12),
+            hidden(13),
+            hidden(14),
+        ],
+        temperatures: &[
+            input(1, "System"),
+            input(2, "CPU Socket"),
+            input(3, "VRM MOS"),
+            input(4, "Chipset"),
+        ],
+    },
+    BoardProfile {
+        vendor: "Gigabyte",
+        model: "Z390 AORUS PRO",
+        chip: "IT8688E",
+        voltages: &[
+            input(0, "Vcore"),
+            divided(1, "+3.3V", 6.49, 10.0),
+            divided(2, "+12V", 5.0, 1.0),
+            divided(3, "+5V", 1.5, 1.0),
+            input(4, "CPU VCCSA"),
+            input(5, "CPU VCCIO"),
+            scaled(6, "DRAM", 2.0),
+            input(7, "3VSB"),
+            input(8, "VBAT"),
+        ],
+        temperatures: &[input(0, "System"), input(1, "PCH"), input(2, "CPU Socket")],
+    },
+];
+
+/// Profile for a chip on a board, or the generic one of its family
+pub fn find_profile(board: Option<&BoardInfo>, chip: &SuperIoChip) -> &'static BoardProfile {
+    board
+        .and_then(|board| BOARD_PROFILES.iter().find(|p| p.matches(board, chip)))
+        .unwrap_or(match chip.family {
+            SuperIoFamily::Ite => &GENERIC_ITE,
+            SuperIoFamily::Nuvoton => &GENERIC_NUVOTON,
+            SuperIoFamily::Fintek => &GENERIC_FINTEK,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::superio::HwmBank;
+
+    fn chip(name: &'static str, family: SuperIoFamily) -> SuperIoChip {
+        SuperIoChip {
+            name,
+            family,
+            id: 0,
+            config_port: 0x2E,
+            hwm: HwmBank::new(family, 0x0290),
+        }
+    }
+
+    fn board(vendor: &str, model: &str) -> BoardInfo {
+        BoardInfo {
+            vendor: vendor.into(),
+            model: model.into(),
+        }
+    }
+
+    #[test]
+    fn matches_vendor_prefix_model_and_chip() {
+        let nct6797 = chip("NCT6797D", SuperIoFamily::Nuvoton);
+        let msi = board(
+            "Micro-Star International Co., Ltd.",
+            "MAG B550 TOMAHAWK (MS-7C91)",
+        );
+
+        let profile = find_profile(Some(&msi), &nct6797);
+        assert_eq!(profile.model, "MAG B550 TOMAHAWK (MS-7C91)");
+        assert_eq!(profile.voltage(5).map(|c| c.label), Some("CPU SoC"));
+
+        // Vendor is case-insensitive
+        let gigabyte = board("GIGABYTE", "Z390 AORUS PRO");
+        let it8688 = chip("IT8688E", SuperIoFamily::Ite);
+        assert_eq!(find_profile(Some(&gigabyte), &it8688).chip, "IT8688E");
+    }
+
+    #[test]
+    fn falls_back_to_family_profile() {
+        let msi = board(
+            "Micro-Star International Co., Ltd.",
+            "MAG B550 TOMAHAWK (MS-7C91)",
+        );
+        let other_chip = chip("NCT6798D", SuperIoFamily::Nuvoton);
+        let other_model = board("Micro-Star International Co., Ltd.", "MAG B550M MORTAR");
+        let nct6797 = chip("NCT6797D", SuperIoFamily::Nuvoton);
+
+        assert_eq!(find_profile(Some(&msi), &other_chip), &GENERIC_NUVOTON);
+        assert_eq!(find_profile(Some(&other_model), &nct6797), &GENERIC_NUVOTON);
+        assert_eq!(
+            find_profile(None, &chip("F71889F", SuperIoFamily::Fintek)),
+            &GENERIC_FINTEK
+        );
+        assert_eq!(
+            find_profile(None, &chip("IT8686E", SuperIoFamily::Ite)),
+            &GENERIC_ITE
+        );
+    }
+
+    #[test]
+    fn applies_dividers() {
+        let plus_12v = divided(4, "+12V", 11.0, 1.0);
+        let plus_3v3 = divided(1, "+3.3V", 6.49, 10.0);
+
+        assert!((plus_12v.apply(1.0) - 12.0).abs() < 1e-4);
+        assert!((plus_3v3.apply(2.0) - 3.298).abs() < 1e-4);
+        assert_eq!(hidden(5).apply(1.5), 1.5);
+    }
+}
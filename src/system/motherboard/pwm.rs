@@ -0,0 +1,419 @@
+use std::{fmt, sync::Arc};
+
+#[cfg(target_os = "linux")]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[cfg(target_os = "linux")]
+use crate::system::superio::SuperIoFamily;
+use crate::system::{
+    port_io::PortIo,
+    superio::{
+        pwm::{read_duty, read_pwm, write_manual, write_pwm, PwmState},
+        SuperIoChip,
+    },
+};
+
+/// The physical output behind a [PwmOutput]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PwmId {
+    SuperIo {
+        config_port: u16,
+        channel: usize,
+    },
+    #[cfg(target_os = "linux")]
+    Hwmon {
+        path: PathBuf,
+        index: usize,
+    },
+}
+
+/// A fan output whose duty cycle can be set
+pub trait PwmOutput: fmt::Debug + Send + Sync {
+    fn id(&self) -> PwmId;
+
+    fn label(&self) -> String;
+
+    /// Mode and duty to put back with [PwmOutput::restore]
+    fn state(&self) -> Result<PwmState, String>;
+
+    /// Duty out of 255 the fan is driven at right now, whatever the mode
+    fn duty(&self) -> Result<u8, String>;
+
+    /// Switch to manual mode and drive the fan at `duty` out of 255
+    fn set_manual(&self, duty: u8) -> Result<(), String>;
+
+    /// Put back a state returned by [PwmOutput::state]
+    fn restore(&self, state: PwmState) -> Result<(), String>;
+}
+
+/// PWM output driven through the Super I/O registers
+pub struct SuperIoPwm {
+    io: Arc<dyn PortIo + Send + Sync>,
+    chip: SuperIoChip,
+    channel: usize,
+}
+
+impl SuperIoPwm {
+    pub fn new(io: Arc<dyn PortIo + Send + Sync>, chip: SuperIoChip, channel: usize) -> Self {
+        Self { io, chip, channel }
+    }
+}
+
+impl fmt::Debug for SuperIoPwm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SuperIoPwm")
+            .field("chip", &self.chip)
+            .field("channel", &self.channel)
+            .finish()
+    }
+}
+
+impl PwmOutput for SuperIoPwm {
+    fn id(&self) -> PwmId {
+        PwmId::SuperIo {
+            config_port: self.chip.config_port,
+            channel: self.channel,
+        }
+    }
+
+    fn label(&self) -> String {
+        format!("{} PWM #{}", self.chip.name, self.channel + 1)
+    }
+
+    fn state(&self) -> Result<PwmState, String> {
+        read_pwm(self.io.as_ref(), &self.chip, self.channel)
+    }
+
+    fn duty(&self) -> Result<u8, String> {
+        read_duty(self.io.as_ref(), &self.chip, self.channel)
+    }
+
+    fn set_manual(&self, duty: u8) -> Result<(), String> {
+        write_manual(self.io.as_ref(), &self.chip, self.channel, duty)
+    }
+
+    fn restore(&self, state: PwmState) -> Result<(), String> {
+        write_pwm(self.io.as_ref(), &self.chip, self.channel, state)
+    }
+}
+
+/// `pwmN_enable` value of manual control
+#[cfg(target_os = "linux")]
+const HWMON_MANUAL: u8 = 1;
+
+/// PWM output of a Linux hwmon driver (`pwmN` and `pwmN_enable`)
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone)]
+pub struct HwmonPwm {
+    /// hwmon device directory
+    path: PathBuf,
+    name: String,
+    /// N of `pwmN`, starting at 1
+    index: usize,
+}
+
+#[cfg(target_os = "linux")]
+impl HwmonPwm {
+    fn read(&self, suffix: &str) -> Result<u8, String> {
+        let path = self.path.join(format!("pwm{}{}", self.index, suffix));
+        fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .trim()
+            .parse()
+            .map_err(|e| format!("Invalid value in {}: {}", path.display(), e))
+    }
+
+    fn write(&self, suffix: &str, value: u8) -> Result<(), String> {
+        let path = self.path.join(format!("pwm{}{}", self.index, suffix));
+        fs::write(&path, value.to_string())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl PwmOutput for HwmonPwm {
+    fn id(&self) -> PwmId {
+        PwmId::Hwmon {
+            path: self.path.clone(),
+            index: self.index,
+        }
+    }
+
+    fn label(&self) -> String {
+        format!("{} pwm{}", self.name, self.index)
+    }
+
+    fn state(&self) -> Result<PwmState, String> {
+        Ok(PwmState {
+            mode: self.read("_enable")?,
+            duty: self.read("")?,
+        })
+    }
+
+    fn duty(&self) -> Result<u8, String> {
+        self.read("")
+    }
+
+    fn set_manual(&self, duty: u8) -> Result<(), String> {
+        self.write("_enable", HWMON_MANUAL)?;
+        self.write("", duty)
+    }
+
+    fn restore(&self, state: PwmState) -> Result<(), String> {
+        // Write the duty while still in manual mode. In automatic modes the
+        // driver owns it, so only the mode has to be written back.
+        let duty = self.write("", state.duty);
+        self.write("_enable", state.mode)?;
+
+        if state.mode == HWMON_MANUAL {
+            duty
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// PWM outputs with a mode switch exported by the loaded hwmon drivers
+#[cfg(target_os = "linux")]
+pub fn hwmon_pwm_outputs() -> Vec<HwmonPwm> {
+    hwmon_devices()
+        .iter()
+        .flat_map(|path| device_outputs(path))
+        .collect()
+}
+
+/// PWM outputs of the hwmon driver bound to a chip, `None` when no driver
+/// owns it
+#[cfg(target_os = "linux")]
+pub fn chip_hwmon_outputs(chip: &SuperIoChip) -> Option<Vec<HwmonPwm>> {
+    chip_hwmon_device(chip).map(|path| device_outputs(&path))
+}
+
+/// hwmon directory of the driver bound to a chip. The it87, nct6775 and
+/// f71882fg drivers register a platform device named after themselves and
+/// the base address of the hardware monitor.
+#[cfg(target_os = "linux")]
+pub fn chip_hwmon_device(chip: &SuperIoChip) -> Option<PathBuf> {
+    let driver = match chip.family {
+        SuperIoFamily::Ite => "it87",
+        SuperIoFamily::Nuvoton => "nct6775",
+        SuperIoFamily::Fintek => "f71882fg",
+    };
+    let device = format!("{}.{}", driver, chip.hwm.base);
+
+    hwmon_devices().into_iter().find(|path| {
+        fs::read_link(path.join("device"))
+            .is_ok_and(|link| link.file_name().is_some_and(|name| name == device.as_str()))
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn hwmon_devices() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir("/sys/class/hwmon") else {
+        return Vec::new();
+    };
+
+    let mut devices: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    devices.sort();
+    devices
+}
+
+#[cfg(target_os = "linux")]
+fn device_outputs(path: &Path) -> Vec<HwmonPwm> {
+    let name = fs::read_to_string(path.join("name"))
+        .map(|s| s.trim().to_owned())
+        .unwrap_or_default();
+
+    let mut outputs = Vec::new();
+    let mut index = 1;
+    while path.join(format!("pwm{}", index)).exists() {
+        if path.join(format!("pwm{}_enable", index)).exists() {
+            outputs.push(HwmonPwm {
+                path: path.to_path_buf(),
+                name: name.clone(),
+                index,
+            });
+        }
+        index += 1;
+    }
+
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{
+        port_io::FakePortIo,
+        superio::{FakeHwm, HwmBank, SuperIoFamily},
+    };
+
+    fn superio_pwm(
+        name: &'static str,
+        family: SuperIoFamily,
+        channel: usize,
+    ) -> (SuperIoPwm, FakeHwm) {
+        let hwm = FakeHwm::new(family, 0x0A40);
+        let io = Arc::new(FakePortIo::new());
+        io.attach(hwm.ports(), hwm.clone());
+
+        let chip = SuperIoChip {
+            name,
+            family,
+            id: 0,
+            config_port: 0x2E,
+            hwm: HwmBank::new(family, 0x0A40),
+        };
+        (SuperIoPwm::new(io, chip, channel), hwm)
+    }
+
+    // Switch to manual and back, then compare every register touched
+    fn round_trip(pwm: &SuperIoPwm, hwm: &FakeHwm, registers: &[u16]) {
+        let before: Vec<u8> = registers.iter().map(|&r| hwm.get(r)).collect();
+
+        let original = pwm.state().unwrap();
+        pwm.set_manual(0x40).unwrap();
+        pwm.restore(original).unwrap();
+
+        let after: Vec<u8> = registers.iter().map(|&r| hwm.get(r)).collect();
+        assert_eq!(before, after, "{}", pwm.label());
+    }
+
+    #[test]
+    fn ite_round_trip() {
+        let (pwm, hwm) = superio_pwm("IT8686E", SuperIoFamily::Ite, 1);
+        hwm.set(0x16, 0x80);
+        hwm.set(0x6B, 0x99);
+
+        pwm.set_manual(0x40).unwrap();
+        assert_eq!((hwm.get(0x16), hwm.get(0x6B)), (0x00, 0x40));
+        pwm.restore(PwmState {
+            mode: 0x80,
+            duty: 0x99,
+        })
+        .unwrap();
+
+        round_trip(&pwm, &hwm, &[0x16, 0x6B]);
+
+        // 7-bit duty in the control register
+        let (old, hwm) = superio_pwm("IT8712F", SuperIoFamily::Ite, 0);
+        hwm.set(0x15, 0x80 | 0x30);
+        round_trip(&old, &hwm, &[0x15]);
+    }
+
+    #[test]
+    fn nuvoton_restores_command_not_output() {
+        let (pwm, hwm) = superio_pwm("NCT6798D", SuperIoFamily::Nuvoton, 0);
+        hwm.set(0x102, 0x50);
+        hwm.set(0x109, 0x60);
+        // Duty the SmartFan mode drives, different from the manual command
+        hwm.set(0x001, 0xC0);
+
+        assert_eq!(pwm.duty(), Ok(0xC0));
+        assert_eq!(pwm.state().unwrap().duty, 0x60);
+
+        round_trip(&pwm, &hwm, &[0x102, 0x109, 0x001]);
+    }
+
+    #[test]
+    fn fintek_keeps_other_outputs_mode() {
+        let (pwm, hwm) = superio_pwm("F71889F", SuperIoFamily::Fintek, 1);
+        hwm.set(0x96, 0b01_10_01);
+        hwm.set(0xB3, 0x33);
+
+        let original = pwm.state().unwrap();
+        pwm.set_manual(0x40).unwrap();
+        assert_eq!(hwm.get(0x96), 0b01_11_01);
+        assert_eq!(hwm.get(0xB3), 0x40);
+
+        // Another output switched to manual meanwhile
+        hwm.set(0x96, hwm.get(0x96) | 0b11);
+        pwm.restore(original).unwrap();
+        assert_eq!(hwm.get(0x96), 0b01_10_11);
+        assert_eq!(hwm.get(0xB3), 0x33);
+    }
+
+    #[test]
+    fn identifies_outputs() {
+        let (pwm, _) = superio_pwm("NCT6798D", SuperIoFamily::Nuvoton, 2);
+
+        assert_eq!(
+            pwm.id(),
+            PwmId::SuperIo {
+                config_port: 0x2E,
+                channel: 2
+            }
+        );
+        assert_eq!(pwm.label(), "NCT6798D PWM #3");
+    }
+
+    #[cfg(target_os = "linux")]
+    fn hwmon_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |file: &str, value: &str| fs::write(dir.path().join(file), value).unwrap();
+
+        write("name", "nct6798\n");
+        write("pwm1", "153\n");
+        write("pwm1_enable", "5\n");
+        // No mode switch, not controllable
+        write("pwm2", "255\n");
+        write("pwm3", "80\n");
+        write("pwm3_enable", "1\n");
+        dir
+    }
+
+    #[cfg(target_os = "linux")]
+    fn read(dir: &tempfile::TempDir, file: &str) -> String {
+        fs::read_to_string(dir.path().join(file)).unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn lists_hwmon_outputs_with_mode_switch() {
+        let dir = hwmon_tree();
+
+        let outputs = device_outputs(dir.path());
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].label(), "nct6798 pwm1");
+        assert_eq!(outputs[1].label(), "nct6798 pwm3");
+        assert_eq!(
+            outputs[1].id(),
+            PwmId::Hwmon {
+                path: dir.path().to_path_buf(),
+                index: 3
+            }
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn hwmon_round_trip() {
+        let dir = hwmon_tree();
+        let outputs = device_outputs(dir.path());
+
+        // Automatic mode: only the mode goes back, the driver owns the duty
+        let original = outputs[0].state().unwrap();
+        assert_eq!(original, PwmState { mode: 5, duty: 153 });
+        outputs[0].set_manual(64).unwrap();
+        assert_eq!(
+            (read(&dir, "pwm1_enable"), read(&dir, "pwm1")),
+            ("1".into(), "64".into())
+        );
+        outputs[0].restore(original).unwrap();
+        assert_eq!(read(&dir, "pwm1_enable"), "5");
+
+        // Manual mode: the duty goes back too
+        let original = outputs[1].state().unwrap();
+        outputs[1].set_manual(200).unwrap();
+        outputs[1].restore(original).unwrap();
+        assert_eq!(
+            (read(&dir, "pwm3_enable"), read(&dir, "pwm3")),
+            ("1".into(), "80".into())
+        );
+        assert_eq!(outputs[1].duty(), Ok(80));
+    }
+}
@@ -0,0 +1,149 @@
+//! Baseboard identification from the SMBIOS tables.
+
+/// Manufacturer and product of the baseboard (SMBIOS type 2)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BoardInfo {
+    pub vendor: String,
+    pub model: String,
+}
+
+/// Read the baseboard strings exported by the kernel
+#[cfg(target_os = "linux")]
+pub fn read_board_info() -> Option<BoardInfo> {
+    let read = |file: &str| {
+        std::fs::read_to_string(format!("/sys/class/dmi/id/{}", file))
+            .ok()
+            .map(|s| s.trim().to_owned())
+    };
+
+    Some(BoardInfo {
+        vendor: read("board_vendor")?,
+        model: read("board_name")?,
+    })
+}
+
+/// Read the baseboard strings from the raw SMBIOS table
+#[cfg(windows)]
+pub fn read_board_info() -> Option<BoardInfo> {
+    use windows::Win32::System::SystemInformation::{GetSystemFirmwareTable, RSMB};
+
+    let size = unsafe { GetSystemFirmwareTable(RSMB, 0, None) };
+    if size == 0 {
+        return None;
+    }
+
+    let mut buffer = vec![0u8; size as usize];
+    if unsafe { GetSystemFirmwareTable(RSMB, 0, Some(&mut buffer)) } != size {
+        return None;
+    }
+
+    // Skip the RawSMBIOSData header
+    parse_baseboard(buffer.get(8..)?)
+}
+
+#[cfg(any(windows, test))]
+const TYPE_BASEBOARD: u8 = 2;
+#[cfg(any(windows, test))]
+const TYPE_END_OF_TABLE: u8 = 127;
+
+/// Find the first baseboard structure in an SMBIOS structure table
+#[cfg(any(windows, test))]
+fn parse_baseboard(table: &[u8]) -> Option<BoardInfo> {
+    let mut offset = 0;
+
+    while offset + 4 <= table.len() {
+        let kind = table[offset];
+        let length = table[offset + 1] as usize;
+        if length < 4 || kind == TYPE_END_OF_TABLE {
+            return None;
+        }
+
+        let formatted = table.get(offset..offset + length)?;
+
+        // Strings follow the formatted area and end with a double NUL
+        let strings_start = offset + length;
+        let strings_len = table
+            .get(strings_start..)?
+            .windows(2)
+            .position(|w| w == [0, 0])?;
+        let strings: Vec<&[u8]> = table[strings_start..strings_start + strings_len]
+            .split(|&b| b == 0)
+            .collect();
+
+        if kind == TYPE_BASEBOARD && length >= 6 {
+            let string = |index: u8| {
+                let bytes = strings.get((index as usize).checked_sub(1)?)?;
+                Some(String::from_utf8_lossy(bytes).trim().to_owned())
+            };
+
+            return Some(BoardInfo {
+                vendor: string(formatted[4])?,
+                model: string(formatted[5])?,
+            });
+        }
+
+        offset = strings_start + strings_len + 2;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Formatted area of `length` bytes with string indices at 4 and 5
+    fn structure(kind: u8, length: u8, strings: &[&str]) -> Vec<u8> {
+        let mut bytes = vec![0u8; length as usize];
+        bytes[0] = kind;
+        bytes[1] = length;
+        if length >= 6 {
+            bytes[4] = 1;
+            bytes[5] = 2;
+        }
+
+        for string in strings {
+            bytes.extend_from_slice(string.as_bytes());
+            bytes.push(0);
+        }
+        if strings.is_empty() {
+            bytes.push(0);
+        }
+        bytes.push(0);
+        bytes
+    }
+
+    #[test]
+    fn finds_baseboard_after_other_structures() {
+        let mut table = structure(0, 0x18, &["American Megatrends", "1.0"]);
+        table.extend(structure(1, 0x1B, &[]));
+        table.extend(structure(
+            TYPE_BASEBOARD,
+            0x0F,
+            &["ASUSTeK COMPUTER INC. ", "ROG STRIX Z690-A GAMING WIFI D4"],
+        ));
+        table.extend(structure(TYPE_END_OF_TABLE, 4, &[]));
+
+        assert_eq!(
+            parse_baseboard(&table),
+            Some(BoardInfo {
+                vendor: "ASUSTeK COMPUTER INC.".into(),
+                model: "ROG STRIX Z690-A GAMING WIFI D4".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn stops_at_end_of_table_or_truncation() {
+        let mut table = structure(TYPE_END_OF_TABLE, 4, &[]);
+        table.extend(structure(TYPE_BASEBOARD, 0x0F, &["Vendor", "Model"]));
+        assert_eq!(parse_baseboard(&table), None);
+
+        let table = structure(TYPE_BASEBOARD, 0x0F, &["Vendor", "Model"]);
+        assert_eq!(parse_baseboard(&table[..table.len() - 2]), None);
+
+        // String index past the string set
+        let table = structure(TYPE_BASEBOARD, 0x0F, &["Vendor"]);
+        assert_eq!(parse_baseboard(&table), None);
+    }
+}
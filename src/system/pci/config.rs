@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::system::kernal_driver::KernelDriver;
+
+/// Size of the extended (PCIe) configuration space of a function
+pub const CONFIG_SPACE_SIZE: u16 = 4096;
+
+/// Bus/device/function address of a PCI function in segment 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PciAddress {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl PciAddress {
+    pub const fn new(bus: u8, device: u8, function: u8) -> Self {
+        Self {
+            bus,
+            device: device & 0x1F,
+            function: function & 0x07,
+        }
+    }
+
+    /// Packed form used by WinRing0: bus in bits 15:8, device 7:3, function 2:0
+    pub const fn bdf(&self) -> u32 {
+        ((self.bus as u32) << 8) | ((self.device as u32) << 3) | self.function as u32
+    }
+}
+
+impl fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02x}:{:02x}.{:x}",
+            self.bus, self.device, self.function
+        )
+    }
+}
+
+/// Access to PCI configuration space.
+///
+/// Implementations only move raw little-endian bytes; the typed accessors
+/// check alignment and bounds before touching the hardware.
+pub trait PciConfig {
+    /// Read `buffer.len()` bytes (1, 2 or 4) at `offset`
+    fn read(&self, address: PciAddress, offset: u16, buffer: &mut [u8]) -> Result<(), String>;
+
+    /// Write `data` (1, 2 or 4 bytes) at `offset`
+    fn write(&self, address: PciAddress, offset: u16, data: &[u8]) -> Result<(), String>;
+
+    fn read_u8(&self, address: PciAddress, offset: u16) -> Result<u8, String> {
+        let mut buffer = [0u8; 1];
+        self.read(address, check_access(offset, 1)?, &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    fn read_u16(&self, address: PciAddress, offset: u16) -> Result<u16, String> {
+        let mut buffer = [0u8; 2];
+        self.read(address, check_access(offset, 2)?, &mut buffer)?;
+        Ok(u16::from_le_bytes(buffer))
+    }
+
+    fn read_u32(&self, address: PciAddress, offset: u16) -> Result<u32, String> {
+        let mut buffer = [0u8; 4];
+        self.read(address, check_access(offset, 4)?, &mut buffer)?;
+        Ok(u32::from_le_bytes(buffer))
+    }
+
+    fn write_u8(&self, address: PciAddress, offset: u16, value: u8) -> Result<(), String> {
+        self.write(address, check_access(offset, 1)?, &[value])
+    }
+
+    fn write_u16(&self, address: PciAddress, offset: u16, value: u16) -> Result<(), String> {
+        self.write(address, check_access(offset, 2)?, &value.to_le_bytes())
+    }
+
+    fn write_u32(&self, address: PciAddress, offset: u16, value: u32) -> Result<(), String> {
+        self.write(address, check_access(offset, 4)?, &value.to_le_bytes())
+    }
+}
+
+// Accesses must be naturally aligned and stay inside the configuration space
+fn check_access(offset: u16, width: u16) -> Result<u16, String> {
+    if !offset.is_multiple_of(width) {
+        return Err(format!(
+            "Unaligned {}-byte PCI config access at {:#x}",
+            width, offset
+        ));
+    }
+    if offset + width > CONFIG_SPACE_SIZE {
+        return Err(format!("PCI config offset {:#x} out of range", offset));
+    }
+
+    Ok(offset)
+}
+
+impl PciConfig for KernelDriver {
+    fn read(&self, address: PciAddress, offset: u16, buffer: &mut [u8]) -> Result<(), String> {
+        self.read_pci_config(address, offset, buffer)
+    }
+
+    fn write(&self, address: PciAddress, offset: u16, data: &[u8]) -> Result<(), String> {
+        self.write_pci_config(address, offset, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::pci::FakePciConfig;
+
+    const ADDRESS: PciAddress = PciAddress::new(0, 3, 1);
+
+    #[test]
+    fn typed_accessors_are_little_endian() {
+        let config = FakePciConfig::new();
+        config.add_function(ADDRESS, &[0x86, 0x80, 0x34, 0x12]);
+
+        assert_eq!(config.read_u8(ADDRESS, 0x01), Ok(0x80));
+        assert_eq!(config.read_u16(ADDRESS, 0x00), Ok(0x8086));
+        assert_eq!(config.read_u32(ADDRESS, 0x00), Ok(0x1234_8086));
+
+        config.write_u32(ADDRESS, 0x40, 0xDEAD_BEEF).unwrap();
+        config.write_u8(ADDRESS, 0x40, 0x00).unwrap();
+        assert_eq!(config.read_u16(ADDRESS, 0x40), Ok(0xBE00));
+        assert_eq!(config.read_u16(ADDRESS, 0x42), Ok(0xDEAD));
+    }
+
+    #[test]
+    fn rejects_unaligned_and_out_of_range_access() {
+        let config = FakePciConfig::new();
+        config.add_function(ADDRESS, &[]);
+
+        assert!(config.read_u16(ADDRESS, 0x01).is_err());
+        assert!(config.read_u32(ADDRESS, 0x02).is_err());
+        assert!(config.write_u32(ADDRESS, 0x06, 0).is_err());
+        assert!(config.read_u32(ADDRESS, CONFIG_SPACE_SIZE).is_err());
+        assert!(config.read_u32(ADDRESS, CONFIG_SPACE_SIZE - 4).is_ok());
+    }
+
+    #[test]
+    fn missing_function_reads_all_ones() {
+        let config = FakePciConfig::new();
+
+        assert_eq!(config.read_u32(ADDRESS, 0x00), Ok(0xFFFF_FFFF));
+        config.write_u16(ADDRESS, 0x04, 0).unwrap();
+        assert_eq!(config.function(ADDRESS), None);
+    }
+
+    #[test]
+    fn formats_and_packs_addresses() {
+        let address = PciAddress::new(0x1A, 0x1F, 7);
+
+        assert_eq!(address.to_string(), "1a:1f.7");
+        assert_eq!(address.bdf(), 0x1AFF);
+        assert_eq!(PciAddress::new(0, 0x20, 8), PciAddress::new(0, 0, 0));
+    }
+}
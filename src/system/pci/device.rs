@@ -0,0 +1,417 @@
+#[cfg(target_os = "linux")]
+use crate::system::pci::link::{read_aer_counters, AerCounters};
+use crate::system::pci::{
+    config::{PciAddress, PciConfig},
+    link::{AerStatus, PcieLink, EXT_CAP_ID_AER},
+};
+
+const VENDOR_ID: u16 = 0x00;
+const DEVICE_ID: u16 = 0x02;
+const STATUS: u16 = 0x06;
+const REVISION_ID: u16 = 0x08;
+const HEADER_TYPE: u16 = 0x0E;
+const BAR0: u16 = 0x10;
+const SUBSYSTEM_VENDOR_ID: u16 = 0x2C;
+const SUBSYSTEM_ID: u16 = 0x2E;
+const CAPABILITIES_POINTER: u16 = 0x34;
+
+const STATUS_CAPABILITIES_LIST: u16 = 1 << 4;
+const HEADER_TYPE_MULTI_FUNCTION: u8 = 1 << 7;
+
+/// First extended capability, only present on PCI Express functions
+const EXTENDED_CAPABILITIES: u16 = 0x100;
+
+/// Capability ID of the PCI Express capability
+pub const CAP_ID_PCI_EXPRESS: u8 = 0x10;
+
+/// Guards against malformed capability lists that loop
+const MAX_CAPABILITIES: usize = 48;
+
+/// Class code of a function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciClass {
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+}
+
+/// A decoded base address register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+    Memory {
+        index: u8,
+        address: u64,
+        is_64bit: bool,
+        prefetchable: bool,
+    },
+    Io {
+        index: u8,
+        port: u32,
+    },
+}
+
+/// An entry of the standard capability list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capability {
+    pub id: u8,
+    pub offset: u16,
+}
+
+/// An entry of the PCI Express extended capability list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedCapability {
+    pub id: u16,
+    pub version: u8,
+    pub offset: u16,
+}
+
+/// A single PCI function
+#[derive(Debug, Clone, PartialEq)]
+pub struct PciDevice {
+    pub address: PciAddress,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    /// Only type 0 headers carry subsystem ids
+    pub subsystem_vendor_id: Option<u16>,
+    pub subsystem_id: Option<u16>,
+    pub class: PciClass,
+    pub revision: u8,
+    pub header_type: u8,
+    pub bars: Vec<Bar>,
+    pub capabilities: Vec<Capability>,
+    pub extended_capabilities: Vec<ExtendedCapability>,
+    /// Link state when the device was read, for PCI Express functions
+    pub link: Option<PcieLink>,
+    /// Latched AER errors when the device was read, if it has the capability
+    pub aer: Option<AerStatus>,
+    pub vendor_name: Option<String>,
+    pub device_name: Option<String>,
+}
+
+impl PciDevice {
+    /// Read a function. Returns `None` when nothing responds at the address.
+    pub fn read(config: &dyn PciConfig, address: PciAddress) -> Option<Self> {
+        let vendor_id = config.read_u16(address, VENDOR_ID).ok()?;
+        if vendor_id == 0xFFFF || vendor_id == 0 {
+            return None;
+        }
+
+        let device_id = config.read_u16(address, DEVICE_ID).ok()?;
+        let class_revision = config.read_u32(address, REVISION_ID).ok()?;
+        let header_type = config.read_u8(address, HEADER_TYPE).ok()? & 0x7F;
+
+        let (subsystem_vendor_id, subsystem_id) = if header_type == 0 {
+            (
+                config.read_u16(address, SUBSYSTEM_VENDOR_ID).ok(),
+                config.read_u16(address, SUBSYSTEM_ID).ok(),
+            )
+        } else {
+            (None, None)
+        };
+
+        let capabilities = read_capabilities(config, address);
+        let pcie = capabilities.iter().find(|c| c.id == CAP_ID_PCI_EXPRESS);
+
+        let link = pcie.and_then(|c| PcieLink::read(config, address, c.offset).ok());
+        let extended_capabilities = if pcie.is_some() {
+            read_extended_capabilities(config, address)
+        } else {
+            Vec::new()
+        };
+        let aer = extended_capabilities
+            .iter()
+            .find(|c| c.id == EXT_CAP_ID_AER)
+            .and_then(|c| AerStatus::read(config, address, c.offset).ok());
+
+        Some(Self {
+            address,
+            vendor_id,
+            device_id,
+            subsystem_vendor_id,
+            subsystem_id,
+            class: PciClass {
+                class: (class_revision >> 24) as u8,
+                subclass: (class_revision >> 16) as u8,
+                prog_if: (class_revision >> 8) as u8,
+            },
+            revision: class_revision as u8,
+            header_type,
+            bars: read_bars(config, address, header_type),
+            capabilities,
+            extended_capabilities,
+            link,
+            aer,
+            vendor_name: None,
+            device_name: None,
+        })
+    }
+
+    pub fn capability(&self, id: u8) -> Option<&Capability> {
+        self.capabilities.iter().find(|c| c.id == id)
+    }
+
+    pub fn extended_capability(&self, id: u16) -> Option<&ExtendedCapability> {
+        self.extended_capabilities.iter().find(|c| c.id == id)
+    }
+
+    /// The link trained below its maximum speed or width
+    pub fn is_link_degraded(&self) -> bool {
+        self.link.is_some_and(|link| link.is_degraded())
+    }
+
+    /// Errors the Linux AER driver counted since boot. Unlike [Self::aer] these
+    /// are running totals, read live.
+    #[cfg(target_os = "linux")]
+    pub fn aer_counters(&self) -> Option<AerCounters> {
+        read_aer_counters(self.address)
+    }
+}
+
+/// Function 0 reports whether functions 1-7 exist
+pub(crate) fn is_multi_function(config: &dyn PciConfig, address: PciAddress) -> bool {
+    config
+        .read_u8(address, HEADER_TYPE)
+        .is_ok_and(|h| h != 0xFF && h & HEADER_TYPE_MULTI_FUNCTION != 0)
+}
+
+fn read_bars(config: &dyn PciConfig, address: PciAddress, header_type: u8) -> Vec<Bar> {
+    let count = match header_type {
+        0 => 6,
+        1 => 2,
+        _ => 0,
+    };
+
+    let mut bars = Vec::new();
+    let mut index = 0;
+    while index < count {
+        let offset = BAR0 + index as u16 * 4;
+        let Ok(low) = config.read_u32(address, offset) else {
+            break;
+        };
+
+        if low & 1 != 0 {
+            if low & !0x3 != 0 {
+                bars.push(Bar::Io {
+                    index,
+                    port: low & !0x3,
+                });
+            }
+            index += 1;
+            continue;
+        }
+
+        let is_64bit = (low >> 1) & 0x3 == 0x2;
+        let mut bar_address = (low & !0xF) as u64;
+        if is_64bit && index + 1 < count {
+            let high = config.read_u32(address, offset + 4).unwrap_or(0);
+            bar_address |= (high as u64) << 32;
+        }
+
+        if bar_address != 0 {
+            bars.push(Bar::Memory {
+                index,
+                address: bar_address,
+                is_64bit,
+                prefetchable: low & (1 << 3) != 0,
+            });
+        }
+        index += if is_64bit { 2 } else { 1 };
+    }
+
+    bars
+}
+
+fn read_capabilities(config: &dyn PciConfig, address: PciAddress) -> Vec<Capability> {
+    let mut capabilities = Vec::new();
+
+    let has_list = config
+        .read_u16(address, STATUS)
+        .is_ok_and(|s| s & STATUS_CAPABILITIES_LIST != 0);
+    if !has_list {
+        return capabilities;
+    }
+
+    let mut pointer = config
+        .read_u8(address, CAPABILITIES_POINTER)
+        .map(|p| p & !0x3)
+        .unwrap_or(0);
+
+    while pointer >= 0x40 && capabilities.len() < MAX_CAPABILITIES {
+        let Ok(header) = config.read_u16(address, pointer as u16) else {
+            break;
+        };
+
+        capabilities.push(Capability {
+            id: header as u8,
+            offset: pointer as u16,
+        });
+        pointer = (header >> 8) as u8 & !0x3;
+    }
+
+    capabilities
+}
+
+fn read_extended_capabilities(
+    config: &dyn PciConfig,
+    address: PciAddress,
+) -> Vec<ExtendedCapability> {
+    let mut capabilities = Vec::new();
+    let mut offset = EXTENDED_CAPABILITIES;
+
+    while offset >= EXTENDED_CAPABILITIES && capabilities.len() < MAX_CAPABILITIES {
+        // Unprivileged sysfs readers only see the first 256 bytes
+        let Ok(header) = config.read_u32(address, offset) else {
+            break;
+        };
+        if header == 0 || header == 0xFFFF_FFFF {
+            break;
+        }
+
+        capabilities.push(ExtendedCapability {
+            id: header as u16,
+            version: ((header >> 16) & 0xF) as u8,
+            offset,
+        });
+        offset = ((header >> 20) & 0xFFC) as u16;
+    }
+
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::pci::FakePciConfig;
+
+    const PCIE_CAP: usize = 0x40;
+    const MSI_CAP: usize = 0x80;
+
+    fn write_u16(config: &mut [u8], offset: usize, value: u16) {
+        config[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u32(config: &mut [u8], offset: usize, value: u32) {
+        config[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    // Type 0 PCI Express endpoint with an MSI capability, a 64-bit memory
+    // BAR and AER followed by one more extended capability
+    fn endpoint() -> Vec<u8> {
+        let mut config = vec![0u8; 0x200];
+        write_u16(&mut config, VENDOR_ID as usize, 0x10DE);
+        write_u16(&mut config, DEVICE_ID as usize, 0x2204);
+        write_u16(&mut config, STATUS as usize, STATUS_CAPABILITIES_LIST);
+        write_u32(&mut config, REVISION_ID as usize, 0x0300_00A1);
+        write_u32(&mut config, BAR0 as usize, 0xF000_000C);
+        write_u32(&mut config, BAR0 as usize + 4, 0x0000_0001);
+        write_u16(&mut config, SUBSYSTEM_VENDOR_ID as usize, 0x1043);
+        write_u16(&mut config, SUBSYSTEM_ID as usize, 0x87B3);
+        config[CAPABILITIES_POINTER as usize] = PCIE_CAP as u8;
+
+        // PCI Express -> MSI -> end
+        config[PCIE_CAP] = CAP_ID_PCI_EXPRESS;
+        config[PCIE_CAP + 1] = MSI_CAP as u8;
+        write_u32(&mut config, PCIE_CAP + 0x0C, 0x0000_0104);
+        write_u16(&mut config, PCIE_CAP + 0x12, 0x0083);
+        config[MSI_CAP] = 0x05;
+
+        // AER -> Secondary PCI Express (0x0019) at 0x180 -> end
+        write_u32(&mut config, 0x100, 0x1801_0001);
+        write_u32(&mut config, 0x104, 1 << 14);
+        write_u32(&mut config, 0x10C, 1 << 14);
+        write_u32(&mut config, 0x110, 1 << 0);
+        write_u32(&mut config, 0x180, 0x0001_0019);
+        config
+    }
+
+    #[test]
+    fn reads_header_and_bars() {
+        let config = FakePciConfig::new();
+        let address = PciAddress::new(1, 0, 0);
+        config.add_function(address, &endpoint());
+
+        let device = PciDevice::read(&config, address).unwrap();
+
+        assert_eq!(device.vendor_id, 0x10DE);
+        assert_eq!(device.device_id, 0x2204);
+        assert_eq!(device.subsystem_vendor_id, Some(0x1043));
+        assert_eq!(device.subsystem_id, Some(0x87B3));
+        assert_eq!(device.class.class, 0x03);
+        assert_eq!(device.revision, 0xA1);
+        assert_eq!(
+            device.bars,
+            [Bar::Memory {
+                index: 0,
+                address: 0x1_F000_0000,
+                is_64bit: true,
+                prefetchable: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn walks_capabilities_and_decodes_link_and_aer() {
+        let config = FakePciConfig::new();
+        let address = PciAddress::new(1, 0, 0);
+        config.add_function(address, &endpoint());
+
+        let device = PciDevice::read(&config, address).unwrap();
+
+        assert_eq!(
+            device.capabilities.iter().map(|c| c.id).collect::<Vec<_>>(),
+            [CAP_ID_PCI_EXPRESS, 0x05]
+        );
+        assert_eq!(
+            device
+                .extended_capabilities
+                .iter()
+                .map(|c| (c.id, c.offset))
+                .collect::<Vec<_>>(),
+            [(EXT_CAP_ID_AER, 0x100), (0x0019, 0x180)]
+        );
+        assert!(device.is_link_degraded());
+
+        let aer = device.aer.unwrap();
+        assert_eq!(aer.correctable_status(), 1 << 0);
+        assert_eq!(aer.fatal_status(), 1 << 14);
+        assert_eq!(aer.nonfatal_status(), 0);
+    }
+
+    #[test]
+    fn conventional_function_has_no_link() {
+        let mut image = endpoint();
+        write_u16(&mut image, STATUS as usize, 0);
+
+        let config = FakePciConfig::new();
+        let address = PciAddress::new(0, 2, 0);
+        config.add_function(address, &image);
+
+        let device = PciDevice::read(&config, address).unwrap();
+
+        assert!(device.capabilities.is_empty());
+        assert!(device.extended_capabilities.is_empty());
+        assert_eq!(device.link, None);
+        assert_eq!(device.aer, None);
+    }
+
+    #[test]
+    fn stops_on_looping_capability_list() {
+        let mut image = endpoint();
+        // MSI points back at the PCI Express capability
+        image[MSI_CAP + 1] = PCIE_CAP as u8;
+
+        let config = FakePciConfig::new();
+        let address = PciAddress::new(0, 3, 0);
+        config.add_function(address, &image);
+
+        let device = PciDevice::read(&config, address).unwrap();
+        assert_eq!(device.capabilities.len(), MAX_CAPABILITIES);
+    }
+
+    #[test]
+    fn empty_slot_reads_as_none() {
+        let config = FakePciConfig::new();
+
+        assert_eq!(PciDevice::read(&config, PciAddress::new(0, 4, 0)), None);
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use crate::system::{
+    kernal_driver::KernelDriver,
+    pci::{
+        config::{PciAddress, PciConfig},
+        device::{is_multi_function, PciDevice},
+        ids::PciIds,
+    },
+};
+
+/// Class and subclass of a host bridge. On AMD this is the root complex, on
+/// Intel client parts it also hosts the memory controller (MCHBAR).
+const CLASS_HOST_BRIDGE: (u8, u8) = (0x06, 0x00);
+/// Class and subclass of an SMBus controller
+const CLASS_SMBUS: (u8, u8) = (0x0C, 0x05);
+
+/// Enumerate all PCI functions through the driver, named with `ids`
+pub fn gather_pci_devices(
+    driver: &Arc<KernelDriver>,
+    ids: Option<&PciIds>,
+) -> Result<Vec<PciDevice>, String> {
+    let devices = enumerate(driver.as_ref(), ids);
+
+    if devices.is_empty() {
+        return Err("No PCI devices found".into());
+    }
+
+    Ok(devices)
+}
+
+/// Scan every bus for PCI functions and resolve their names
+pub fn enumerate(config: &dyn PciConfig, ids: Option<&PciIds>) -> Vec<PciDevice> {
+    (0..=u8::MAX)
+        .flat_map(|bus| enumerate_bus(config, bus, ids))
+        .collect()
+}
+
+/// Scan a single bus for PCI functions
+pub fn enumerate_bus(config: &dyn PciConfig, bus: u8, ids: Option<&PciIds>) -> Vec<PciDevice> {
+    let mut devices = Vec::new();
+
+    for device in 0..32 {
+        let Some(first) = PciDevice::read(config, PciAddress::new(bus, device, 0)) else {
+            continue;
+        };
+
+        let functions = if is_multi_function(config, first.address) {
+            8
+        } else {
+            1
+        };
+
+        devices.push(first);
+        devices.extend(
+            (1..functions).filter_map(|f| PciDevice::read(config, PciAddress::new(bus, device, f))),
+        );
+    }
+
+    if let Some(ids) = ids {
+        for device in &mut devices {
+            device.vendor_name = ids.vendor_name(device.vendor_id).map(str::to_owned);
+            device.device_name = ids
+                .device_name(device.vendor_id, device.device_id)
+                .map(str::to_owned);
+        }
+    }
+
+    devices
+}
+
+fn find_class(devices: &[PciDevice], (class, subclass): (u8, u8)) -> Option<&PciDevice> {
+    devices
+        .iter()
+        .find(|d| d.class.class == class && d.class.subclass == subclass)
+}
+
+/// The first host bridge of a vendor: the AMD root complex or the Intel IMC
+pub fn host_bridge(devices: &[PciDevice], vendor_id: u16) -> Option<&PciDevice> {
+    host_bridges(devices, vendor_id).next()
+}
+
+/// Every host bridge of a vendor, in enumeration order
+pub fn host_bridges(devices: &[PciDevice], vendor_id: u16) -> impl Iterator<Item = &PciDevice> {
+    devices.iter().filter(move |d| {
+        d.vendor_id == vendor_id && (d.class.class, d.class.subclass) == CLASS_HOST_BRIDGE
+    })
+}
+
+/// The first SMBus controller
+pub fn smbus_controller(devices: &[PciDevice]) -> Option<&PciDevice> {
+    find_class(devices, CLASS_SMBUS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::pci::{FakePciConfig, VENDOR_INTEL};
+
+    // Type 0 header with the given ids and class
+    fn function(vendor: u16, device: u16, class: (u8, u8), header_type: u8) -> Vec<u8> {
+        let mut config = vec![0u8; 0x40];
+        config[0x00..0x02].copy_from_slice(&vendor.to_le_bytes());
+        config[0x02..0x04].copy_from_slice(&device.to_le_bytes());
+        config[0x0A] = class.1;
+        config[0x0B] = class.0;
+        config[0x0E] = header_type;
+        config
+    }
+
+    fn fixture() -> FakePciConfig {
+        let config = FakePciConfig::new();
+        config.add_function(
+            PciAddress::new(0, 0, 0),
+            &function(VENDOR_INTEL, 0x3E30, CLASS_HOST_BRIDGE, 0),
+        );
+        // Multi-function PCH: ISA bridge and SMBus controller
+        config.add_function(
+            PciAddress::new(0, 0x1F, 0),
+            &function(VENDOR_INTEL, 0xA305, (0x06, 0x01), 0x80),
+        );
+        config.add_function(
+            PciAddress::new(0, 0x1F, 4),
+            &function(VENDOR_INTEL, 0xA323, CLASS_SMBUS, 0),
+        );
+        // Single-function GPU; function 1 is not reported without the flag
+        config.add_function(
+            PciAddress::new(1, 0, 0),
+            &function(0x10DE, 0x2204, (0x03, 0x00), 0),
+        );
+        config.add_function(
+            PciAddress::new(1, 0, 1),
+            &function(0x10DE, 0x1AEF, (0x04, 0x03), 0),
+        );
+        config
+    }
+
+    #[test]
+    fn enumerates_every_bus_and_function() {
+        let devices = enumerate(&fixture(), None);
+        let addresses: Vec<String> = devices.iter().map(|d| d.address.to_string()).collect();
+
+        assert_eq!(addresses, ["00:00.0", "00:1f.0", "00:1f.4", "01:00.0"]);
+        assert_eq!(devices[3].vendor_id, 0x10DE);
+        assert_eq!(devices[3].device_id, 0x2204);
+        assert_eq!(devices[0].vendor_name, None);
+    }
+
+    #[test]
+    fn resolves_names() {
+        let ids =
+            PciIds::parse("8086  Intel Corporation\n\ta323  Cannon Lake PCH SMBus Controller\n");
+        let devices = enumerate(&fixture(), Some(&ids));
+        let smbus = &devices[2];
+
+        assert_eq!(smbus.vendor_name.as_deref(), Some("Intel Corporation"));
+        assert_eq!(
+            smbus.device_name.as_deref(),
+            Some("Cannon Lake PCH SMBus Controller")
+        );
+        assert_eq!(devices[3].vendor_name, None);
+    }
+
+    #[test]
+    fn finds_host_bridge_and_smbus_controller() {
+        let devices = enumerate(&fixture(), None);
+
+        assert_eq!(
+            host_bridge(&devices, VENDOR_INTEL).map(|d| d.address),
+            Some(PciAddress::new(0, 0, 0))
+        );
+        assert!(host_bridge(&devices, 0x1022).is_none());
+        assert_eq!(
+            smbus_controller(&devices).map(|d| d.address),
+            Some(PciAddress::new(0, 0x1F, 4))
+        );
+    }
+}
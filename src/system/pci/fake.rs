@@ -0,0 +1,73 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::system::pci::config::{PciAddress, PciConfig, CONFIG_SPACE_SIZE};
+
+/// In-memory configuration space for tests and fixtures.
+///
+/// Functions that were never added read as all ones, like an empty slot on
+/// real hardware, and ignore writes.
+#[derive(Debug, Default)]
+pub struct FakePciConfig {
+    functions: Mutex<HashMap<PciAddress, Vec<u8>>>,
+}
+
+impl FakePciConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a function with the given configuration space. Shorter images are
+    /// padded with zeroes up to the full extended configuration space.
+    pub fn add_function(&self, address: PciAddress, config: &[u8]) {
+        let mut space = config.to_vec();
+        space.resize(CONFIG_SPACE_SIZE as usize, 0);
+
+        if let Ok(mut functions) = self.functions.lock() {
+            functions.insert(address, space);
+        }
+    }
+
+    /// Copy of the current configuration space of a function
+    pub fn function(&self, address: PciAddress) -> Option<Vec<u8>> {
+        self.functions.lock().ok()?.get(&address).cloned()
+    }
+}
+
+impl PciConfig for FakePciConfig {
+    fn read(&self, address: PciAddress, offset: u16, buffer: &mut [u8]) -> Result<(), String> {
+        let functions = self
+            .functions
+            .lock()
+            .map_err(|_| "Fake PCI config poisoned")?;
+
+        match functions.get(&address) {
+            Some(space) => {
+                let start = offset as usize;
+                let bytes = space
+                    .get(start..start + buffer.len())
+                    .ok_or_else(|| format!("PCI config offset {:#x} out of range", offset))?;
+                buffer.copy_from_slice(bytes);
+            }
+            None => buffer.fill(0xFF),
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, address: PciAddress, offset: u16, data: &[u8]) -> Result<(), String> {
+        let mut functions = self
+            .functions
+            .lock()
+            .map_err(|_| "Fake PCI config poisoned")?;
+
+        if let Some(space) = functions.get_mut(&address) {
+            let start = offset as usize;
+            space
+                .get_mut(start..start + data.len())
+                .ok_or_else(|| format!("PCI config offset {:#x} out of range", offset))?
+                .copy_from_slice(data);
+        }
+
+        Ok(())
+    }
+}
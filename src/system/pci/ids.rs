@@ -0,0 +1,155 @@
+use std::{collections::HashMap, fs, path::Path};
+
+/// Locations of pci.ids used by common Linux distributions
+const SYSTEM_PATHS: &[&str] = &[
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+    "/usr/share/pci.ids",
+];
+
+#[derive(Debug, Default)]
+struct VendorEntry {
+    name: String,
+    devices: HashMap<u16, String>,
+}
+
+/// Vendor and device names from a `pci.ids` database
+#[derive(Debug, Default)]
+pub struct PciIds {
+    vendors: HashMap<u16, VendorEntry>,
+}
+
+impl PciIds {
+    /// Parse the contents of a `pci.ids` file. Subsystem and class entries
+    /// are skipped.
+    pub fn parse(contents: &str) -> Self {
+        let mut vendors: HashMap<u16, VendorEntry> = HashMap::new();
+        let mut current: Option<u16> = None;
+
+        for line in contents.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // The class section comes last and uses the same indentation
+            if line.starts_with("C ") {
+                break;
+            }
+
+            if line.starts_with("\t\t") {
+                continue;
+            }
+
+            if let Some(device) = line.strip_prefix('\t') {
+                let (Some(vendor), Some((id, name))) = (current, parse_entry(device)) else {
+                    continue;
+                };
+                if let Some(entry) = vendors.get_mut(&vendor) {
+                    entry.devices.insert(id, name.to_owned());
+                }
+                continue;
+            }
+
+            current = parse_entry(line).map(|(id, name)| {
+                vendors.insert(
+                    id,
+                    VendorEntry {
+                        name: name.to_owned(),
+                        devices: HashMap::new(),
+                    },
+                );
+                id
+            });
+        }
+
+        Self { vendors }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        // The file is mostly ASCII but some names use Latin-1
+        Ok(Self::parse(&String::from_utf8_lossy(&contents)))
+    }
+
+    /// Load the database installed with the OS, if there is one
+    pub fn system() -> Option<Self> {
+        SYSTEM_PATHS.iter().find_map(|path| Self::load(path).ok())
+    }
+
+    pub fn vendor_name(&self, vendor_id: u16) -> Option<&str> {
+        self.vendors.get(&vendor_id).map(|v| v.name.as_str())
+    }
+
+    pub fn device_name(&self, vendor_id: u16, device_id: u16) -> Option<&str> {
+        self.vendors
+            .get(&vendor_id)?
+            .devices
+            .get(&device_id)
+            .map(String::as_str)
+    }
+}
+
+// Parse "1022  Advanced Micro Devices, Inc. [AMD]"
+fn parse_entry(line: &str) -> Option<(u16, &str)> {
+    let (id, name) = line.split_once("  ")?;
+    let id = u16::from_str_radix(id.trim(), 16).ok()?;
+
+    Some((id, name.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATABASE: &str = "\
+# pci.ids excerpt
+8086  Intel Corporation
+\t3e30  8th/9th Gen Core 8-core Desktop Processor Host Bridge/DRAM Registers
+\t\t1043 8694  PRIME Z390-A
+\ta323  Cannon Lake PCH SMBus Controller
+10de  NVIDIA Corporation
+\t2204  GA102 [GeForce RTX 3090]
+
+# List of known device classes
+C 00  Unclassified device
+\t00  Non-VGA unclassified device
+";
+
+    #[test]
+    fn parses_vendors_and_devices() {
+        let ids = PciIds::parse(DATABASE);
+
+        assert_eq!(ids.vendor_name(0x8086), Some("Intel Corporation"));
+        assert_eq!(ids.vendor_name(0x10DE), Some("NVIDIA Corporation"));
+        assert_eq!(
+            ids.device_name(0x8086, 0xA323),
+            Some("Cannon Lake PCH SMBus Controller")
+        );
+        assert_eq!(
+            ids.device_name(0x10DE, 0x2204),
+            Some("GA102 [GeForce RTX 3090]")
+        );
+        assert_eq!(ids.vendor_name(0x1022), None);
+    }
+
+    #[test]
+    fn skips_subsystems_and_classes() {
+        let ids = PciIds::parse(DATABASE);
+
+        // The subsystem line must not be taken as a device of vendor 8086
+        assert_eq!(ids.device_name(0x8086, 0x1043), None);
+        // Nor the class entries as devices of the last vendor
+        assert_eq!(ids.device_name(0x10DE, 0x0000), None);
+    }
+
+    #[test]
+    fn ignores_malformed_lines() {
+        let ids = PciIds::parse("zzzz  Not hex\n\t1234  Orphan device\n1022  AMD\n");
+
+        assert_eq!(ids.vendor_name(0x1022), Some("AMD"));
+        assert_eq!(ids.device_name(0x1022, 0x1234), None);
+    }
+}
@@ -0,0 +1,255 @@
+//! PCI Express link state and Advanced Error Reporting.
+//! For more information see the PCI Express Base Specification, sections 7.5.3
+//! (PCI Express capability) and 7.8.4 (AER extended capability).
+
+use crate::system::pci::config::{PciAddress, PciConfig};
+
+/// Offsets inside the PCI Express capability
+const LINK_CAPABILITIES: u16 = 0x0C;
+const LINK_STATUS: u16 = 0x12;
+
+/// Extended capability ID of Advanced Error Reporting
+pub const EXT_CAP_ID_AER: u16 = 0x0001;
+
+/// Offsets inside the AER capability
+const AER_UNCORRECTABLE_STATUS: u16 = 0x04;
+const AER_UNCORRECTABLE_SEVERITY: u16 = 0x0C;
+const AER_CORRECTABLE_STATUS: u16 = 0x10;
+
+/// Negotiated and maximum link parameters of a PCI Express function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PcieLink {
+    /// Transfer rate in GT/s, 0 when the link is down or the encoding is unknown
+    pub current_speed: f32,
+    pub current_width: u8,
+    pub max_speed: f32,
+    pub max_width: u8,
+}
+
+impl PcieLink {
+    /// Decode the Link Capabilities and Link Status registers
+    pub fn from_registers(capabilities: u32, status: u16) -> Self {
+        Self {
+            current_speed: speed_gts(status as u32 & 0xF),
+            current_width: ((status >> 4) & 0x3F) as u8,
+            max_speed: speed_gts(capabilities & 0xF),
+            max_width: ((capabilities >> 4) & 0x3F) as u8,
+        }
+    }
+
+    /// Read the link registers through the PCI Express capability at `cap_offset`
+    pub fn read(
+        config: &dyn PciConfig,
+        address: PciAddress,
+        cap_offset: u16,
+    ) -> Result<Self, String> {
+        let capabilities = config.read_u32(address, cap_offset + LINK_CAPABILITIES)?;
+        let status = config.read_u16(address, cap_offset + LINK_STATUS)?;
+
+        Ok(Self::from_registers(capabilities, status))
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.current_width != 0
+    }
+
+    /// The link trained below this function's own maximum speed or width, which
+    /// the other end may not support. Devices that lower their link speed to
+    /// save power will also report this while idle.
+    pub fn is_degraded(&self) -> bool {
+        self.is_up() && (self.current_speed < self.max_speed || self.current_width < self.max_width)
+    }
+}
+
+// Link speed encodings index the supported link speeds vector, which in
+// practice maps 1..=6 to generations 1 through 6
+fn speed_gts(encoding: u32) -> f32 {
+    match encoding {
+        1 => 2.5,
+        2 => 5.0,
+        3 => 8.0,
+        4 => 16.0,
+        5 => 32.0,
+        6 => 64.0,
+        _ => 0.0,
+    }
+}
+
+const CORRECTABLE_ERRORS: &[(u32, &str)] = &[
+    (0, "Receiver Error"),
+    (6, "Bad TLP"),
+    (7, "Bad DLLP"),
+    (8, "REPLAY_NUM Rollover"),
+    (12, "Replay Timer Timeout"),
+    (13, "Advisory Non-Fatal Error"),
+    (14, "Corrected Internal Error"),
+    (15, "Header Log Overflow"),
+];
+
+const UNCORRECTABLE_ERRORS: &[(u32, &str)] = &[
+    (4, "Data Link Protocol Error"),
+    (5, "Surprise Down Error"),
+    (12, "Poisoned TLP"),
+    (13, "Flow Control Protocol Error"),
+    (14, "Completion Timeout"),
+    (15, "Completer Abort"),
+    (16, "Unexpected Completion"),
+    (17, "Receiver Overflow"),
+    (18, "Malformed TLP"),
+    (19, "ECRC Error"),
+    (20, "Unsupported Request Error"),
+    (21, "ACS Violation"),
+    (22, "Uncorrectable Internal Error"),
+];
+
+/// Errors latched in the AER status registers. The bits are sticky until
+/// cleared by software, so each set bit is at least one occurrence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AerStatus {
+    pub correctable: u32,
+    pub uncorrectable: u32,
+    /// Uncorrectable errors with their severity bit set are fatal
+    pub uncorrectable_severity: u32,
+}
+
+impl AerStatus {
+    /// Read the status registers of the AER capability at `cap_offset`
+    pub fn read(
+        config: &dyn PciConfig,
+        address: PciAddress,
+        cap_offset: u16,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            correctable: config.read_u32(address, cap_offset + AER_CORRECTABLE_STATUS)?,
+            uncorrectable: config.read_u32(address, cap_offset + AER_UNCORRECTABLE_STATUS)?,
+            uncorrectable_severity: config
+                .read_u32(address, cap_offset + AER_UNCORRECTABLE_SEVERITY)?,
+        })
+    }
+
+    /// Latched correctable error bits, without reserved bits
+    pub fn correctable_status(&self) -> u32 {
+        masked(self.correctable, CORRECTABLE_ERRORS)
+    }
+
+    /// Latched uncorrectable error bits of non-fatal severity
+    pub fn nonfatal_status(&self) -> u32 {
+        masked(
+            self.uncorrectable & !self.uncorrectable_severity,
+            UNCORRECTABLE_ERRORS,
+        )
+    }
+
+    /// Latched uncorrectable error bits of fatal severity
+    pub fn fatal_status(&self) -> u32 {
+        masked(
+            self.uncorrectable & self.uncorrectable_severity,
+            UNCORRECTABLE_ERRORS,
+        )
+    }
+
+    /// Names of the latched correctable errors
+    pub fn correctable_errors(&self) -> Vec<&'static str> {
+        names(self.correctable, CORRECTABLE_ERRORS)
+    }
+
+    /// Names of the latched uncorrectable errors, fatal or not
+    pub fn uncorrectable_errors(&self) -> Vec<&'static str> {
+        names(self.uncorrectable, UNCORRECTABLE_ERRORS)
+    }
+}
+
+fn masked(value: u32, table: &[(u32, &str)]) -> u32 {
+    table.iter().fold(0, |mask, (bit, _)| mask | (1 << bit)) & value
+}
+
+fn names(value: u32, table: &[(u32, &'static str)]) -> Vec<&'static str> {
+    table
+        .iter()
+        .filter(|(bit, _)| value & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Running AER totals kept by the Linux kernel since boot
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AerCounters {
+    pub correctable: u64,
+    pub nonfatal: u64,
+    pub fatal: u64,
+}
+
+/// Read the kernel's AER counters of a function, if the AER driver tracks it
+#[cfg(target_os = "linux")]
+pub(crate) fn read_aer_counters(address: PciAddress) -> Option<AerCounters> {
+    let read_total = |file: &str, key: &str| -> Option<u64> {
+        let path = format!("/sys/bus/pci/devices/0000:{}/{}", address, file);
+        std::fs::read_to_string(path)
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.trim().parse().ok())
+    };
+
+    Some(AerCounters {
+        correctable: read_total("aer_dev_correctable", "TOTAL_ERR_COR")?,
+        nonfatal: read_total("aer_dev_nonfatal", "TOTAL_ERR_NONFATAL")?,
+        fatal: read_total("aer_dev_fatal", "TOTAL_ERR_FATAL")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_link_registers() {
+        // Gen4 x16 capable, trained at Gen3 x8
+        let link = PcieLink::from_registers(0x0000_0104, 0x0083);
+
+        assert_eq!(link.max_speed, 16.0);
+        assert_eq!(link.max_width, 16);
+        assert_eq!(link.current_speed, 8.0);
+        assert_eq!(link.current_width, 8);
+        assert!(link.is_up());
+        assert!(link.is_degraded());
+    }
+
+    #[test]
+    fn full_link_is_not_degraded() {
+        let link = PcieLink::from_registers(0x0000_0044, 0x0044);
+
+        assert!(link.is_up());
+        assert!(!link.is_degraded());
+    }
+
+    #[test]
+    fn link_down_is_not_degraded() {
+        let link = PcieLink::from_registers(0x0000_0104, 0x0000);
+
+        assert!(!link.is_up());
+        assert!(!link.is_degraded());
+        assert_eq!(link.current_speed, 0.0);
+    }
+
+    #[test]
+    fn splits_errors_by_severity() {
+        let aer = AerStatus {
+            // Receiver Error, Bad TLP and an undefined bit
+            correctable: (1 << 0) | (1 << 6) | (1 << 31),
+            // Poisoned TLP and Completion Timeout, Surprise Down
+            uncorrectable: (1 << 12) | (1 << 14) | (1 << 5),
+            // Only Surprise Down is fatal
+            uncorrectable_severity: (1 << 5) | (1 << 18),
+        };
+
+        assert_eq!(aer.correctable_status(), (1 << 0) | (1 << 6));
+        assert_eq!(aer.nonfatal_status(), (1 << 12) | (1 << 14));
+        assert_eq!(aer.fatal_status(), 1 << 5);
+        assert_eq!(aer.correctable_errors(), ["Receiver Error", "Bad TLP"]);
+        assert_eq!(
+            aer.uncorrectable_errors(),
+            ["Surprise Down Error", "Poisoned TLP", "Completion Timeout"]
+        );
+    }
+}
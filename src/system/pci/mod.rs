@@ -0,0 +1,22 @@
+pub mod config;
+pub mod device;
+pub mod enumerate;
+pub mod fake;
+pub mod ids;
+pub mod link;
+
+pub use config::{PciAddress, PciConfig};
+pub use device::PciDevice;
+pub use enumerate::{
+    enumerate, enumerate_bus, gather_pci_devices, host_bridge, host_bridges, smbus_controller,
+};
+pub use fake::FakePciConfig;
+pub use ids::PciIds;
+#[cfg(target_os = "linux")]
+pub use link::AerCounters;
+pub use link::{AerStatus, PcieLink};
+
+/// PCI vendor ids of the CPU vendors with PCI-visible uncore devices
+pub const VENDOR_INTEL: u16 = 0x8086;
+pub const VENDOR_AMD: u16 = 0x1022;
+pub const VENDOR_HYGON: u16 = 0x1D94;
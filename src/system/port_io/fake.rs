@@ -0,0 +1,124 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::RangeInclusive,
+    sync::Mutex,
+};
+
+use crate::system::port_io::PortIo;
+
+/// Emulated hardware behind a range of ports
+pub trait PortDevice {
+    /// Return the value of a `width`-byte read
+    fn read(&mut self, port: u16, width: usize) -> u32;
+
+    fn write(&mut self, port: u16, value: u32, width: usize);
+}
+
+/// A single access seen by [FakePortIo]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortAccess {
+    Read { port: u16, value: u32, width: usize },
+    Write { port: u16, value: u32, width: usize },
+}
+
+type Attached = (RangeInclusive<u16>, Box<dyn PortDevice + Send>);
+
+/// Scriptable port bus for tests.
+///
+/// Reads are answered, in order of precedence, by queued values for the port,
+/// by an attached [PortDevice], or with all ones like a floating bus. Every
+/// access is recorded.
+#[derive(Default)]
+pub struct FakePortIo {
+    queued: Mutex<HashMap<u16, VecDeque<u32>>>,
+    devices: Mutex<Vec<Attached>>,
+    log: Mutex<Vec<PortAccess>>,
+}
+
+impl FakePortIo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer the next read of `port` with `value`
+    pub fn queue_read(&self, port: u16, value: u32) {
+        if let Ok(mut queued) = self.queued.lock() {
+            queued.entry(port).or_default().push_back(value);
+        }
+    }
+
+    /// Route accesses to `ports` to an emulated device
+    pub fn attach(&self, ports: RangeInclusive<u16>, device: impl PortDevice + Send + 'static) {
+        if let Ok(mut devices) = self.devices.lock() {
+            devices.push((ports, Box::new(device)));
+        }
+    }
+
+    /// All accesses so far, oldest first
+    pub fn accesses(&self) -> Vec<PortAccess> {
+        self.log.lock().map(|log| log.clone()).unwrap_or_default()
+    }
+
+    fn record(&self, access: PortAccess) {
+        if let Ok(mut log) = self.log.lock() {
+            log.push(access);
+        }
+    }
+}
+
+impl PortIo for FakePortIo {
+    fn read(&self, port: u16, buffer: &mut [u8]) -> Result<(), String> {
+        let width = check_width(buffer.len())?;
+
+        let queued = self
+            .queued
+            .lock()
+            .map_err(|_| "Fake port bus poisoned")?
+            .get_mut(&port)
+            .and_then(VecDeque::pop_front);
+
+        let value = match queued {
+            Some(value) => value,
+            None => self
+                .devices
+                .lock()
+                .map_err(|_| "Fake port bus poisoned")?
+                .iter_mut()
+                .find(|(ports, _)| ports.contains(&port))
+                .map_or(u32::MAX, |(_, device)| device.read(port, width)),
+        };
+
+        buffer.copy_from_slice(&value.to_le_bytes()[..width]);
+        self.record(PortAccess::Read { port, value, width });
+
+        Ok(())
+    }
+
+    fn write(&self, port: u16, data: &[u8]) -> Result<(), String> {
+        let width = check_width(data.len())?;
+
+        let mut bytes = [0u8; 4];
+        bytes[..width].copy_from_slice(data);
+        let value = u32::from_le_bytes(bytes);
+
+        if let Some((_, device)) = self
+            .devices
+            .lock()
+            .map_err(|_| "Fake port bus poisoned")?
+            .iter_mut()
+            .find(|(ports, _)| ports.contains(&port))
+        {
+            device.write(port, value, width);
+        }
+        self.record(PortAccess::Write { port, value, width });
+
+        Ok(())
+    }
+}
+
+fn check_width(width: usize) -> Result<usize, String> {
+    match width {
+        1 | 2 | 4 => Ok(width),
+        _ => Err(format!("Unsupported I/O port access width {}", width)),
+    }
+}
@@ -0,0 +1,147 @@
+pub mod fake;
+
+pub use fake::{FakePortIo, PortAccess, PortDevice};
+
+use crate::system::kernal_driver::KernelDriver;
+
+/// Access to x86 I/O ports.
+///
+/// Implementations only move raw little-endian bytes; the width of the access
+/// is the length of the buffer.
+pub trait PortIo {
+    /// Read `buffer.len()` bytes (1, 2 or 4) from `port`
+    fn read(&self, port: u16, buffer: &mut [u8]) -> Result<(), String>;
+
+    /// Write `data` (1, 2 or 4 bytes) to `port`
+    fn write(&self, port: u16, data: &[u8]) -> Result<(), String>;
+
+    fn inb(&self, port: u16) -> Result<u8, String> {
+        let mut buffer = [0u8; 1];
+        self.read(port, &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    fn inw(&self, port: u16) -> Result<u16, String> {
+        let mut buffer = [0u8; 2];
+        self.read(port, &mut buffer)?;
+        Ok(u16::from_le_bytes(buffer))
+    }
+
+    fn inl(&self, port: u16) -> Result<u32, String> {
+        let mut buffer = [0u8; 4];
+        self.read(port, &mut buffer)?;
+        Ok(u32::from_le_bytes(buffer))
+    }
+
+    fn outb(&self, port: u16, value: u8) -> Result<(), String> {
+        self.write(port, &[value])
+    }
+
+    fn outw(&self, port: u16, value: u16) -> Result<(), String> {
+        self.write(port, &value.to_le_bytes())
+    }
+
+    fn outl(&self, port: u16, value: u32) -> Result<(), String> {
+        self.write(port, &value.to_le_bytes())
+    }
+}
+
+impl PortIo for KernelDriver {
+    fn read(&self, port: u16, buffer: &mut [u8]) -> Result<(), String> {
+        self.read_io_port(port, buffer)
+    }
+
+    fn write(&self, port: u16, data: &[u8]) -> Result<(), String> {
+        self.write_io_port(port, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Byte-wide scratch registers
+    #[derive(Default)]
+    struct Scratch([u8; 8]);
+
+    impl PortDevice for Scratch {
+        fn read(&mut self, port: u16, width: usize) -> u32 {
+            let start = (port & 0x7) as usize;
+            let mut bytes = [0u8; 4];
+            bytes[..width].copy_from_slice(&self.0[start..start + width]);
+            u32::from_le_bytes(bytes)
+        }
+
+        fn write(&mut self, port: u16, value: u32, width: usize) {
+            let start = (port & 0x7) as usize;
+            self.0[start..start + width].copy_from_slice(&value.to_le_bytes()[..width]);
+        }
+    }
+
+    #[test]
+    fn typed_accessors_are_little_endian() {
+        let io = FakePortIo::new();
+        io.attach(0x290..=0x297, Scratch::default());
+
+        io.outl(0x290, 0x1234_5678).unwrap();
+        assert_eq!(io.inb(0x290), Ok(0x78));
+        assert_eq!(io.inw(0x292), Ok(0x1234));
+
+        io.outw(0x294, 0xBEEF).unwrap();
+        io.outb(0x296, 0xAD).unwrap();
+        assert_eq!(io.inl(0x294), Ok(0x00AD_BEEF));
+    }
+
+    #[test]
+    fn queued_reads_take_precedence() {
+        let io = FakePortIo::new();
+        io.attach(0x290..=0x297, Scratch::default());
+        io.queue_read(0x290, 0x42);
+        io.queue_read(0x290, 0x43);
+
+        assert_eq!(io.inb(0x290), Ok(0x42));
+        assert_eq!(io.inb(0x290), Ok(0x43));
+        assert_eq!(io.inb(0x290), Ok(0x00));
+    }
+
+    #[test]
+    fn floating_bus_reads_all_ones() {
+        let io = FakePortIo::new();
+
+        assert_eq!(io.inb(0x2E), Ok(0xFF));
+        assert_eq!(io.inw(0x2E), Ok(0xFFFF));
+        assert_eq!(io.inl(0x2E), Ok(0xFFFF_FFFF));
+    }
+
+    #[test]
+    fn records_accesses_in_order() {
+        let io = FakePortIo::new();
+        io.outb(0x2E, 0x87).unwrap();
+        io.inw(0x2F).unwrap();
+
+        assert_eq!(
+            io.accesses(),
+            [
+                PortAccess::Write {
+                    port: 0x2E,
+                    value: 0x87,
+                    width: 1
+                },
+                PortAccess::Read {
+                    port: 0x2F,
+                    value: 0xFFFF_FFFF,
+                    width: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_widths() {
+        let io = FakePortIo::new();
+
+        assert!(io.read(0x2E, &mut [0u8; 3]).is_err());
+        assert!(io.write(0x2E, &[]).is_err());
+        assert!(io.accesses().is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use SuperIoFamily::{Fintek, Ite, Nuvoton};
+
+/// Super I/O vendors with a supported hardware monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SuperIoFamily {
+    Ite,
+    Nuvoton,
+    Fintek,
+}
+
+/// Known chips by the id in configuration registers 0x20/0x21
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChipInfo {
+    pub id: u16,
+    pub name: &'static str,
+    pub family: SuperIoFamily,
+}
+
+const fn chip(id: u16, name: &'static str, family: SuperIoFamily) -> ChipInfo {
+    ChipInfo { id, name, family }
+}
+
+const ITE_CHIPS: &[ChipInfo] = &[
+    chip(0x8613, "IT8613E", Ite),
+    chip(0x8620, "IT8620E", Ite),
+    chip(0x8625, "IT8625E", Ite),
+    chip(0x8628, "IT8628E", Ite),
+    chip(0x8655, "IT8655E", Ite),
+    chip(0x8665, "IT8665E", Ite),
+    chip(0x8686, "IT8686E", Ite),
+    chip(0x8688, "IT8688E", Ite),
+    chip(0x8689, "IT8689E", Ite),
+    chip(0x8695, "IT8695E", Ite),
+    chip(0x8705, "IT8705F", Ite),
+    chip(0x8712, "IT8712F", Ite),
+    chip(0x8716, "IT8716F", Ite),
+    chip(0x8718, "IT8718F", Ite),
+    chip(0x8720, "IT8720F", Ite),
+    chip(0x8721, "IT8721F", Ite),
+    chip(0x8726, "IT8726F", Ite),
+    chip(0x8728, "IT8728F", Ite),
+    chip(0x8733, "IT8792E", Ite),
+    chip(0x8771, "IT8771E", Ite),
+    chip(0x8772, "IT8772E", Ite),
+];
+
+// Nuvoton ids carry the revision in the low three bits, which are masked off
+const NUVOTON_ID_MASK: u16 = 0xFFF8;
+
+const NUVOTON_CHIPS: &[ChipInfo] = &[
+    chip(0xB470, "NCT6771F", Nuvoton),
+    chip(0xC330, "NCT6776F", Nuvoton),
+    chip(0xC560, "NCT6779D", Nuvoton),
+    chip(0xC800, "NCT6791D", Nuvoton),
+    chip(0xC910, "NCT6792D", Nuvoton),
+    chip(0xD120, "NCT6793D", Nuvoton),
+    chip(0xD350, "NCT6795D", Nuvoton),
+    chip(0xD420, "NCT6796D", Nuvoton),
+    chip(0xD450, "NCT6797D", Nuvoton),
+    chip(0xD428, "NCT6798D", Nuvoton),
+    chip(0xD800, "NCT6799D", Nuvoton),
+];
+
+const FINTEK_CHIPS: &[ChipInfo] = &[
+    chip(0x0507, "F71858", Fintek),
+    chip(0x0541, "F71882", Fintek),
+    chip(0x0601, "F71862", Fintek),
+    chip(0x0723, "F71889F", Fintek),
+    chip(0x0814, "F71869", Fintek),
+    chip(0x0901, "F71808E", Fintek),
+    chip(0x0909, "F71889ED", Fintek),
+    chip(0x1005, "F71889AD", Fintek),
+    chip(0x1007, "F71869A", Fintek),
+    chip(0x1106, "F71868A", Fintek),
+];
+
+/// Look up a chip id read in the configuration mode of `family`
+pub fn identify(family: SuperIoFamily, id: u16) -> Option<&'static ChipInfo> {
+    match family {
+        Ite => ITE_CHIPS.iter().find(|c| c.id == id),
+        Nuvoton => NUVOTON_CHIPS.iter().find(|c| c.id == id & NUVOTON_ID_MASK),
+        Fintek => FINTEK_CHIPS.iter().find(|c| c.id == id),
+    }
+}
@@ -0,0 +1,255 @@
+use crate::system::{
+    port_io::PortIo,
+    superio::{
+        chip::{identify, SuperIoFamily},
+        hwm::HwmBank,
+    },
+};
+
+/// Configuration index ports; the data port is the next one up
+pub const CONFIG_PORTS: [u16; 2] = [0x2E, 0x4E];
+
+const REG_LOGICAL_DEVICE: u8 = 0x07;
+const REG_CHIP_ID: u8 = 0x20;
+const REG_CHIP_REVISION: u8 = 0x21;
+const REG_ACTIVATE: u8 = 0x30;
+const REG_BASE_ADDRESS: u8 = 0x60;
+const REG_ITE_CONFIG_CONTROL: u8 = 0x02;
+
+/// NCT6791D and later lock the hardware monitor I/O space until bit 4 of this
+/// global register is cleared
+const REG_NUVOTON_IO_SPACE_LOCK: u8 = 0x28;
+const NUVOTON_IO_SPACE_LOCK: u8 = 1 << 4;
+const NCT6791D_ID: u16 = 0xC800;
+
+/// Logical device of the hardware monitor (environment controller)
+const ITE_EC_LDN: u8 = 0x04;
+const NUVOTON_HWM_LDN: u8 = 0x0B;
+const FINTEK_HWM_LDN: u8 = 0x04;
+
+const WINBOND_ENTER: [u8; 2] = [0x87, 0x87];
+const WINBOND_EXIT: u8 = 0xAA;
+const ITE_ENTER_2E: [u8; 4] = [0x87, 0x01, 0x55, 0x55];
+const ITE_ENTER_4E: [u8; 4] = [0x87, 0x01, 0x55, 0xAA];
+
+/// A detected Super I/O chip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuperIoChip {
+    pub name: &'static str,
+    pub family: SuperIoFamily,
+    /// Chip id including the revision
+    pub id: u16,
+    pub config_port: u16,
+    /// Register bank of the hardware monitor
+    pub hwm: HwmBank,
+}
+
+/// Probe both configuration ports for supported chips
+pub fn detect(io: &dyn PortIo) -> Vec<SuperIoChip> {
+    CONFIG_PORTS
+        .iter()
+        .filter_map(|&port| probe(io, port))
+        .collect()
+}
+
+/// Probe one configuration port, first with the Winbond-style key used by
+/// Nuvoton and Fintek, then with the ITE key
+pub fn probe(io: &dyn PortIo, port: u16) -> Option<SuperIoChip> {
+    probe_winbond(io, port).or_else(|| probe_ite(io, port))
+}
+
+fn probe_winbond(io: &dyn PortIo, port: u16) -> Option<SuperIoChip> {
+    enter(io, port, &WINBOND_ENTER)?;
+
+    let id = read_id(io, port);
+    let chip = id.and_then(|id| {
+        let info =
+            identify(SuperIoFamily::Nuvoton, id).or_else(|| identify(SuperIoFamily::Fintek, id))?;
+        let ldn = match info.family {
+            SuperIoFamily::Fintek => FINTEK_HWM_LDN,
+            _ => NUVOTON_HWM_LDN,
+        };
+        if info.family == SuperIoFamily::Nuvoton && info.id >= NCT6791D_ID {
+            unlock_nuvoton_io_space(io, port).ok()?;
+        }
+
+        Some(SuperIoChip {
+            name: info.name,
+            family: info.family,
+            id,
+            config_port: port,
+            hwm: HwmBank::new(info.family, read_base(io, port, ldn)?),
+        })
+    });
+
+    let _ = io.outb(port, WINBOND_EXIT);
+    chip
+}
+
+fn probe_ite(io: &dyn PortIo, port: u16) -> Option<SuperIoChip> {
+    let key = if port == 0x4E {
+        &ITE_ENTER_4E
+    } else {
+        &ITE_ENTER_2E
+    };
+    enter(io, port, key)?;
+
+    let id = read_id(io, port);
+    let chip = id.and_then(|id| {
+        let info = identify(SuperIoFamily::Ite, id)?;
+
+        Some(SuperIoChip {
+            name: info.name,
+            family: info.family,
+            id,
+            config_port: port,
+            hwm: HwmBank::new(info.family, read_base(io, port, ITE_EC_LDN)?),
+        })
+    });
+
+    // Return to wait-for-key state
+    let _ = write_config(io, port, REG_ITE_CONFIG_CONTROL, 0x02);
+    chip
+}
+
+fn enter(io: &dyn PortIo, port: u16, key: &[u8]) -> Option<()> {
+    key.iter().try_for_each(|&b| io.outb(port, b)).ok()
+}
+
+fn read_config(io: &dyn PortIo, port: u16, register: u8) -> Result<u8, String> {
+    io.outb(port, register)?;
+    io.inb(port + 1)
+}
+
+fn write_config(io: &dyn PortIo, port: u16, register: u8, value: u8) -> Result<(), String> {
+    io.outb(port, register)?;
+    io.outb(port + 1, value)
+}
+
+fn read_id(io: &dyn PortIo, port: u16) -> Option<u16> {
+    let high = read_config(io, port, REG_CHIP_ID).ok()?;
+    let low = read_config(io, port, REG_CHIP_REVISION).ok()?;
+    let id = u16::from_be_bytes([high, low]);
+
+    (id != 0 && id != 0xFFFF).then_some(id)
+}
+
+fn unlock_nuvoton_io_space(io: &dyn PortIo, port: u16) -> Result<(), String> {
+    let value = read_config(io, port, REG_NUVOTON_IO_SPACE_LOCK)?;
+    if value & NUVOTON_IO_SPACE_LOCK == 0 {
+        return Ok(());
+    }
+
+    write_config(
+        io,
+        port,
+        REG_NUVOTON_IO_SPACE_LOCK,
+        value & !NUVOTON_IO_SPACE_LOCK,
+    )
+}
+
+// Base address of a logical device, `None` when it is inactive or not configured
+fn read_base(io: &dyn PortIo, port: u16, ldn: u8) -> Option<u16> {
+    write_config(io, port, REG_LOGICAL_DEVICE, ldn).ok()?;
+
+    if read_config(io, port, REG_ACTIVATE).ok()? & 0x01 == 0 {
+        return None;
+    }
+
+    let high = read_config(io, port, REG_BASE_ADDRESS).ok()?;
+    let low = read_config(io, port, REG_BASE_ADDRESS + 1).ok()?;
+    let base = u16::from_be_bytes([high, low]) & !0x7;
+
+    (base != 0 && base != 0xFFF8).then_some(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{
+        port_io::{FakePortIo, PortAccess},
+        superio::FakeSuperIo,
+    };
+
+    fn bus(chip: FakeSuperIo) -> FakePortIo {
+        let io = FakePortIo::new();
+        io.attach(chip.ports(), chip);
+        io
+    }
+
+    fn wrote(io: &FakePortIo, port: u16, value: u32) -> bool {
+        io.accesses().contains(&PortAccess::Write {
+            port,
+            value,
+            width: 1,
+        })
+    }
+
+    #[test]
+    fn detects_ite_on_either_port() {
+        for port in CONFIG_PORTS {
+            let io = bus(FakeSuperIo::ite(port, 0x8686, 0x0A40));
+
+            let chips = detect(&io);
+
+            assert_eq!(chips.len(), 1);
+            assert_eq!(chips[0].name, "IT8686E");
+            assert_eq!(chips[0].config_port, port);
+            assert_eq!(chips[0].hwm, HwmBank::new(SuperIoFamily::Ite, 0x0A40));
+            // Left configuration mode through the config control register
+            assert!(wrote(&io, port, REG_ITE_CONFIG_CONTROL as u32));
+            assert!(wrote(&io, port + 1, 0x02));
+        }
+    }
+
+    #[test]
+    fn detects_nuvoton_with_revision() {
+        let io = bus(FakeSuperIo::nuvoton(0x2E, 0xC562, 0x0290));
+
+        let chip = probe(&io, 0x2E).unwrap();
+
+        assert_eq!(chip.name, "NCT6779D");
+        assert_eq!(chip.id, 0xC562);
+        assert_eq!(chip.hwm, HwmBank::new(SuperIoFamily::Nuvoton, 0x0290));
+        assert!(wrote(&io, 0x2E, WINBOND_EXIT as u32));
+        // Older chips have no I/O space lock
+        assert!(!wrote(&io, 0x2E, REG_NUVOTON_IO_SPACE_LOCK as u32));
+    }
+
+    #[test]
+    fn unlocks_nuvoton_io_space() {
+        let io =
+            bus(FakeSuperIo::nuvoton(0x2E, 0xD428, 0x0290)
+                .with_global(REG_NUVOTON_IO_SPACE_LOCK, 0x31));
+
+        assert_eq!(probe(&io, 0x2E).unwrap().name, "NCT6798D");
+        assert!(wrote(&io, 0x2F, 0x21));
+    }
+
+    #[test]
+    fn detects_fintek() {
+        let io = bus(FakeSuperIo::fintek(0x4E, 0x0723, 0x0A00));
+
+        let chip = probe(&io, 0x4E).unwrap();
+
+        assert_eq!(chip.name, "F71889F");
+        assert_eq!(chip.hwm, HwmBank::new(SuperIoFamily::Fintek, 0x0A00));
+    }
+
+    #[test]
+    fn skips_inactive_or_unconfigured_monitor() {
+        let inactive = bus(FakeSuperIo::nuvoton(0x2E, 0xC800, 0x0290).with_inactive(0x0B));
+        let unconfigured = bus(FakeSuperIo::ite(0x2E, 0x8686, 0));
+
+        assert_eq!(probe(&inactive, 0x2E), None);
+        assert_eq!(probe(&unconfigured, 0x2E), None);
+    }
+
+    #[test]
+    fn ignores_unknown_chips_and_empty_ports() {
+        let io = bus(FakeSuperIo::ite(0x2E, 0x1234, 0x0A40));
+
+        assert!(detect(&io).is_empty());
+        assert!(detect(&FakePortIo::new()).is_empty());
+    }
+}
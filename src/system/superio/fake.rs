@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+};
+
+use crate::system::{port_io::PortDevice, superio::chip::SuperIoFamily};
+
+/// Emulated Super I/O configuration interface.
+///
+/// The chip ignores everything until its key sequence is written to the index
+/// port, then exposes the global registers (below 0x30) and the registers of
+/// the logical device selected through register 0x07.
+pub struct FakeSuperIo {
+    family: SuperIoFamily,
+    config_port: u16,
+    key: Vec<u8>,
+    key_progress: usize,
+    unlocked: bool,
+    index: u8,
+    global: [u8; 0x30],
+    logical_device: u8,
+    logical: HashMap<u8, [u8; 0x100]>,
+}
+
+impl FakeSuperIo {
+    pub fn ite(config_port: u16, id: u16, hwm_base: u16) -> Self {
+        let last = if config_port == 0x4E { 0xAA } else { 0x55 };
+        Self::new(
+            SuperIoFamily::Ite,
+            config_port,
+            vec![0x87, 0x01, 0x55, last],
+            id,
+        )
+        .with_base(0x04, hwm_base)
+    }
+
+    pub fn nuvoton(config_port: u16, id: u16, hwm_base: u16) -> Self {
+        Self::new(SuperIoFamily::Nuvoton, config_port, vec![0x87, 0x87], id)
+            .with_base(0x0B, hwm_base)
+    }
+
+    pub fn fintek(config_port: u16, id: u16, hwm_base: u16) -> Self {
+        Self::new(SuperIoFamily::Fintek, config_port, vec![0x87, 0x87], id)
+            .with_base(0x04, hwm_base)
+    }
+
+    fn new(family: SuperIoFamily, config_port: u16, key: Vec<u8>, id: u16) -> Self {
+        let mut global = [0u8; 0x30];
+        [global[0x20], global[0x21]] = id.to_be_bytes();
+
+        Self {
+            family,
+            config_port,
+            key,
+            key_progress: 0,
+            unlocked: false,
+            index: 0,
+            global,
+            logical_device: 0,
+            logical: HashMap::new(),
+        }
+    }
+
+    /// Set the base address registers of a logical device and activate it
+    pub fn with_base(mut self, logical_device: u8, base: u16) -> Self {
+        let registers = self.logical.entry(logical_device).or_insert([0; 0x100]);
+        registers[0x30] = 0x01;
+        [registers[0x60], registers[0x61]] = base.to_be_bytes();
+        self
+    }
+
+    /// Set a global configuration register
+    pub fn with_global(mut self, register: u8, value: u8) -> Self {
+        self.global[register as usize] = value;
+        self
+    }
+
+    /// Deactivate a logical device, keeping its base address
+    pub fn with_inactive(mut self, logical_device: u8) -> Self {
+        self.logical.entry(logical_device).or_insert([0; 0x100])[0x30] = 0x00;
+        self
+    }
+
+    /// Index and data port, for [FakePortIo::attach](crate::system::port_io::FakePortIo::attach)
+    pub fn ports(&self) -> RangeInclusive<u16> {
+        self.config_port..=self.config_port + 1
+    }
+
+    /// Whether the chip is still in configuration mode
+    pub fn is_unlocked(&self) -> bool {
+        self.unlocked
+    }
+
+    fn write_index(&mut self, value: u8) {
+        if !self.unlocked {
+            self.key_progress = if self.key[self.key_progress] == value {
+                self.key_progress + 1
+            } else {
+                (self.key[0] == value) as usize
+            };
+            if self.key_progress == self.key.len() {
+                self.unlocked = true;
+                self.key_progress = 0;
+            }
+            return;
+        }
+
+        if value == 0xAA && self.family != SuperIoFamily::Ite {
+            self.unlocked = false;
+        } else {
+            self.index = value;
+        }
+    }
+
+    fn write_data(&mut self, value: u8) {
+        match self.index {
+            0x07 => self.logical_device = value,
+            0x02 if self.family == SuperIoFamily::Ite && value & 0x02 != 0 => {
+                self.unlocked = false;
+            }
+            index if index < 0x30 => self.global[index as usize] = value,
+            index => {
+                self.logical
+                    .entry(self.logical_device)
+                    .or_insert([0; 0x100])[index as usize] = value
+            }
+        }
+    }
+
+    fn read_data(&self) -> u8 {
+        match self.index {
+            0x07 => self.logical_device,
+            index if index < 0x30 => self.global[index as usize],
+            index => self
+                .logical
+                .get(&self.logical_device)
+                .map_or(0, |registers| registers[index as usize]),
+        }
+    }
+}
+
+impl PortDevice for FakeSuperIo {
+    fn read(&mut self, port: u16, _width: usize) -> u32 {
+        if !self.unlocked {
+            return 0xFF;
+        }
+
+        if port == self.config_port {
+            self.index as u32
+        } else {
+            self.read_data() as u32
+        }
+    }
+
+    fn write(&mut self, port: u16, value: u32, _width: usize) {
+        if port == self.config_port {
+            self.write_index(value as u8);
+        } else if self.unlocked {
+            self.write_data(value as u8);
+        }
+    }
+}
+
+/// Emulated hardware monitor register file behind its index/data ports.
+///
+/// Registers are keyed by `bank << 8 | index`. Clones share the register
+/// file, so a test can keep one to inspect writes after attaching another.
+#[derive(Clone)]
+pub struct FakeHwm {
+    family: SuperIoFamily,
+    base: u16,
+    index: u8,
+    bank: u8,
+    registers: Arc<Mutex<HashMap<u16, u8>>>,
+}
+
+impl FakeHwm {
+    pub fn new(family: SuperIoFamily, base: u16) -> Self {
+        Self {
+            family,
+            base,
+            index: 0,
+            bank: 0,
+            registers: Arc::default(),
+        }
+    }
+
+    pub fn set(&self, register: u16, value: u8) {
+        if let Ok(mut registers) = self.registers.lock() {
+            registers.insert(register, value);
+        }
+    }
+
+    /// Current value of a register, 0 if it was never written
+    pub fn get(&self, register: u16) -> u8 {
+        self.registers
+            .lock()
+            .ok()
+            .and_then(|registers| registers.get(&register).copied())
+            .unwrap_or(0)
+    }
+
+    /// Index and data port, for [FakePortIo::attach](crate::system::port_io::FakePortIo::attach)
+    pub fn ports(&self) -> RangeInclusive<u16> {
+        self.base + 5..=self.base + 6
+    }
+
+    fn is_bank_select(&self) -> bool {
+        self.family == SuperIoFamily::Nuvoton && self.index == 0x4E
+    }
+
+    fn register(&self) -> u16 {
+        u16::from_be_bytes([self.bank, self.index])
+    }
+}
+
+impl PortDevice for FakeHwm {
+    fn read(&mut self, port: u16, _width: usize) -> u32 {
+        if port == self.base + 5 {
+            self.index as u32
+        } else if self.is_bank_select() {
+            self.bank as u32
+        } else {
+            self.get(self.register()) as u32
+        }
+    }
+
+    fn write(&mut self, port: u16, value: u32, _width: usize) {
+        if port == self.base + 5 {
+            self.index = value as u8;
+        } else if self.is_bank_select() {
+            self.bank = value as u8;
+        } else {
+            self.set(self.register(), value as u8);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Fan tachometers of the supported hardware monitors.
+//!
+//! Older chips report a pulse count with a clock divisor, newer ITE and
+//! Fintek chips a 16-bit count, and Nuvoton chips from the NCT6779D on the
+//! RPM itself.
+
+use crate::system::{
+    port_io::PortIo,
+    superio::{chip::SuperIoFamily, detect::SuperIoChip},
+};
+
+/// ITE tachometer low bytes and the extended high bytes of 16-bit counters
+const ITE_FAN_TACH: [u16; 6] = [0x0D, 0x0E, 0x0F, 0x80, 0x82, 0x4C];
+const ITE_FAN_TACH_EXT: [u16; 6] = [0x18, 0x19, 0x1A, 0x81, 0x83, 0x4D];
+/// Divisors of the first fans in 8-bit mode, three bits each
+const ITE_FAN_DIVISOR: u16 = 0x0B;
+
+/// 16-bit RPM registers, high byte first (NCT6779D and later)
+const NUVOTON_FAN_RPM: [u16; 7] = [0x4C0, 0x4C2, 0x4C4, 0x4C6, 0x4C8, 0x4CA, 0x4CE];
+/// 13-bit count registers, high byte first (NCT6771F and NCT6776F)
+const NUVOTON_FAN_COUNT: [u16; 5] = [0x656, 0x658, 0x65A, 0x65C, 0x65E];
+const NUVOTON_MAX_COUNT: u16 = 0x1FFF;
+
+/// 16-bit count registers, high byte first
+const FINTEK_FAN_TACH: [u16; 4] = [0xA0, 0xB0, 0xC0, 0xD0];
+const FINTEK_MAX_COUNT: u16 = 0x0FFF;
+
+/// Number of fan headers of a chip
+pub fn fan_count(chip: &SuperIoChip) -> usize {
+    match chip.name {
+        "IT8705F" | "IT8712F" | "IT8716F" | "IT8718F" | "IT8726F" => 3,
+        "IT8628E" | "IT8665E" | "IT8686E" | "IT8688E" | "IT8689E" => 6,
+        "NCT6771F" => 4,
+        "NCT6776F" | "NCT6779D" => 5,
+        "NCT6791D" | "NCT6792D" | "NCT6793D" | "NCT6795D" => 6,
+        "F71882" => 4,
+        _ => match chip.family {
+            SuperIoFamily::Ite => 5,
+            SuperIoFamily::Nuvoton => 7,
+            SuperIoFamily::Fintek => 3,
+        },
+    }
+}
+
+/// Read every fan header of a chip in RPM. A header is `None` when it
+/// returns no usable count and 0 when the fan has stopped.
+pub fn read_fans(io: &dyn PortIo, chip: &SuperIoChip) -> Result<Vec<Option<f32>>, String> {
+    (0..fan_count(chip))
+        .map(|index| match chip.family {
+            SuperIoFamily::Ite => read_ite_fan(io, chip, index),
+            SuperIoFamily::Nuvoton => read_nuvoton_fan(io, chip, index),
+            SuperIoFamily::Fintek => read_fintek_fan(io, chip, index),
+        })
+        .collect()
+}
+
+fn read_u16(io: &dyn PortIo, chip: &SuperIoChip, register: u16) -> Result<u16, String> {
+    let high = chip.hwm.read(io, register)?;
+    let low = chip.hwm.read(io, register + 1)?;
+
+    Ok(u16::from_be_bytes([high, low]))
+}
+
+fn read_ite_fan(io: &dyn PortIo, chip: &SuperIoChip, index: usize) -> Result<Option<f32>, String> {
+    let low = chip.hwm.read(io, ITE_FAN_TACH[index])?;
+
+    // The two oldest chips only have 8-bit counters
+    if matches!(chip.name, "IT8705F" | "IT8712F") {
+        if low == 0 {
+            return Ok(None);
+        }
+
+        let divisor = if index < 2 {
+            let divisors = chip.hwm.read(io, ITE_FAN_DIVISOR)?;
+            1 << ((divisors >> (3 * index)) & 0x7)
+        } else {
+            2
+        };
+
+        return Ok(Some(if low < 0xFF {
+            1.35e6 / (low as f32 * divisor as f32)
+        } else {
+            0.0
+        }));
+    }
+
+    let high = chip.hwm.read(io, ITE_FAN_TACH_EXT[index])?;
+    let count = u16::from_le_bytes([low, high]);
+
+    Ok(match count {
+        0..=0x3F => None,
+        0xFFFF => Some(0.0),
+        count => Some(1.35e6 / (count as f32 * 2.0)),
+    })
+}
+
+fn read_nuvoton_fan(
+    io: &dyn PortIo,
+    chip: &SuperIoChip,
+    index: usize,
+) -> Result<Option<f32>, String> {
+    if matches!(chip.name, "NCT6771F" | "NCT6776F") {
+        let high = chip.hwm.read(io, NUVOTON_FAN_COUNT[index])?;
+        let low = chip.hwm.read(io, NUVOTON_FAN_COUNT[index] + 1)?;
+        let count = ((high as u16) << 5) | (low as u16 & 0x1F);
+
+        return Ok(Some(match count {
+            0 | NUVOTON_MAX_COUNT.. => 0.0,
+            count => 1.35e6 / count as f32,
+        }));
+    }
+
+    Ok(Some(read_u16(io, chip, NUVOTON_FAN_RPM[index])? as f32))
+}
+
+fn read_fintek_fan(
+    io: &dyn PortIo,
+    chip: &SuperIoChip,
+    index: usize,
+) -> Result<Option<f32>, String> {
+    let count = read_u16(io, chip, FINTEK_FAN_TACH[index])?;
+
+    Ok(match count {
+        0 => None,
+        FINTEK_MAX_COUNT.. => Some(0.0),
+        count => Some(1.5e6 / count as f32),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{
+        port_io::FakePortIo,
+        superio::{FakeHwm, HwmBank},
+    };
+
+    fn chip(name: &'static str, family: SuperIoFamily) -> (FakePortIo, FakeHwm, SuperIoChip) {
+        let hwm = FakeHwm::new(family, 0x0290);
+        let io = FakePortIo::new();
+        io.attach(hwm.ports(), hwm.clone());
+
+        let chip = SuperIoChip {
+            name,
+            family,
+            id: 0,
+            config_port: 0x2E,
+            hwm: HwmBank::new(family, 0x0290),
+        };
+        (io, hwm, chip)
+    }
+
+    fn assert_rpm(fan: Option<f32>, expected: f32) {
+        let rpm = fan.expect("fan has a reading");
+        assert!((rpm - expected).abs() < 0.1, "{} != {}", rpm, expected);
+    }
+
+    #[test]
+    fn decodes_ite_16_bit_counters() {
+        let (io, hwm, chip) = chip("IT8686E", SuperIoFamily::Ite);
+        // 0x0264 pulses on the first fan, the fourth uses the extended registers
+        hwm.set(0x0D, 0x64);
+        hwm.set(0x18, 0x02);
+        hwm.set(0x80, 0xFF);
+        hwm.set(0x81, 0xFF);
+        hwm.set(0x0E, 0x20);
+
+        let fans = read_fans(&io, &chip).unwrap();
+        assert_eq!(fans.len(), 6);
+        assert_rpm(fans[0], 1.35e6 / (0x0264 as f32 * 2.0));
+        assert_eq!(fans[1], None);
+        assert_eq!(fans[3], Some(0.0));
+    }
+
+    #[test]
+    fn decodes_ite_8_bit_counters_with_divisor() {
+        let (io, hwm, chip) = chip("IT8712F", SuperIoFamily::Ite);
+        hwm.set(0x0B, 0b011_011);
+        hwm.set(0x0D, 0x40);
+        hwm.set(0x0E, 0xFF);
+        hwm.set(0x0F, 0x80);
+
+        let fans = read_fans(&io, &chip).unwrap();
+        assert_rpm(fans[0], 1.35e6 / (0x40 as f32 * 8.0));
+        assert_eq!(fans[1], Some(0.0));
+        assert_rpm(fans[2], 1.35e6 / (0x80 as f32 * 2.0));
+    }
+
+    #[test]
+    fn decodes_nuvoton_counts_and_rpm() {
+        let (io, hwm, chip) = chip("NCT6776F", SuperIoFamily::Nuvoton);
+        hwm.set(0x656, 0x10);
+        hwm.set(0x657, 0x05);
+        hwm.set(0x658, 0xFF);
+        hwm.set(0x659, 0x1F);
+
+        let fans = read_fans(&io, &chip).unwrap();
+        assert_rpm(fans[0], 1.35e6 / ((0x10 << 5) | 0x05) as f32);
+        assert_eq!(fans[1], Some(0.0));
+        assert_eq!(fans[2], Some(0.0));
+
+        let (io, hwm, chip) = self::chip("NCT6779D", SuperIoFamily::Nuvoton);
+        hwm.set(0x4C0, 0x04);
+        hwm.set(0x4C1, 0xB0);
+        assert_eq!(read_fans(&io, &chip).unwrap()[0], Some(1200.0));
+    }
+
+    #[test]
+    fn decodes_fintek_counts() {
+        let (io, hwm, chip) = chip("F71882", SuperIoFamily::Fintek);
+        hwm.set(0xA0, 0x01);
+        hwm.set(0xA1, 0xF4);
+        hwm.set(0xB0, 0x0F);
+        hwm.set(0xB1, 0xFF);
+
+        let fans = read_fans(&io, &chip).unwrap();
+        assert_eq!(fans.len(), 4);
+        assert_rpm(fans[0], 1.5e6 / 500.0);
+        assert_eq!(fans[1], Some(0.0));
+        assert_eq!(fans[2], None);
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::{Arc, Mutex};
+
+use crate::system::{port_io::PortIo, superio::chip::SuperIoFamily};
+
+/// Offsets of the index and data ports from the base address
+const ADDRESS_OFFSET: u16 = 5;
+const DATA_OFFSET: u16 = 6;
+
+/// Nuvoton bank select register, present in every bank
+const NUVOTON_BANK_SELECT: u8 = 0x4E;
+
+/// One lock per hardware monitor, by base address. Banks are plain copies, so
+/// the lock lives here to be shared by every copy in every thread.
+static BANK_LOCKS: Mutex<Vec<(u16, Arc<Mutex<()>>)>> = Mutex::new(Vec::new());
+
+/// Hardware monitor register bank behind an index/data port pair.
+///
+/// Registers are addressed as `bank << 8 | index`. Only Nuvoton chips have
+/// banks; on ITE and Fintek chips the bank must be 0.
+/// Accesses to the same monitor are serialized, whichever copy they go through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HwmBank {
+    pub family: SuperIoFamily,
+    pub base: u16,
+}
+
+impl HwmBank {
+    pub fn new(family: SuperIoFamily, base: u16) -> Self {
+        Self { family, base }
+    }
+
+    pub fn read(&self, io: &dyn PortIo, register: u16) -> Result<u8, String> {
+        self.locked(|| self.read_unlocked(io, register))
+    }
+
+    pub fn write(&self, io: &dyn PortIo, register: u16, value: u8) -> Result<(), String> {
+        self.locked(|| self.write_unlocked(io, register, value))
+    }
+
+    /// Read a register and write back `f` of its value, with no other access
+    /// to the monitor in between
+    pub fn update(
+        &self,
+        io: &dyn PortIo,
+        register: u16,
+        f: impl FnOnce(u8) -> u8,
+    ) -> Result<(), String> {
+        self.locked(|| {
+            let value = self.read_unlocked(io, register)?;
+            self.write_unlocked(io, register, f(value))
+        })
+    }
+
+    // Bank select, index and data must not interleave with another access
+    fn locked<R>(&self, f: impl FnOnce() -> Result<R, String>) -> Result<R, String> {
+        let lock = {
+            let mut locks = BANK_LOCKS
+                .lock()
+                .map_err(|_| "Hardware monitor locks poisoned")?;
+            match locks.iter().find(|(base, _)| *base == self.base) {
+                Some((_, lock)) => lock.clone(),
+                None => {
+                    let lock = Arc::new(Mutex::new(()));
+                    locks.push((self.base, lock.clone()));
+                    lock
+                }
+            }
+        };
+        let _guard = lock.lock().map_err(|_| "Hardware monitor lock poisoned")?;
+
+        f()
+    }
+
+    fn read_unlocked(&self, io: &dyn PortIo, register: u16) -> Result<u8, String> {
+        self.select(io, register)?;
+        io.inb(self.base + DATA_OFFSET)
+    }
+
+    fn write_unlocked(&self, io: &dyn PortIo, register: u16, value: u8) -> Result<(), String> {
+        self.select(io, register)?;
+        io.outb(self.base + DATA_OFFSET, value)
+    }
+
+    fn select(&self, io: &dyn PortIo, register: u16) -> Result<(), String> {
+        let [bank, index] = register.to_be_bytes();
+
+        if self.family == SuperIoFamily::Nuvoton {
+            io.outb(self.base + ADDRESS_OFFSET, NUVOTON_BANK_SELECT)?;
+            io.outb(self.base + DATA_OFFSET, bank)?;
+        } else if bank != 0 {
+            return Err(format!("{:?} hardware monitors have no banks", self.family));
+        }
+
+        io.outb(self.base + ADDRESS_OFFSET, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{port_io::FakePortIo, superio::FakeHwm};
+
+    fn bus(family: SuperIoFamily, base: u16) -> (FakePortIo, FakeHwm) {
+        let hwm = FakeHwm::new(family, base);
+        let io = FakePortIo::new();
+        io.attach(hwm.ports(), hwm.clone());
+        (io, hwm)
+    }
+
+    #[test]
+    fn switches_nuvoton_banks() {
+        let (io, hwm) = bus(SuperIoFamily::Nuvoton, 0x0290);
+        hwm.set(0x0027, 0x11);
+        hwm.set(0x0627, 0x66);
+        let bank = HwmBank::new(SuperIoFamily::Nuvoton, 0x0290);
+
+        assert_eq!(bank.read(&io, 0x0627), Ok(0x66));
+        assert_eq!(bank.read(&io, 0x0027), Ok(0x11));
+
+        bank.write(&io, 0x0109, 0x80).unwrap();
+        assert_eq!(hwm.get(0x0109), 0x80);
+        assert_eq!(hwm.get(0x0009), 0x00);
+    }
+
+    #[test]
+    fn flat_banks_reject_bank_numbers() {
+        let (io, hwm) = bus(SuperIoFamily::Ite, 0x0A40);
+        hwm.set(0x0020, 0x42);
+        let bank = HwmBank::new(SuperIoFamily::Ite, 0x0A40);
+
+        assert_eq!(bank.read(&io, 0x0020), Ok(0x42));
+        assert!(bank.read(&io, 0x0120).is_err());
+        assert!(bank.write(&io, 0x0120, 0).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+//! Super I/O chips and the hardware monitor they contain.
+//!
+//! Motherboard fans, voltages and temperatures are read from the environment
+//! controller of the Super I/O chip. The chip is found through its
+//! configuration interface at port 0x2E or 0x4E, which also reports where the
+//! controller's own index/data ports are.
+
+pub mod chip;
+pub mod detect;
+pub mod fake;
+pub mod fan;
+pub mod hwm;
+pub mod pwm;
+pub mod temperature;
+pub mod voltage;
+
+pub use chip::{identify, ChipInfo, SuperIoFamily};
+pub use detect::{detect, probe, SuperIoChip};
+pub use fake::{FakeHwm, FakeSuperIo};
+pub use hwm::HwmBank;
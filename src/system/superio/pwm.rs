@@ -0,0 +1,166 @@
+//! PWM fan outputs of the supported hardware monitors.
+//!
+//! Every output has a mode (automatic curves of the chip, or manual) and a
+//! duty cycle from 0 to 255. Switching to manual keeps the other bits of the
+//! mode register so the original mode can be written back unchanged.
+
+use crate::system::{
+    port_io::PortIo,
+    superio::{chip::SuperIoFamily, detect::SuperIoChip, fan::fan_count},
+};
+
+/// Control registers: bit 7 selects automatic mode, bits 6:0 hold the duty
+/// of the older chips
+const ITE_PWM_CONTROL: [u16; 6] = [0x15, 0x16, 0x17, 0x7F, 0xA7, 0xAF];
+/// 8-bit duty of the newer chips
+const ITE_PWM_DUTY: [u16; 6] = [0x63, 0x6B, 0x73, 0x7B, 0xA3, 0xAB];
+
+/// Bits 7:4 select the mode, 0 is manual
+const NUVOTON_PWM_MODE: [u16; 7] = [0x102, 0x202, 0x302, 0x802, 0x902, 0xA02, 0xB02];
+/// Duty used in manual mode
+const NUVOTON_PWM_COMMAND: [u16; 7] = [0x109, 0x209, 0x309, 0x809, 0x909, 0xA09, 0xB09];
+/// Duty currently driven, whatever the mode
+const NUVOTON_PWM_OUTPUT: [u16; 7] = [0x001, 0x003, 0x011, 0x013, 0x015, 0xA09, 0xB09];
+
+/// Two bits per output, 0b11 is manual duty mode
+const FINTEK_FAN_MODE: u16 = 0x96;
+const FINTEK_MANUAL_DUTY: u8 = 0b11;
+const FINTEK_PWM_DUTY: [u16; 4] = [0xA3, 0xB3, 0xC3, 0xD3];
+
+/// Mode and duty of a PWM output in the encoding of its backend, enough to
+/// put the output back as it was. On Nuvoton chips the duty is the manual
+/// command, which is not what the fan runs at in an automatic mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PwmState {
+    pub mode: u8,
+    pub duty: u8,
+}
+
+/// Number of PWM outputs of a chip
+pub fn pwm_count(chip: &SuperIoChip) -> usize {
+    let outputs = match chip.family {
+        SuperIoFamily::Ite => ITE_PWM_CONTROL.len(),
+        SuperIoFamily::Nuvoton => NUVOTON_PWM_MODE.len(),
+        SuperIoFamily::Fintek => FINTEK_PWM_DUTY.len(),
+    };
+
+    outputs.min(fan_count(chip))
+}
+
+// The oldest ITE chips have a 7-bit duty in the control register
+fn has_ite_duty_register(chip: &SuperIoChip) -> bool {
+    !matches!(
+        chip.name,
+        "IT8705F" | "IT8712F" | "IT8716F" | "IT8718F" | "IT8720F" | "IT8726F"
+    )
+}
+
+fn check_channel(chip: &SuperIoChip, channel: usize) -> Result<(), String> {
+    if channel >= pwm_count(chip) {
+        return Err(format!("{} has no PWM output {}", chip.name, channel));
+    }
+
+    Ok(())
+}
+
+/// Read the mode and duty of an output, to be written back with [write_pwm]
+pub fn read_pwm(io: &dyn PortIo, chip: &SuperIoChip, channel: usize) -> Result<PwmState, String> {
+    check_channel(chip, channel)?;
+
+    match chip.family {
+        SuperIoFamily::Ite => {
+            let mode = chip.hwm.read(io, ITE_PWM_CONTROL[channel])?;
+            let duty = if has_ite_duty_register(chip) {
+                chip.hwm.read(io, ITE_PWM_DUTY[channel])?
+            } else {
+                (mode & 0x7F) << 1
+            };
+            Ok(PwmState { mode, duty })
+        }
+        SuperIoFamily::Nuvoton => Ok(PwmState {
+            mode: chip.hwm.read(io, NUVOTON_PWM_MODE[channel])?,
+            duty: chip.hwm.read(io, NUVOTON_PWM_COMMAND[channel])?,
+        }),
+        SuperIoFamily::Fintek => Ok(PwmState {
+            mode: chip.hwm.read(io, FINTEK_FAN_MODE)?,
+            duty: chip.hwm.read(io, FINTEK_PWM_DUTY[channel])?,
+        }),
+    }
+}
+
+/// Duty the output is driving right now, whatever the mode
+pub fn read_duty(io: &dyn PortIo, chip: &SuperIoChip, channel: usize) -> Result<u8, String> {
+    match chip.family {
+        SuperIoFamily::Nuvoton => {
+            check_channel(chip, channel)?;
+            chip.hwm.read(io, NUVOTON_PWM_OUTPUT[channel])
+        }
+        _ => read_pwm(io, chip, channel).map(|state| state.duty),
+    }
+}
+
+/// Switch an output to manual mode and drive it at `duty`
+pub fn write_manual(
+    io: &dyn PortIo,
+    chip: &SuperIoChip,
+    channel: usize,
+    duty: u8,
+) -> Result<(), String> {
+    check_channel(chip, channel)?;
+
+    match chip.family {
+        SuperIoFamily::Ite if has_ite_duty_register(chip) => {
+            chip.hwm
+                .update(io, ITE_PWM_CONTROL[channel], |control| control & 0x7F)?;
+            chip.hwm.write(io, ITE_PWM_DUTY[channel], duty)
+        }
+        SuperIoFamily::Ite => chip.hwm.write(io, ITE_PWM_CONTROL[channel], duty >> 1),
+        SuperIoFamily::Nuvoton => {
+            chip.hwm
+                .update(io, NUVOTON_PWM_MODE[channel], |mode| mode & 0x0F)?;
+            chip.hwm.write(io, NUVOTON_PWM_COMMAND[channel], duty)
+        }
+        SuperIoFamily::Fintek => {
+            let shift = 2 * channel;
+            chip.hwm.update(io, FINTEK_FAN_MODE, |mode| {
+                mode | (FINTEK_MANUAL_DUTY << shift)
+            })?;
+            chip.hwm.write(io, FINTEK_PWM_DUTY[channel], duty)
+        }
+    }
+}
+
+/// Write back a state read with [read_pwm]
+pub fn write_pwm(
+    io: &dyn PortIo,
+    chip: &SuperIoChip,
+    channel: usize,
+    state: PwmState,
+) -> Result<(), String> {
+    check_channel(chip, channel)?;
+
+    // Restore the duty first so the fan never runs at the manual duty in the
+    // original mode
+    match chip.family {
+        SuperIoFamily::Ite => {
+            if has_ite_duty_register(chip) {
+                chip.hwm.write(io, ITE_PWM_DUTY[channel], state.duty)?;
+            }
+            chip.hwm.write(io, ITE_PWM_CONTROL[channel], state.mode)
+        }
+        SuperIoFamily::Nuvoton => {
+            chip.hwm
+                .write(io, NUVOTON_PWM_COMMAND[channel], state.duty)?;
+            chip.hwm.write(io, NUVOTON_PWM_MODE[channel], state.mode)
+        }
+        SuperIoFamily::Fintek => {
+            // Other outputs share the mode register and may be controlled too
+            let mask = 0b11 << (2 * channel);
+
+            chip.hwm.write(io, FINTEK_PWM_DUTY[channel], state.duty)?;
+            chip.hwm.update(io, FINTEK_FAN_MODE, |mode| {
+                (mode & !mask) | (state.mode & mask)
+            })
+        }
+    }
+}
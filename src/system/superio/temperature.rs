@@ -0,0 +1,308 @@
+//! Temperature inputs of the supported hardware monitors.
+//!
+//! ITE and Fintek inputs are fixed thermistor or diode pins. Nuvoton inputs
+//! are monitoring slots that each read one of many sources (thermistor pins,
+//! PECI, the PCH over SMBus), chosen by the BIOS through a source register.
+
+use crate::system::{
+    port_io::PortIo,
+    superio::{chip::SuperIoFamily, detect::SuperIoChip},
+};
+
+const ITE_TEMPERATURE_BASE: u16 = 0x29;
+const ITE_TEMPERATURE_COUNT: u16 = 3;
+
+/// Whole degrees, half-degree bit 7 and source select of each slot
+const NUVOTON_TEMPERATURE: [(u16, u16, u16); 7] = [
+    (0x027, 0x000, 0x621),
+    (0x073, 0x074, 0x100),
+    (0x075, 0x076, 0x200),
+    (0x077, 0x078, 0x300),
+    (0x079, 0x07A, 0x800),
+    (0x07B, 0x07C, 0x900),
+    (0x150, 0x151, 0x622),
+];
+const NUVOTON_TEMPERATURE_LEGACY: [(u16, u16, u16); 3] = [
+    (0x027, 0x000, 0x621),
+    (0x150, 0x151, 0x622),
+    (0x250, 0x251, 0x623),
+];
+
+/// Source ids of the NCT6771F
+const NCT6771F_SOURCES: &[(u8, &str)] = &[
+    (1, "SYSTIN"),
+    (2, "CPUTIN"),
+    (3, "AUXTIN"),
+    (4, "SMBUSMASTER"),
+    (5, "PECI Agent 0"),
+    (6, "PECI Agent 1"),
+    (7, "PECI Agent 2"),
+    (8, "PECI Agent 3"),
+    (9, "PECI Agent 4"),
+    (10, "PECI Agent 5"),
+    (11, "PECI Agent 6"),
+    (12, "PECI Agent 7"),
+    (13, "PCH CPU Max"),
+    (14, "PCH Chip"),
+    (15, "PCH CPU"),
+    (16, "PCH MCH"),
+    (17, "PCH DIMM0"),
+    (18, "PCH DIMM1"),
+    (19, "PCH DIMM2"),
+    (20, "PCH DIMM3"),
+];
+
+/// Source ids of the NCT6776F
+const NCT6776F_SOURCES: &[(u8, &str)] = &[
+    (1, "SYSTIN"),
+    (2, "CPUTIN"),
+    (3, "AUXTIN"),
+    (4, "SMBUSMASTER 0"),
+    (5, "SMBUSMASTER 1"),
+    (6, "SMBUSMASTER 2"),
+    (7, "SMBUSMASTER 3"),
+    (8, "SMBUSMASTER 4"),
+    (9, "SMBUSMASTER 5"),
+    (10, "SMBUSMASTER 6"),
+    (11, "SMBUSMASTER 7"),
+    (12, "PECI Agent 0"),
+    (13, "PECI Agent 1"),
+    (14, "PCH CPU Max"),
+    (15, "PCH Chip"),
+    (16, "PCH CPU"),
+    (17, "PCH MCH"),
+    (18, "PCH DIMM0"),
+    (19, "PCH DIMM1"),
+    (20, "PCH DIMM2"),
+    (21, "PCH DIMM3"),
+    (22, "BYTE_TEMP"),
+];
+
+/// Source ids of the NCT6779D and later
+const NUVOTON_SOURCES: &[(u8, &str)] = &[
+    (1, "SYSTIN"),
+    (2, "CPUTIN"),
+    (3, "AUXTIN0"),
+    (4, "AUXTIN1"),
+    (5, "AUXTIN2"),
+    (6, "AUXTIN3"),
+    (7, "AUXTIN4"),
+    (16, "PECI Agent 0"),
+    (17, "PECI Agent 1"),
+    (18, "PCH CPU Max"),
+    (19, "PCH Chip"),
+    (20, "PCH CPU"),
+    (21, "PCH MCH"),
+    (26, "BYTE_TEMP0"),
+    (27, "BYTE_TEMP1"),
+];
+
+const FINTEK_TEMPERATURE_BASE: u16 = 0x70;
+const FINTEK_TEMPERATURE_COUNT: u16 = 3;
+
+/// A temperature input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemperatureInput {
+    /// `None` when no sensor is connected
+    pub celsius: Option<f32>,
+    /// What the input measures, when the chip reports it (Nuvoton)
+    pub source: Option<&'static str>,
+}
+
+/// Read every temperature input of a chip
+pub fn read_temperatures(
+    io: &dyn PortIo,
+    chip: &SuperIoChip,
+) -> Result<Vec<TemperatureInput>, String> {
+    match chip.family {
+        SuperIoFamily::Ite => (0..ITE_TEMPERATURE_COUNT)
+            .map(|i| {
+                let value = chip.hwm.read(io, ITE_TEMPERATURE_BASE + i)?;
+                Ok(TemperatureInput {
+                    celsius: whole_degrees(value),
+                    source: None,
+                })
+            })
+            .collect(),
+        SuperIoFamily::Nuvoton => {
+            let (slots, sources): (&[(u16, u16, u16)], _) = match chip.name {
+                "NCT6771F" => (&NUVOTON_TEMPERATURE_LEGACY, NCT6771F_SOURCES),
+                "NCT6776F" => (&NUVOTON_TEMPERATURE_LEGACY, NCT6776F_SOURCES),
+                _ => (&NUVOTON_TEMPERATURE, NUVOTON_SOURCES),
+            };
+            slots
+                .iter()
+                .map(|&slot| read_nuvoton_slot(io, chip, slot, sources))
+                .collect()
+        }
+        SuperIoFamily::Fintek => (0..FINTEK_TEMPERATURE_COUNT)
+            .map(|i| read_fintek(io, chip, i))
+            .collect(),
+    }
+}
+
+// Signed whole degrees, with the values chips use for an open or absent input
+fn whole_degrees(value: u8) -> Option<f32> {
+    match value as i8 {
+        -128 | -127 | 127 => None,
+        celsius => Some(celsius as f32),
+    }
+}
+
+fn read_nuvoton_slot(
+    io: &dyn PortIo,
+    chip: &SuperIoChip,
+    (register, half, source): (u16, u16, u16),
+    sources: &[(u8, &'static str)],
+) -> Result<TemperatureInput, String> {
+    let source = chip.hwm.read(io, source)? & 0x1F;
+    if source == 0 {
+        return Ok(TemperatureInput {
+            celsius: None,
+            source: None,
+        });
+    }
+
+    let mut celsius = whole_degrees(chip.hwm.read(io, register)?);
+    if half != 0 && chip.hwm.read(io, half)? & 0x80 != 0 {
+        celsius = celsius.map(|c| c + 0.5);
+    }
+
+    Ok(TemperatureInput {
+        celsius,
+        source: sources
+            .iter()
+            .find(|(id, _)| *id == source)
+            .map(|(_, name)| *name),
+    })
+}
+
+fn read_fintek(
+    io: &dyn PortIo,
+    chip: &SuperIoChip,
+    index: u16,
+) -> Result<TemperatureInput, String> {
+    let celsius = if chip.name == "F71858" {
+        // 11-bit value, high byte first, 0.125 degrees in bits 7:5 of the low byte
+        let high = chip.hwm.read(io, FINTEK_TEMPERATURE_BASE + 2 * index)?;
+        let low = chip.hwm.read(io, FINTEK_TEMPERATURE_BASE + 2 * index + 1)?;
+        match high {
+            0xBB | 0xCC => None,
+            _ => Some(high as i8 as f32 + (low >> 5) as f32 * 0.125),
+        }
+    } else {
+        whole_degrees(
+            chip.hwm
+                .read(io, FINTEK_TEMPERATURE_BASE + 2 * (index + 1))?,
+        )
+    };
+
+    Ok(TemperatureInput {
+        celsius,
+        source: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{
+        port_io::FakePortIo,
+        superio::{FakeHwm, HwmBank},
+    };
+
+    fn chip(name: &'static str, family: SuperIoFamily) -> (FakePortIo, FakeHwm, SuperIoChip) {
+        let hwm = FakeHwm::new(family, 0x0290);
+        let io = FakePortIo::new();
+        io.attach(hwm.ports(), hwm.clone());
+
+        let chip = SuperIoChip {
+            name,
+            family,
+            id: 0,
+            config_port: 0x2E,
+            hwm: HwmBank::new(family, 0x0290),
+        };
+        (io, hwm, chip)
+    }
+
+    #[test]
+    fn reads_legacy_nuvoton_slots() {
+        let (io, hwm, chip) = chip("NCT6776F", SuperIoFamily::Nuvoton);
+        hwm.set(0x621, 1);
+        hwm.set(0x027, 35);
+        hwm.set(0x622, 12);
+        hwm.set(0x150, 48);
+        hwm.set(0x151, 0x80);
+        hwm.set(0x623, 4);
+        hwm.set(0x250, 0x80);
+        // SmartFan temperature selects, not sources
+        hwm.set(0x100, 2);
+        hwm.set(0x200, 3);
+
+        let temperatures = read_temperatures(&io, &chip).unwrap();
+
+        assert_eq!(
+            temperatures,
+            [
+                TemperatureInput {
+                    celsius: Some(35.0),
+                    source: Some("SYSTIN"),
+                },
+                TemperatureInput {
+                    celsius: Some(48.5),
+                    source: Some("PECI Agent 0"),
+                },
+                TemperatureInput {
+                    celsius: None,
+                    source: Some("SMBUSMASTER 0"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_sources_per_chip() {
+        let (io, hwm, nct6771) = chip("NCT6771F", SuperIoFamily::Nuvoton);
+        hwm.set(0x621, 5);
+        hwm.set(0x027, 40);
+
+        let temperatures = read_temperatures(&io, &nct6771).unwrap();
+        assert_eq!(temperatures[0].source, Some("PECI Agent 0"));
+        // Unselected slots have no reading
+        assert_eq!(temperatures[1].celsius, None);
+        assert_eq!(temperatures[1].source, None);
+
+        let (io, hwm, nct6798) = chip("NCT6798D", SuperIoFamily::Nuvoton);
+        hwm.set(0x621, 16);
+        hwm.set(0x027, 40);
+
+        let temperatures = read_temperatures(&io, &nct6798).unwrap();
+        assert_eq!(temperatures.len(), NUVOTON_TEMPERATURE.len());
+        assert_eq!(temperatures[0].source, Some("PECI Agent 0"));
+    }
+
+    #[test]
+    fn decodes_ite_and_fintek() {
+        let (io, hwm, ite) = chip("IT8686E", SuperIoFamily::Ite);
+        hwm.set(0x29, 42);
+        hwm.set(0x2A, (-5i8) as u8);
+        hwm.set(0x2B, 0x80);
+
+        let celsius: Vec<_> = read_temperatures(&io, &ite)
+            .unwrap()
+            .iter()
+            .map(|t| t.celsius)
+            .collect();
+        assert_eq!(celsius, [Some(42.0), Some(-5.0), None]);
+
+        let (io, hwm, f71858) = chip("F71858", SuperIoFamily::Fintek);
+        hwm.set(0x70, 50);
+        hwm.set(0x71, 0b1010_0000);
+        hwm.set(0x72, 0xCC);
+
+        let temperatures = read_temperatures(&io, &f71858).unwrap();
+        assert_eq!(temperatures[0].celsius, Some(50.625));
+        assert_eq!(temperatures[1].celsius, None);
+    }
+}
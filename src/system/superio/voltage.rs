@@ -0,0 +1,206 @@
+//! Voltage inputs of the supported hardware monitors.
+//!
+//! The ADCs measure up to about 2-3 V at the pin. Higher rails go through a
+//! resistor divider on the board, so the values here still need the board's
+//! scaling before they mean anything.
+
+use crate::system::{
+    port_io::PortIo,
+    superio::{chip::SuperIoFamily, detect::SuperIoChip},
+};
+
+const ITE_VOLTAGE_BASE: u16 = 0x20;
+const ITE_VOLTAGE_COUNT: u16 = 9;
+
+/// NCT6779D and later
+const NUVOTON_VOLTAGE: [u16; 15] = [
+    0x480, 0x481, 0x482, 0x483, 0x484, 0x485, 0x486, 0x487, 0x488, 0x489, 0x48A, 0x48B, 0x48C,
+    0x48D, 0x48E,
+];
+/// NCT6771F and NCT6776F
+const NUVOTON_VOLTAGE_LEGACY: [u16; 9] = [
+    0x020, 0x021, 0x022, 0x023, 0x024, 0x025, 0x026, 0x550, 0x551,
+];
+/// Bit 0 enables the VBAT monitor, which is otherwise off to save the battery
+const NUVOTON_VBAT_CONTROL: u16 = 0x05D;
+
+const FINTEK_VOLTAGE_BASE: u16 = 0x20;
+
+/// Volts per ADC step
+fn voltage_gain(chip: &SuperIoChip) -> f32 {
+    match chip.name {
+        "IT8705F" | "IT8712F" | "IT8716F" | "IT8718F" | "IT8720F" | "IT8726F" => 0.016,
+        "IT8613E" | "IT8625E" | "IT8655E" | "IT8665E" => 0.0109,
+        _ if chip.family == SuperIoFamily::Ite => 0.012,
+        _ => 0.008,
+    }
+}
+
+/// Read every voltage input of a chip in volts, after the chip's internal
+/// dividers but before the board's. An input is `None` when it is unused or
+/// disabled.
+pub fn read_voltages(io: &dyn PortIo, chip: &SuperIoChip) -> Result<Vec<Option<f32>>, String> {
+    let gain = voltage_gain(chip);
+
+    let raw = match chip.family {
+        SuperIoFamily::Ite => (0..ITE_VOLTAGE_COUNT)
+            .map(|i| chip.hwm.read(io, ITE_VOLTAGE_BASE + i).map(Some))
+            .collect::<Result<Vec<_>, String>>()?,
+        SuperIoFamily::Nuvoton => read_nuvoton(io, chip)?,
+        SuperIoFamily::Fintek => {
+            let count = if chip.name == "F71858" { 3 } else { 9 };
+            (0..count)
+                .map(|i| chip.hwm.read(io, FINTEK_VOLTAGE_BASE + i).map(Some))
+                .collect::<Result<Vec<_>, String>>()?
+        }
+    };
+
+    // A saturated or zero reading is a floating or grounded pin
+    Ok(raw
+        .into_iter()
+        .enumerate()
+        .map(|(input, value)| {
+            value
+                .filter(|&v| v != 0 && v != 0xFF)
+                .map(|v| v as f32 * gain * internal_scale(chip, input))
+        })
+        .collect())
+}
+
+/// Divider built into the chip in front of some inputs: AVCC, 3VCC, 3VSB and
+/// VBAT on Nuvoton, 3VSB and VBAT on ITE chips with the 12 mV ADC, 3VCC, 3VSB
+/// and VBAT on Fintek chips other than the F71858
+fn internal_scale(chip: &SuperIoChip, input: usize) -> f32 {
+    let scaled = match chip.family {
+        SuperIoFamily::Nuvoton => matches!(input, 2 | 3 | 7 | 8),
+        SuperIoFamily::Ite => voltage_gain(chip) < 0.016 && matches!(input, 7 | 8),
+        SuperIoFamily::Fintek => chip.name != "F71858" && matches!(input, 0 | 7 | 8),
+    };
+
+    if scaled {
+        2.0
+    } else {
+        1.0
+    }
+}
+
+fn read_nuvoton(io: &dyn PortIo, chip: &SuperIoChip) -> Result<Vec<Option<u8>>, String> {
+    let (registers, vbat): (&[u16], u16) = if matches!(chip.name, "NCT6771F" | "NCT6776F") {
+        (&NUVOTON_VOLTAGE_LEGACY, 0x551)
+    } else {
+        (&NUVOTON_VOLTAGE, 0x488)
+    };
+    let vbat_enabled = chip.hwm.read(io, NUVOTON_VBAT_CONTROL)? & 0x01 != 0;
+
+    registers
+        .iter()
+        .map(|&register| {
+            if register == vbat && !vbat_enabled {
+                return Ok(None);
+            }
+            chip.hwm.read(io, register).map(Some)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{
+        port_io::FakePortIo,
+        superio::{FakeHwm, HwmBank},
+    };
+
+    fn chip(name: &'static str, family: SuperIoFamily) -> (FakePortIo, FakeHwm, SuperIoChip) {
+        let hwm = FakeHwm::new(family, 0x0290);
+        let io = FakePortIo::new();
+        io.attach(hwm.ports(), hwm.clone());
+
+        let chip = SuperIoChip {
+            name,
+            family,
+            id: 0,
+            config_port: 0x2E,
+            hwm: HwmBank::new(family, 0x0290),
+        };
+        (io, hwm, chip)
+    }
+
+    fn assert_volts(actual: Option<f32>, expected: f32) {
+        let actual = actual.expect("no reading");
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn scales_nuvoton_internal_dividers() {
+        let (io, hwm, chip) = chip("NCT6798D", SuperIoFamily::Nuvoton);
+        hwm.set(0x480, 100);
+        hwm.set(0x482, 200);
+        hwm.set(0x488, 190);
+        hwm.set(NUVOTON_VBAT_CONTROL, 0x01);
+
+        let volts = read_voltages(&io, &chip).unwrap();
+
+        assert_eq!(volts.len(), NUVOTON_VOLTAGE.len());
+        assert_volts(volts[0], 0.8);
+        assert_volts(volts[2], 3.2);
+        assert_volts(volts[8], 3.04);
+        // Unconnected input
+        assert_eq!(volts[1], None);
+    }
+
+    #[test]
+    fn skips_disabled_vbat_on_legacy_nuvoton() {
+        let (io, hwm, chip) = chip("NCT6776F", SuperIoFamily::Nuvoton);
+        hwm.set(0x020, 150);
+        hwm.set(0x551, 190);
+
+        let volts = read_voltages(&io, &chip).unwrap();
+
+        assert_eq!(volts.len(), NUVOTON_VOLTAGE_LEGACY.len());
+        assert_volts(volts[0], 1.2);
+        assert_eq!(volts[8], None);
+    }
+
+    #[test]
+    fn scales_ite_by_adc() {
+        let (io, hwm, it8686) = chip("IT8686E", SuperIoFamily::Ite);
+        hwm.set(0x20, 100);
+        hwm.set(0x27, 140);
+
+        let volts = read_voltages(&io, &it8686).unwrap();
+        assert_volts(volts[0], 1.2);
+        assert_volts(volts[7], 3.36);
+
+        // The 16 mV ADC has no internal divider
+        let (io, hwm, it8720) = chip("IT8720F", SuperIoFamily::Ite);
+        hwm.set(0x27, 200);
+        assert_volts(read_voltages(&io, &it8720).unwrap()[7], 3.2);
+    }
+
+    #[test]
+    fn scales_fintek_except_f71858() {
+        let (io, hwm, f71889) = chip("F71889F", SuperIoFamily::Fintek);
+        hwm.set(0x20, 200);
+        hwm.set(0x21, 100);
+        hwm.set(0x28, 190);
+
+        let volts = read_voltages(&io, &f71889).unwrap();
+        assert_eq!(volts.len(), 9);
+        assert_volts(volts[0], 3.2);
+        assert_volts(volts[1], 0.8);
+        assert_volts(volts[8], 3.04);
+
+        let (io, hwm, f71858) = chip("F71858", SuperIoFamily::Fintek);
+        hwm.set(0x20, 200);
+
+        let volts = read_voltages(&io, &f71858).unwrap();
+        assert_eq!(volts.len(), 3);
+        assert_volts(volts[0], 1.6);
+    }
+}
@@ -1,14 +1,36 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
+#[cfg(windows)]
+use crate::system::kernal_driver::DriverBuilder;
 use crate::system::{
-    cpu::cpu::{gather_cpus, Cpu},
-    kernal_driver::{DriverBuilder, KernelDriver},
+    cpu::{
+        cpu::{gather_cpus, Cpu},
+        group_affinity::AffinityPool,
+        group_affinity::GroupAffinity,
+        hotplug::{merge_offline, update_online_state, TopologyChanges},
+        machine_check::{MachineCheckEvent, MachineCheckMonitor},
+    },
+    kernal_driver::{KernelDriver, MsrGuard, MsrRestoreLog},
+    motherboard::{
+        fan_curve::{BoundCurve, TemperatureSource},
+        gather_motherboard, FanControl, FanCurveConfig, FanCurveController, FanLimits,
+        FanRestoreLog, Motherboard, PwmOutput,
+    },
+    pci::{enumerate, gather_pci_devices, smbus_controller, PciDevice, PciIds},
 };
 
 #[derive(Debug)]
 pub struct System {
     driver: Arc<KernelDriver>,
     pub cpu: Option<Vec<Cpu>>,
+    pub pci: Option<Vec<PciDevice>>,
+    pub motherboard: Option<Motherboard>,
+    pci_ids: Option<PciIds>,
+    /// PCI scan of the build, where the CPU backends find their devices
+    platform_devices: Vec<PciDevice>,
+    machine_checks: MachineCheckMonitor,
+    msr_restore: Arc<MsrRestoreLog>,
+    fan_restore: Arc<FanRestoreLog>,
 }
 
 impl System {
@@ -17,18 +39,212 @@ impl System {
     }
 
     // Internal constructor used by builder
-    fn new(driver: Arc<KernelDriver>, cpu: Option<Vec<Cpu>>) -> Self {
-        Self { driver, cpu }
+    fn new(
+        driver: Arc<KernelDriver>,
+        cpu: Option<Vec<Cpu>>,
+        pci: Option<Vec<PciDevice>>,
+        motherboard: Option<Motherboard>,
+        pci_ids: Option<PciIds>,
+        platform_devices: Vec<PciDevice>,
+        msr_restore: Arc<MsrRestoreLog>,
+    ) -> Self {
+        Self {
+            driver,
+            cpu,
+            pci,
+            motherboard,
+            pci_ids,
+            platform_devices,
+            machine_checks: MachineCheckMonitor::new(),
+            msr_restore,
+            fan_restore: FanRestoreLog::new(),
+        }
+    }
+
+    /// Start a worker pool pinned to every logical processor of the gathered CPUs
+    pub fn worker_pool(&self) -> Result<AffinityPool, String> {
+        let cpus = self.cpu.as_ref().ok_or("CPU subsystem not enabled")?;
+
+        let affinities = cpus
+            .iter()
+            .flat_map(|cpu| cpu.cores())
+            .flat_map(|core| &core.threads)
+            .map(|thread| thread.affinity.clone())
+            .collect();
+
+        AffinityPool::new(affinities)
+    }
+
+    /// Check which logical processors went offline or came back since the
+    /// topology was gathered, and mark the affected threads accordingly
+    pub fn detect_topology_changes(&mut self) -> Result<TopologyChanges, String> {
+        let cpus = self.cpu.as_mut().ok_or("CPU subsystem not enabled")?;
+
+        update_online_state(cpus)
+    }
+
+    /// Rebuild the CPU topology from the processors that are online now.
+    /// Package, core and thread ids are stable; threads that are offline stay
+    /// in the tree marked as offline.
+    pub fn refresh_topology(&mut self) -> Result<(), String> {
+        let previous = self.cpu.take().ok_or("CPU subsystem not enabled")?;
+
+        let mut cpus = match gather_cpus(&self.driver, &self.msr_restore, &self.platform_devices) {
+            Ok(cpus) => cpus,
+            Err(e) => {
+                self.cpu = Some(previous);
+                return Err(e);
+            }
+        };
+        merge_offline(&mut cpus, previous);
+
+        self.cpu = Some(cpus);
+        Ok(())
+    }
+
+    /// Machine-check records logged in the MCA banks since the previous call.
+    /// The first call reports every valid record already present.
+    pub fn poll_machine_checks(&mut self) -> Result<Vec<MachineCheckEvent>, String> {
+        let cpus = self.cpu.as_ref().ok_or("CPU subsystem not enabled")?;
+
+        // The MCA banks of a guest are emulated or empty
+        if let Some(hypervisor) = cpus.iter().find_map(|cpu| cpu.hypervisor.as_ref()) {
+            return Err(format!("Machine checks not supported under {}", hypervisor));
+        }
+
+        self.machine_checks.poll(&self.driver, cpus)
+    }
+
+    /// Enumerate the PCI devices again to pick up new link and error state
+    pub fn refresh_pci(&mut self) -> Result<(), String> {
+        if self.pci.is_none() {
+            return Err("PCI subsystem not enabled".into());
+        }
+
+        self.pci = Some(gather_pci_devices(&self.driver, self.pci_ids.as_ref())?);
+        Ok(())
+    }
+
+    /// The SMBus controller, which gives access to SPD and other board sensors
+    pub fn smbus_controller(&self) -> Result<Option<&PciDevice>, String> {
+        let devices = self.pci.as_ref().ok_or("PCI subsystem not enabled")?;
+
+        Ok(smbus_controller(devices))
+    }
+
+    /// PCI Express devices whose link trained below its maximum speed or width
+    pub fn degraded_pcie_links(&self) -> Result<Vec<&PciDevice>, String> {
+        let devices = self.pci.as_ref().ok_or("PCI subsystem not enabled")?;
+
+        Ok(devices.iter().filter(|d| d.is_link_degraded()).collect())
+    }
+
+    /// Write an allowlisted MSR on a logical processor
+    pub fn wrmsr_tx(&self, index: u32, value: u64, affinity: &GroupAffinity) -> Result<(), String> {
+        self.driver.wrmsr_tx(index, value, affinity)
+    }
+
+    /// Write an allowlisted MSR and keep its original value. It is written back
+    /// when the guard is dropped or when the system is closed, whichever is first.
+    pub fn wrmsr_guarded(
+        &self,
+        index: u32,
+        value: u64,
+        affinity: &GroupAffinity,
+    ) -> Result<MsrGuard, String> {
+        MsrGuard::write(&self.driver, &self.msr_restore, index, value, affinity)
+    }
+
+    /// Take manual control of a fan output, e.g. one of
+    /// [Motherboard::pwm_outputs]. Its original mode and duty are put back when
+    /// the control is dropped, when the system is closed or on a panic.
+    pub fn control_fan(
+        &self,
+        output: Arc<dyn PwmOutput>,
+        limits: FanLimits,
+    ) -> Result<FanControl, String> {
+        FanControl::take(output, &self.fan_restore, limits)
+    }
+
+    /// Reader for a named temperature: `cpu` or `cpu.<package id>` for a
+    /// package, `board.<label>` for a motherboard temperature
+    pub fn temperature_source(&self, name: &str) -> Result<TemperatureSource, String> {
+        if let Some(label) = name.strip_prefix("board.") {
+            let motherboard = self
+                .motherboard
+                .as_ref()
+                .ok_or("Motherboard subsystem not enabled")?;
+
+            return Ok(Box::new(motherboard.temperature_source(label)?));
+        }
+
+        let cpus = self.cpu.as_ref().ok_or("CPU subsystem not enabled")?;
+        let cpu = match name.strip_prefix("cpu") {
+            Some("") => cpus.first(),
+            Some(id) => id
+                .strip_prefix('.')
+                .and_then(|id| id.parse::<u32>().ok())
+                .and_then(|id| cpus.iter().find(|cpu| cpu.package_id == id)),
+            None => None,
+        }
+        .ok_or_else(|| format!("Unknown temperature source {}", name))?;
+
+        Ok(Box::new(cpu.package_temp_source()))
+    }
+
+    /// Take control of the fans of a configuration and run its curves on a
+    /// background thread. The fans are restored when the controller stops.
+    pub fn start_fan_curves(&self, config: &FanCurveConfig) -> Result<FanCurveController, String> {
+        let motherboard = self
+            .motherboard
+            .as_ref()
+            .ok_or("Motherboard subsystem not enabled")?;
+        let outputs = motherboard.pwm_outputs();
+
+        let limits = FanLimits::default();
+        let mut curves: Vec<BoundCurve> = Vec::new();
+        for curve in &config.curves {
+            let output = outputs
+                .iter()
+                .find(|output| output.label().eq_ignore_ascii_case(&curve.output))
+                .ok_or_else(|| format!("Curve {}: no PWM output {}", curve.name, curve.output))?;
+
+            // A second control would record the first one's manual state
+            if curves.iter().any(|c| c.control.label() == output.label()) {
+                return Err(format!("PWM output {} is used by two curves", curve.output));
+            }
+
+            curves.push(BoundCurve {
+                name: curve.name.clone(),
+                source: self
+                    .temperature_source(&curve.source)
+                    .map_err(|e| format!("Curve {}: {}", curve.name, e))?,
+                curve: curve.curve.clone(),
+                control: self.control_fan(
+                    output.clone(),
+                    FanLimits {
+                        min_duty: curve.min_duty,
+                        ..limits
+                    },
+                )?,
+            });
+        }
+
+        FanCurveController::start(curves, config.interval)
     }
 
     /// Explicit close
     pub fn close(self) -> Result<(), String> {
+        // Undo fan control and guarded MSR writes while the driver is still open
+        let fans = self.fan_restore.restore_all();
+        let restored = self.msr_restore.restore_all(&self.driver).and(fans);
+
         // Force close/uninstall through RefCell
         self.driver.close()?;
         self.driver.uninstall()?;
 
         println!("uninstalled (forced)");
-        Ok(())
+        restored
     }
 }
 
@@ -36,6 +252,9 @@ impl System {
 #[derive(Default)]
 pub struct SystemBuilder {
     enable_cpu: bool,
+    enable_pci: bool,
+    pci_ids: Option<PathBuf>,
+    enable_motherboard: bool,
     // future: enable_gpu, enable_ram, etc.
 }
 
@@ -45,33 +264,32 @@ impl SystemBuilder {
         self
     }
 
-    pub fn build(self) -> Result<System, String> {
-        // Select the driver binary based on architecture
-        let driver_bin: &[u8] = if cfg!(target_arch = "x86_64") {
-            include_bytes!("../../resources/WinRing0x64.sys")
-        } else {
-            include_bytes!("../../resources/WinRing0.sys")
-        };
+    pub fn pci(mut self) -> Self {
+        self.enable_pci = true;
+        self
+    }
 
-        // Create driver
-        let mut driver = DriverBuilder::new()
-            .set_device_description("Hw Monitor Driver")
-            .set_device_id("WinRing0_1_2_0")
-            .set_driver_bin(driver_bin.to_vec())
-            .build()?;
-        // driver.close()?;
-        // driver.uninstall()?;
+    /// Resolve PCI names from this `pci.ids` file instead of the one installed
+    /// with the OS. Windows does not ship one, so names need this there.
+    pub fn pci_ids(mut self, path: impl Into<PathBuf>) -> Self {
+        self.pci_ids = Some(path.into());
+        self
+    }
 
-        // return Err("t".into());
+    pub fn motherboard(mut self) -> Self {
+        self.enable_motherboard = true;
+        self
+    }
 
-        // Install
-        driver.install()?;
+    pub fn build(self) -> Result<System, String> {
+        // Load the names before the driver is opened, a bad path fails early
+        let pci_ids = match (&self.pci_ids, self.enable_pci) {
+            (_, false) => None,
+            (Some(path), true) => Some(PciIds::load(path)?),
+            (None, true) => PciIds::system(),
+        };
 
-        // Open driver
-        if let Err(e) = driver.open() {
-            let _ = driver.uninstall();
-            return Err(format!("Failed to open driver: {}", e));
-        }
+        let driver = open_driver()?;
 
         // Wrap in Rc after successful open
         let driver_rc = Arc::new(driver);
@@ -102,8 +320,71 @@ impl SystemBuilder {
         }
 
         // Initialize subsystems
-        let cpu = init_subsystem(&driver_rc, self.enable_cpu, |drv| gather_cpus(drv))?;
+        let msr_restore = Arc::new(MsrRestoreLog::new());
+        let pci = init_subsystem(&driver_rc, self.enable_pci, |drv| {
+            gather_pci_devices(drv, pci_ids.as_ref())
+        })?;
+        // The CPU backends reuse that scan, or make their own without PCI
+        let platform_devices = match &pci {
+            Some(devices) if self.enable_cpu => devices.clone(),
+            None if self.enable_cpu => enumerate(driver_rc.as_ref(), None),
+            _ => Vec::new(),
+        };
+        let cpu = init_subsystem(&driver_rc, self.enable_cpu, |drv| {
+            gather_cpus(drv, &msr_restore, &platform_devices)
+        })?;
+        let motherboard = init_subsystem(&driver_rc, self.enable_motherboard, gather_motherboard)?;
 
-        Ok(System::new(driver_rc, cpu))
+        Ok(System::new(
+            driver_rc,
+            cpu,
+            pci,
+            motherboard,
+            pci_ids,
+            platform_devices,
+            msr_restore,
+        ))
     }
 }
+
+// Install and open the bundled WinRing0 driver
+#[cfg(windows)]
+fn open_driver() -> Result<KernelDriver, String> {
+    // Select the driver binary based on architecture
+    let driver_bin: &[u8] = if cfg!(target_arch = "x86_64") {
+        include_bytes!("../../resources/WinRing0x64.sys")
+    } else {
+        include_bytes!("../../resources/WinRing0.sys")
+    };
+
+    // Create driver
+    let mut driver = DriverBuilder::new()
+        .set_device_description("Hw Monitor Driver")
+        .set_device_id("WinRing0_1_2_0")
+        .set_driver_bin(driver_bin.to_vec())
+        .build()?;
+    // driver.close()?;
+    // driver.uninstall()?;
+
+    // return Err("t".into());
+
+    // Install
+    driver.install()?;
+
+    // Open driver
+    if let Err(e) = driver.open() {
+        let _ = driver.uninstall();
+        return Err(format!("Failed to open driver: {}", e));
+    }
+
+    Ok(driver)
+}
+
+// Use the kernel's msr driver, nothing to install
+#[cfg(target_os = "linux")]
+fn open_driver() -> Result<KernelDriver, String> {
+    let mut driver = KernelDriver::new();
+    driver.open()?;
+
+    Ok(driver)
+}